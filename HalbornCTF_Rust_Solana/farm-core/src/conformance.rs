@@ -0,0 +1,258 @@
+//! fee payment scenarios both farm programs have to pass with the same result
+//! each program implements `FarmProgram` in its tests and runs every scenario against its
+//! own processor, so a check or an error code that drifts in one of them fails its suite
+
+use {
+    crate::{error::FarmError, pda::find_authority},
+    solana_program::{
+        account_info::AccountInfo,
+        bpf_loader_upgradeable::{self, UpgradeableLoaderState},
+        entrypoint::ProgramResult,
+        instruction::{Instruction, InstructionError},
+        program_pack::Pack,
+        pubkey::Pubkey,
+    },
+    solana_program_test::{processor, BanksClientError, ProgramTest, ProgramTestContext},
+    solana_sdk::{
+        account::Account,
+        signature::{keypair_from_seed, Keypair, Signer},
+        transaction::{Transaction, TransactionError},
+    },
+    spl_token::state::{Account as TokenAccount, AccountState, Mint},
+};
+
+/// accounts of a `PayFarmFee` instruction
+pub struct PayFee {
+    pub farm_id: Pubkey,
+    pub authority: Pubkey,
+    pub creator: Pubkey,
+    pub creator_token_account: Pubkey,
+    pub fee_vault: Pubkey,
+    pub token_program_id: Pubkey,
+    pub amount: u64,
+}
+
+/// what the scenarios need to know about a farm program
+pub trait FarmProgram {
+    /// fee a farm of the scenarios pays in the scenario mint
+    const FEE: u64;
+
+    /// the program entrypoint, run natively
+    fn process_instruction(program_id: &Pubkey, accounts: &[AccountInfo], instruction_data: &[u8]) -> ProgramResult;
+
+    /// data of a farm account created by `creator` and not paid yet
+    fn unpaid_farm(creator: &Pubkey, nonce: u8, fee_vault: &Pubkey) -> Vec<u8>;
+
+    /// whether the farm in `data` has been paid for
+    fn is_enabled(data: &[u8]) -> bool;
+
+    /// instructions of the program admin `admin` before a fee in `mint` can be paid, signed by
+    /// the payer and `upgrade_authority`
+    fn setup(program_id: &Pubkey, admin: &Pubkey, upgrade_authority: &Pubkey, mint: &Pubkey) -> Vec<Instruction>;
+
+    fn pay_fee(program_id: &Pubkey, accounts: &PayFee) -> Instruction;
+
+    /// custom error code the program returns for `error`
+    fn error_code(error: FarmError) -> u32 {
+        error as u32
+    }
+
+    /// error of a fee payment signed by someone who isn't the creator, the second generation
+    /// rejects anyone without a role on the farm before looking at the creator
+    fn other_creator_error() -> FarmError {
+        FarmError::WrongCreator
+    }
+
+    /// program test with the program under `program_id`, the processor runs natively unless
+    /// overridden, e.g. to load a `cargo build-sbf` artifact
+    fn program_test(program_id: Pubkey) -> ProgramTest {
+        ProgramTest::new("ctf_solana_farm", program_id, processor!(Self::process_instruction))
+    }
+}
+
+/// a started program test with one unpaid farm
+pub struct Scenario {
+    pub context: ProgramTestContext,
+    pub program_id: Pubkey,
+    pub farm_id: Pubkey,
+    pub authority: Pubkey,
+    pub creator: Keypair,
+    pub creator_token_account: Pubkey,
+    pub fee_vault: Pubkey,
+}
+
+/// upgrade authority of the program in the ProgramData account `program_data_account` makes,
+/// the same key in every test so the suites can sign with it
+pub fn upgrade_authority() -> Keypair {
+    keypair_from_seed(b"farm program test upgrade authority").unwrap()
+}
+
+/// address of the ProgramData account of `program_id` under the upgradeable loader
+pub fn program_data_address(program_id: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[program_id.as_ref()], &bpf_loader_upgradeable::id()).0
+}
+
+/// ProgramData account naming `upgrade_authority`, what the upgradeable loader leaves behind
+/// a deploy, without the program bytes the programs never read
+pub fn program_data_account(upgrade_authority: &Pubkey) -> Account {
+    let state = UpgradeableLoaderState::ProgramData { slot: 0, upgrade_authority_address: Some(*upgrade_authority) };
+    Account::new_data(1_000_000_000, &state, &bpf_loader_upgradeable::id()).unwrap()
+}
+
+/// what the creator holds before paying, enough for two fees
+const CREATOR_BALANCE_FEES: u64 = 2;
+
+fn token_program_account(data: Vec<u8>) -> Account {
+    Account { lamports: 1_000_000_000, data, owner: spl_token::id(), ..Account::default() }
+}
+
+fn mint_account() -> Account {
+    let mut data = vec![0; Mint::LEN];
+    Mint { supply: u64::MAX, decimals: 6, is_initialized: true, ..Mint::default() }.pack_into_slice(&mut data);
+    token_program_account(data)
+}
+
+fn token_account(mint: Pubkey, owner: Pubkey, amount: u64) -> Account {
+    let mut data = vec![0; TokenAccount::LEN];
+    TokenAccount { mint, owner, amount, state: AccountState::Initialized, ..TokenAccount::default() }
+        .pack_into_slice(&mut data);
+    token_program_account(data)
+}
+
+impl Scenario {
+    pub async fn start<P: FarmProgram>() -> Self {
+        let program_id = Pubkey::new_unique();
+        let mut program_test = P::program_test(program_id);
+
+        let (farm_id, mint, creator_token_account, fee_vault) =
+            (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+        let (authority, nonce) = find_authority(&farm_id, &program_id);
+        let creator = Keypair::new();
+        let upgrade_authority = upgrade_authority();
+        program_test.add_account(program_data_address(&program_id), program_data_account(&upgrade_authority.pubkey()));
+        program_test.add_account(mint, mint_account());
+        program_test.add_account(
+            creator_token_account,
+            token_account(mint, creator.pubkey(), CREATOR_BALANCE_FEES * P::FEE),
+        );
+        program_test.add_account(fee_vault, token_account(mint, authority, 0));
+        let farm = P::unpaid_farm(&creator.pubkey(), nonce, &fee_vault);
+        program_test.add_account(
+            farm_id,
+            Account { lamports: 1_000_000_000, data: farm, owner: program_id, ..Account::default() },
+        );
+
+        let context = program_test.start_with_context().await;
+        let setup = P::setup(&program_id, &context.payer.pubkey(), &upgrade_authority.pubkey(), &mint);
+        let mut scenario = Scenario { context, program_id, farm_id, authority, creator, creator_token_account, fee_vault };
+        if !setup.is_empty() {
+            scenario.process(&setup, &[&upgrade_authority]).await.unwrap();
+        }
+        scenario
+    }
+
+    /// the accounts of a valid payment of `amount`
+    pub fn pay_fee(&self, amount: u64) -> PayFee {
+        PayFee {
+            farm_id: self.farm_id,
+            authority: self.authority,
+            creator: self.creator.pubkey(),
+            creator_token_account: self.creator_token_account,
+            fee_vault: self.fee_vault,
+            token_program_id: spl_token::id(),
+            amount,
+        }
+    }
+
+    /// processes `instructions` signed by the payer and `signers`, each time with a fresh blockhash
+    pub async fn process(&mut self, instructions: &[Instruction], signers: &[&Keypair]) -> Result<(), BanksClientError> {
+        let blockhash = self.context.get_new_latest_blockhash().await.unwrap();
+        let mut transaction = Transaction::new_with_payer(instructions, Some(&self.context.payer.pubkey()));
+        transaction.sign(&[&[&self.context.payer], signers].concat(), blockhash);
+        self.context.banks_client.process_transaction(transaction).await
+    }
+
+    /// pays with `accounts`, the creator signs
+    pub async fn pay<P: FarmProgram>(&mut self, accounts: &PayFee) -> Result<(), TransactionError> {
+        let instruction = P::pay_fee(&self.program_id, accounts);
+        let blockhash = self.context.get_new_latest_blockhash().await.unwrap();
+        let transaction = Transaction::new_signed_with_payer(
+            &[instruction],
+            Some(&self.context.payer.pubkey()),
+            &[&self.context.payer, &self.creator],
+            blockhash,
+        );
+        self.context.banks_client.process_transaction(transaction).await.map_err(|err| err.unwrap())
+    }
+
+    pub async fn token_balance(&mut self, address: Pubkey) -> u64 {
+        let account = self.context.banks_client.get_account(address).await.unwrap().unwrap();
+        TokenAccount::unpack(&account.data).unwrap().amount
+    }
+
+    pub async fn is_enabled<P: FarmProgram>(&mut self) -> bool {
+        P::is_enabled(&self.context.banks_client.get_account(self.farm_id).await.unwrap().unwrap().data)
+    }
+}
+
+fn failed(error: InstructionError) -> Result<(), TransactionError> {
+    Err(TransactionError::InstructionError(0, error))
+}
+
+fn farm_error<P: FarmProgram>(error: FarmError) -> Result<(), TransactionError> {
+    failed(InstructionError::Custom(P::error_code(error)))
+}
+
+/// the fee moves from the creator to the fee vault and enables the farm
+pub async fn pays_fee<P: FarmProgram>() {
+    let mut scenario = Scenario::start::<P>().await;
+    assert_eq!(scenario.pay::<P>(&scenario.pay_fee(P::FEE)).await, Ok(()));
+    assert!(scenario.is_enabled::<P>().await);
+    assert_eq!(scenario.token_balance(scenario.creator_token_account).await, (CREATOR_BALANCE_FEES - 1) * P::FEE);
+    assert_eq!(scenario.token_balance(scenario.fee_vault).await, P::FEE);
+}
+
+/// a farm is paid for once
+pub async fn rejects_second_payment<P: FarmProgram>() {
+    let mut scenario = Scenario::start::<P>().await;
+    assert_eq!(scenario.pay::<P>(&scenario.pay_fee(P::FEE)).await, Ok(()));
+    assert_eq!(scenario.pay::<P>(&scenario.pay_fee(P::FEE)).await, farm_error::<P>(FarmError::AlreadyInUse));
+}
+
+/// nobody pays on behalf of the creator
+pub async fn rejects_missing_creator_signature<P: FarmProgram>() {
+    let mut scenario = Scenario::start::<P>().await;
+    let mut instruction = P::pay_fee(&scenario.program_id, &scenario.pay_fee(P::FEE));
+    let creator = scenario.creator.pubkey();
+    for meta in instruction.accounts.iter_mut().filter(|meta| meta.pubkey == creator) {
+        meta.is_signer = false;
+    }
+    let result = scenario.process(&[instruction], &[]).await.map_err(|err| err.unwrap());
+    assert_eq!(result, farm_error::<P>(FarmError::SignatureMissing));
+}
+
+/// only the creator of the farm enables it
+pub async fn rejects_other_creator<P: FarmProgram>() {
+    let mut scenario = Scenario::start::<P>().await;
+    scenario.creator = Keypair::new();
+    assert_eq!(scenario.pay::<P>(&scenario.pay_fee(P::FEE)).await, farm_error::<P>(P::other_creator_error()));
+}
+
+pub async fn rejects_wrong_authority<P: FarmProgram>() {
+    let mut scenario = Scenario::start::<P>().await;
+    let accounts = PayFee { authority: Pubkey::new_unique(), ..scenario.pay_fee(P::FEE) };
+    assert_eq!(scenario.pay::<P>(&accounts).await, farm_error::<P>(FarmError::InvalidProgramAddress));
+}
+
+/// another program would take the transfer and enable the farm without moving a token
+pub async fn rejects_foreign_token_program<P: FarmProgram>() {
+    let mut scenario = Scenario::start::<P>().await;
+    let accounts = PayFee { token_program_id: Pubkey::new_unique(), ..scenario.pay_fee(P::FEE) };
+    assert_eq!(scenario.pay::<P>(&accounts).await, failed(InstructionError::IncorrectProgramId));
+}
+
+pub async fn rejects_wrong_fee<P: FarmProgram>() {
+    let mut scenario = Scenario::start::<P>().await;
+    assert_eq!(scenario.pay::<P>(&scenario.pay_fee(P::FEE - 1)).await, farm_error::<P>(FarmError::InvalidFarmFee));
+    assert!(!scenario.is_enabled::<P>().await);
+}
@@ -0,0 +1,23 @@
+use {
+    crate::error::FarmError,
+    solana_program::{
+        account_info::AccountInfo, entrypoint::ProgramResult, program_error::PrintProgramError, pubkey::Pubkey,
+    },
+};
+
+/// the instruction router of a farm program
+pub type Process = fn(&Pubkey, &[AccountInfo], &[u8]) -> ProgramResult;
+
+/// runs `process` and prints the error it fails with to the validator log
+/// the program still returns the error, so the transaction reverts
+pub fn process_instruction(
+    process: Process,
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    instruction_data: &[u8],
+) -> ProgramResult {
+    process(program_id, accounts, instruction_data).map_err(|error| {
+        error.print::<FarmError>();
+        error
+    })
+}
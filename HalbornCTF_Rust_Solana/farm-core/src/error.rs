@@ -0,0 +1,209 @@
+use {
+    num_derive::FromPrimitive,
+    num_traits::FromPrimitive,
+    solana_program::{
+        decode_error::DecodeError,
+        msg,
+        program_error::{PrintProgramError, ProgramError},
+    },
+    thiserror::Error,
+};
+
+/// errors of both farm programs
+/// the codes are the ones the second generation always returned, errors only the
+/// first generation knows come after them, new errors go at the end
+/// the first generation returns its own codes, see `v1_code`
+#[derive(Clone, Debug, Eq, Error, FromPrimitive, PartialEq)]
+pub enum FarmError {
+    #[error("AlreadyInUse")]
+    AlreadyInUse,
+
+    #[error("InvalidProgramAddress")]
+    InvalidProgramAddress,
+
+    #[error("SignatureMissing")]
+    SignatureMissing,
+
+    #[error("InvalidFeeAccount")]
+    InvalidFeeAccount,
+
+    #[error("WrongPoolMint")]
+    WrongPoolMint,
+
+    #[error("This farm is not allowed yet")]
+    NotAllowed,
+
+    #[error("Wrong Farm Fee")]
+    InvalidFarmFee,
+
+    #[error("Wrong Creator")]
+    WrongCreator,
+
+    #[error("Program is paused")]
+    ProgramPaused,
+
+    #[error("Invalid Program Config")]
+    InvalidProgramConfig,
+
+    #[error("Wrong Admin")]
+    WrongAdmin,
+
+    #[error("No Pending Creator")]
+    NoPendingCreator,
+
+    #[error("Invalid Referral Share")]
+    InvalidReferralShare,
+
+    #[error("Invalid Referrer")]
+    InvalidReferrer,
+
+    #[error("Invalid Fee Schedule")]
+    InvalidFeeSchedule,
+
+    #[error("Unsupported Fee Mint")]
+    UnsupportedFeeMint,
+
+    #[error("Invalid Creator Registry")]
+    InvalidCreatorRegistry,
+
+    #[error("Creator Quota Exhausted")]
+    CreatorQuotaExhausted,
+
+    #[error("Creator Approval Expired")]
+    CreatorExpired,
+
+    #[error("Invalid Token Account")]
+    InvalidTokenAccount,
+
+    #[error("Invalid Farm Window")]
+    InvalidFarmWindow,
+
+    #[error("Farm Ended")]
+    FarmEnded,
+
+    #[error("Invalid Farm Metadata")]
+    InvalidFarmMetadata,
+
+    #[error("Farm Metadata Too Long")]
+    FarmMetadataTooLong,
+
+    #[error("Unauthorized")]
+    Unauthorized,
+
+    #[error("Farm Paused")]
+    FarmPaused,
+}
+
+/// the error at each code the first generation returned before it shared this enum,
+/// code 2 was WrongManager and stays retired, errors it didn't know follow in enum order
+const V1_CODES: [Option<FarmError>; 27] = [
+    Some(FarmError::AlreadyInUse),
+    Some(FarmError::InvalidProgramAddress),
+    None,
+    Some(FarmError::SignatureMissing),
+    Some(FarmError::InvalidFeeAccount),
+    Some(FarmError::WrongPoolMint),
+    Some(FarmError::NotAllowed),
+    Some(FarmError::InvalidFarmFee),
+    Some(FarmError::WrongCreator),
+    Some(FarmError::NoPendingCreator),
+    Some(FarmError::InvalidCreatorRegistry),
+    Some(FarmError::WrongAdmin),
+    Some(FarmError::CreatorQuotaExhausted),
+    Some(FarmError::CreatorExpired),
+    Some(FarmError::InvalidTokenAccount),
+    Some(FarmError::ProgramPaused),
+    Some(FarmError::InvalidProgramConfig),
+    Some(FarmError::InvalidReferralShare),
+    Some(FarmError::InvalidReferrer),
+    Some(FarmError::InvalidFeeSchedule),
+    Some(FarmError::UnsupportedFeeMint),
+    Some(FarmError::InvalidFarmWindow),
+    Some(FarmError::FarmEnded),
+    Some(FarmError::InvalidFarmMetadata),
+    Some(FarmError::FarmMetadataTooLong),
+    Some(FarmError::Unauthorized),
+    Some(FarmError::FarmPaused),
+];
+
+impl FarmError {
+    /// code the first generation program returns for this error
+    pub fn v1_code(&self) -> u32 {
+        V1_CODES.iter().position(|error| error.as_ref() == Some(self)).unwrap() as u32
+    }
+
+    /// error of a code the first generation program returned
+    pub fn from_v1_code(code: u32) -> Option<FarmError> {
+        V1_CODES.get(code as usize).cloned().flatten()
+    }
+}
+
+impl From<FarmError> for ProgramError {
+    fn from(e: FarmError) -> Self {
+        ProgramError::Custom(e as u32)
+    }
+}
+
+impl<T> DecodeError<T> for FarmError {
+    fn type_of() -> &'static str {
+        "Farm Error"
+    }
+}
+
+impl PrintProgramError for FarmError {
+    fn print<E>(&self)
+    where
+        E: 'static + std::error::Error + DecodeError<E> + PrintProgramError + FromPrimitive,
+    {
+        match self {
+            FarmError::AlreadyInUse => msg!("Error: account already in use"),
+            FarmError::InvalidProgramAddress => msg!("Error: the program address provided doesn't match the value generated by the program"),
+            FarmError::SignatureMissing => msg!("Error: signature missing"),
+            FarmError::InvalidFeeAccount => msg!("Error: fee vault mismatch"),
+            FarmError::WrongPoolMint => msg!("Error: pool mint incorrect"),
+            FarmError::NotAllowed => msg!("Error: farm not allowed"),
+            FarmError::InvalidFarmFee => msg!("Error: farm fee incorrect"),
+            FarmError::WrongCreator => msg!("Error: creator mismatch or creator not approved"),
+            FarmError::ProgramPaused => msg!("Error: program is paused"),
+            FarmError::InvalidProgramConfig => msg!("Error: program config mismatch"),
+            FarmError::WrongAdmin => msg!("Error: admin mismatch"),
+            FarmError::NoPendingCreator => msg!("Error: no creator transfer pending"),
+            FarmError::InvalidReferralShare => msg!("Error: referral share above 100%"),
+            FarmError::InvalidReferrer => msg!("Error: referrer can't be the creator token account or the fee vault"),
+            FarmError::InvalidFeeSchedule => msg!("Error: fee schedule mismatch"),
+            FarmError::UnsupportedFeeMint => msg!("Error: fee can't be paid in this mint"),
+            FarmError::InvalidCreatorRegistry => msg!("Error: creator registry mismatch"),
+            FarmError::CreatorQuotaExhausted => msg!("Error: creator used up its farm quota"),
+            FarmError::CreatorExpired => msg!("Error: creator approval expired"),
+            FarmError::InvalidTokenAccount => msg!("Error: farm token accounts have to be owned by the farm authority"),
+            FarmError::InvalidFarmWindow => msg!("Error: farm has to start before it ends"),
+            FarmError::FarmEnded => msg!("Error: farm end is in the past"),
+            FarmError::InvalidFarmMetadata => msg!("Error: farm metadata mismatch"),
+            FarmError::FarmMetadataTooLong => msg!("Error: farm metadata field above its max length"),
+            FarmError::Unauthorized => msg!("Error: signer has no role on the farm that allows this instruction"),
+            FarmError::FarmPaused => msg!("Error: farm is paused"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_v1_codes() {
+        let errors: Vec<FarmError> = (0..).map_while(FarmError::from_u32).collect();
+        assert_eq!(errors.len(), V1_CODES.iter().flatten().count());
+        for error in errors {
+            assert_eq!(FarmError::from_v1_code(error.v1_code()), Some(error));
+        }
+        // the codes the first generation returned before the errors were shared
+        assert_eq!(FarmError::AlreadyInUse.v1_code(), 0);
+        assert_eq!(FarmError::from_v1_code(2), None);
+        assert_eq!(FarmError::SignatureMissing.v1_code(), 3);
+        assert_eq!(FarmError::WrongCreator.v1_code(), 8);
+        assert_eq!(FarmError::NoPendingCreator.v1_code(), 9);
+        assert_eq!(FarmError::InvalidTokenAccount.v1_code(), 14);
+        assert_eq!(FarmError::from_v1_code(27), None);
+    }
+}
@@ -0,0 +1,12 @@
+//! code shared by both generations of the farm program: the error codes, the entrypoint glue,
+//! the farm authority PDA, the token transfer CPI and the account checks
+
+pub mod entrypoint;
+pub mod error;
+pub mod pda;
+pub mod token;
+pub mod validation;
+#[cfg(feature = "conformance")]
+pub mod conformance;
+
+pub use error::FarmError;
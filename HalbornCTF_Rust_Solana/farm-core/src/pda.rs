@@ -0,0 +1,12 @@
+use {crate::error::FarmError, solana_program::pubkey::Pubkey};
+
+/// address of the authority PDA of `farm_id` for `nonce`, it owns the farm token accounts
+pub fn authority_id(program_id: &Pubkey, farm_id: &Pubkey, nonce: u8) -> Result<Pubkey, FarmError> {
+    Pubkey::create_program_address(&[&farm_id.to_bytes()[..32], &[nonce]], program_id)
+        .or(Err(FarmError::InvalidProgramAddress))
+}
+
+/// address and nonce of the authority PDA of `farm_id`
+pub fn find_authority(farm_id: &Pubkey, program_id: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[&farm_id.to_bytes()[..32]], program_id)
+}
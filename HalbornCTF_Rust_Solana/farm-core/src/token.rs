@@ -0,0 +1,20 @@
+use solana_program::{account_info::AccountInfo, program::invoke_signed, program_error::ProgramError, pubkey::Pubkey};
+
+/// transfers `amount` from `source` to `destination`
+/// the farm authority PDA signs with the seeds of `farm_id`, any other `authority` has to sign the transaction
+/// fails with IncorrectProgramId unless `token_program` is the spl token program
+pub fn token_transfer<'a>(
+    farm_id: &Pubkey,
+    token_program: AccountInfo<'a>,
+    source: AccountInfo<'a>,
+    destination: AccountInfo<'a>,
+    authority: AccountInfo<'a>,
+    nonce: u8,
+    amount: u64,
+) -> Result<(), ProgramError> {
+    let farm_bytes = farm_id.to_bytes();
+    let authority_signature_seeds = [&farm_bytes[..32], &[nonce]];
+    let signers = &[&authority_signature_seeds[..]];
+    let ix = spl_token::instruction::transfer(token_program.key, source.key, destination.key, authority.key, &[], amount)?;
+    invoke_signed(&ix, &[source, destination, authority, token_program], signers)
+}
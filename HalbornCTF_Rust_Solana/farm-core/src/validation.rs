@@ -0,0 +1,197 @@
+use {
+    crate::{error::FarmError, pda::authority_id},
+    solana_program::{
+        account_info::AccountInfo, bpf_loader_upgradeable::{self, UpgradeableLoaderState},
+        entrypoint::ProgramResult, program_error::ProgramError, program_pack::Pack, program_utils::limited_deserialize,
+        pubkey::Pubkey,
+    },
+    spl_token::state::Account as TokenAccount,
+};
+
+/// `account` has to sign the transaction
+pub fn check_signer(account: &AccountInfo) -> ProgramResult {
+    if !account.is_signer {
+        return Err(FarmError::SignatureMissing.into());
+    }
+    Ok(())
+}
+
+/// `account` has to be owned by the farm program, a farm in any other account could be forged
+pub fn check_program_account(account: &AccountInfo, program_id: &Pubkey) -> ProgramResult {
+    if account.owner != program_id {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+    Ok(())
+}
+
+/// any program other than spl token would take a token transfer without moving a token
+pub fn check_token_program(token_program: &AccountInfo) -> ProgramResult {
+    if *token_program.key != spl_token::id() {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+    Ok(())
+}
+
+/// `authority` has to be the authority PDA of `farm_id` for `nonce`
+pub fn check_authority(program_id: &Pubkey, farm_id: &Pubkey, nonce: u8, authority: &AccountInfo) -> ProgramResult {
+    if *authority.key != authority_id(program_id, farm_id, nonce)? {
+        return Err(FarmError::InvalidProgramAddress.into());
+    }
+    Ok(())
+}
+
+/// `token_account` has to be an spl token account of `mint` owned by `authority`
+pub fn check_token_account(token_account: &AccountInfo, authority: &Pubkey, mint: &Pubkey) -> ProgramResult {
+    if *token_account.owner != spl_token::id() {
+        return Err(FarmError::InvalidTokenAccount.into());
+    }
+    let state = TokenAccount::unpack(&token_account.data.borrow())?;
+    if state.owner != *authority {
+        return Err(FarmError::InvalidTokenAccount.into());
+    }
+    if state.mint != *mint {
+        return Err(FarmError::WrongPoolMint.into());
+    }
+    Ok(())
+}
+
+/// `program_data` has to be the ProgramData account the upgradeable loader keeps for
+/// `program_id` and `authority` has to sign as its upgrade authority, only the deployer
+/// sets up a freshly deployed program
+pub fn check_upgrade_authority(program_id: &Pubkey, program_data: &AccountInfo, authority: &AccountInfo) -> ProgramResult {
+    check_signer(authority)?;
+    let (program_data_key, _) = Pubkey::find_program_address(&[program_id.as_ref()], &bpf_loader_upgradeable::id());
+    if *program_data.key != program_data_key || *program_data.owner != bpf_loader_upgradeable::id() {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    let data = program_data.data.borrow();
+    let metadata_len = UpgradeableLoaderState::size_of_programdata_metadata();
+    let metadata = data.get(..metadata_len).ok_or(ProgramError::InvalidAccountData)?;
+    match limited_deserialize(metadata, metadata_len as u64) {
+        Ok(UpgradeableLoaderState::ProgramData { upgrade_authority_address, .. }) => {
+            // a program deployed as final has no upgrade authority and nobody to set it up
+            if upgrade_authority_address != Some(*authority.key) {
+                return Err(FarmError::WrongAdmin.into());
+            }
+            Ok(())
+        }
+        _ => Err(ProgramError::InvalidAccountData),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::pda::find_authority,
+        solana_program::clock::Epoch,
+    };
+
+    fn token_account_data(mint: Pubkey, owner: Pubkey) -> Vec<u8> {
+        let mut data = vec![0; TokenAccount::LEN];
+        TokenAccount {
+            mint,
+            owner,
+            state: spl_token::state::AccountState::Initialized,
+            ..TokenAccount::default()
+        }
+        .pack_into_slice(&mut data);
+        data
+    }
+
+    /// ProgramData metadata as the upgradeable loader serializes it
+    fn program_data(upgrade_authority: Option<Pubkey>) -> Vec<u8> {
+        let mut data = 3u32.to_le_bytes().to_vec();
+        data.extend_from_slice(&0u64.to_le_bytes());
+        match upgrade_authority {
+            Some(authority) => {
+                data.push(1);
+                data.extend_from_slice(authority.as_ref());
+            }
+            None => data.push(0),
+        }
+        // the header keeps its size without an authority
+        data.resize(UpgradeableLoaderState::size_of_programdata_metadata(), 0);
+        data
+    }
+
+    #[test]
+    fn test_check_upgrade_authority() {
+        let (program_id, upgrade_authority) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let (program_data_key, _) = Pubkey::find_program_address(&[program_id.as_ref()], &bpf_loader_upgradeable::id());
+        let check = |key: Pubkey, owner: Pubkey, data: Vec<u8>, authority: Pubkey, is_signer: bool| {
+            let (mut lamports, mut data) = (0, data);
+            let program_data = AccountInfo::new(&key, false, false, &mut lamports, &mut data, &owner, false, Epoch::default());
+            let (mut lamports, owner) = (0, Pubkey::default());
+            let authority = AccountInfo::new(&authority, is_signer, false, &mut lamports, &mut [], &owner, false, Epoch::default());
+            check_upgrade_authority(&program_id, &program_data, &authority)
+        };
+        let loader = bpf_loader_upgradeable::id();
+
+        assert_eq!(check(program_data_key, loader, program_data(Some(upgrade_authority)), upgrade_authority, true), Ok(()));
+        assert_eq!(
+            check(program_data_key, loader, program_data(Some(upgrade_authority)), upgrade_authority, false),
+            Err(FarmError::SignatureMissing.into())
+        );
+        assert_eq!(
+            check(program_data_key, loader, program_data(Some(upgrade_authority)), Pubkey::new_unique(), true),
+            Err(FarmError::WrongAdmin.into())
+        );
+        assert_eq!(
+            check(program_data_key, loader, program_data(None), upgrade_authority, true),
+            Err(FarmError::WrongAdmin.into())
+        );
+        // a lookalike account under another address or owner
+        assert_eq!(
+            check(Pubkey::new_unique(), loader, program_data(Some(upgrade_authority)), upgrade_authority, true),
+            Err(ProgramError::InvalidAccountData)
+        );
+        assert_eq!(
+            check(program_data_key, Pubkey::new_unique(), program_data(Some(upgrade_authority)), upgrade_authority, true),
+            Err(ProgramError::InvalidAccountData)
+        );
+        assert_eq!(check(program_data_key, loader, vec![3, 0, 0], upgrade_authority, true), Err(ProgramError::InvalidAccountData));
+    }
+
+    #[test]
+    fn test_check_authority() {
+        let (program_id, farm_id) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let (mut key, nonce) = find_authority(&farm_id, &program_id);
+        let (mut lamports, owner) = (0, Pubkey::default());
+        let authority = AccountInfo::new(&key, false, false, &mut lamports, &mut [], &owner, false, Epoch::default());
+        assert_eq!(check_authority(&program_id, &farm_id, nonce, &authority), Ok(()));
+
+        key = Pubkey::new_unique();
+        let mut lamports = 0;
+        let other = AccountInfo::new(&key, false, false, &mut lamports, &mut [], &owner, false, Epoch::default());
+        assert_eq!(
+            check_authority(&program_id, &farm_id, nonce, &other),
+            Err(FarmError::InvalidProgramAddress.into())
+        );
+    }
+
+    #[test]
+    fn test_check_token_account() {
+        let (key, authority, mint) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+        let token_program = spl_token::id();
+        let check = |owner: &Pubkey, data: Vec<u8>| {
+            let (mut lamports, mut data) = (0, data);
+            let account = AccountInfo::new(&key, false, true, &mut lamports, &mut data, owner, false, Epoch::default());
+            check_token_account(&account, &authority, &mint)
+        };
+
+        assert_eq!(check(&token_program, token_account_data(mint, authority)), Ok(()));
+        assert_eq!(
+            check(&Pubkey::new_unique(), token_account_data(mint, authority)),
+            Err(FarmError::InvalidTokenAccount.into())
+        );
+        assert_eq!(
+            check(&token_program, token_account_data(mint, Pubkey::new_unique())),
+            Err(FarmError::InvalidTokenAccount.into())
+        );
+        assert_eq!(
+            check(&token_program, token_account_data(Pubkey::new_unique(), authority)),
+            Err(FarmError::WrongPoolMint.into())
+        );
+    }
+}
@@ -0,0 +1,6 @@
+//! Prints the JSON IDL of the farm program.
+//! `cargo run --example idl > idl/ctf_solana_farm.json`
+
+fn main() {
+    println!("{}", serde_json::to_string_pretty(&ctf_solana_farm::idl::idl()).unwrap());
+}
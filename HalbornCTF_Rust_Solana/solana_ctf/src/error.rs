@@ -1,48 +1,3 @@
-use {
-    num_derive::FromPrimitive,
-    solana_program::{
-        decode_error::DecodeError,
-        program_error::ProgramError
-    },
-    thiserror::Error
-};
-
-#[derive(Clone, Debug, Eq, Error, FromPrimitive, PartialEq)]
-pub enum FarmError {
-    #[error("AlreadyInUse")]
-    AlreadyInUse,
-
-    #[error("InvalidProgramAddress")]
-    InvalidProgramAddress,
-
-    #[error("WrongManager")]
-    WrongManager,
-
-    #[error("SignatureMissing")]
-    SignatureMissing,
-
-    #[error("InvalidFeeAccount")]
-    InvalidFeeAccount,
-
-    #[error("WrongPoolMint")]
-    WrongPoolMint,
-    
-    #[error("This farm is not allowed yet")]
-    NotAllowed,
-
-    #[error("Wrong Farm Fee")]
-    InvalidFarmFee,
-
-    #[error("Wrong creator")]
-    WrongCreator,
-}
-impl From<FarmError> for ProgramError {
-    fn from(e: FarmError) -> Self {
-        ProgramError::Custom(e as u32)
-    }
-}
-impl<T> DecodeError<T> for FarmError {
-    fn type_of() -> &'static str {
-        "Farm Error"
-    }
-} 
\ No newline at end of file
+// the errors are shared with the second generation program, this program keeps returning
+// the codes it had before, see `FarmError::v1_code`
+pub use farm_core::error::FarmError;
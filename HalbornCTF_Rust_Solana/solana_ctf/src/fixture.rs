@@ -0,0 +1,372 @@
+//! ProgramTest fixture for the farm tests: real SPL mints and token accounts,
+//! farms at their authority PDA, an initialized creator registry and a funded creator.
+//! The clock starts at `START_TIMESTAMP`.
+
+use std::str::FromStr;
+
+use borsh::BorshSerialize;
+use farm_core::conformance::{self, FarmProgram, PayFee};
+use solana_program::account_info::AccountInfo;
+use solana_program::clock::Clock;
+use solana_program::entrypoint::ProgramResult;
+use solana_program::instruction::Instruction;
+use solana_program::program_option::COption;
+use solana_program::program_pack::Pack;
+use solana_program::rent::Rent;
+use solana_program_test::*;
+use solana_sdk::account::Account;
+use solana_sdk::borsh0_10::try_from_slice_unchecked;
+use solana_sdk::native_token::LAMPORTS_PER_SOL;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signer};
+use solana_sdk::transaction::{Transaction, TransactionError};
+use spl_token::state::{Account as TokenAccount, AccountState, Mint};
+
+use crate::constant::{FARM_FEE, FEE_OWNER, USDC_MINT_ADDRESS};
+use crate::error::FarmError;
+use crate::instruction::{ix_add_creator, ix_create_farm, ix_initialize_registry, ix_pay_create_fee};
+use crate::process_instruction;
+use crate::state::{Farm, FarmAccount};
+
+/// USDC the creator starts with
+pub const CREATOR_USDC_BALANCE: u64 = 1_000_000;
+pub const START_TIMESTAMP: u64 = 1711446463;
+pub const END_TIMESTAMP: u64 = 1711792063;
+
+/// Builds the accounts of a farm test. By default there is one farm account allocated
+/// for the program but not created yet, and the creator is approved for one farm.
+pub struct FarmFixture {
+    program_id: Pubkey,
+    creator: Keypair,
+    farm_count: usize,
+    created: bool,
+    registry: bool,
+    creator_approval: Option<(u64, i64)>,
+}
+
+/// Accounts of one farm of the fixture.
+pub struct TestFarm {
+    pub id: Pubkey,
+    pub authority: Pubkey,
+    pub nonce: u8,
+    pub pool_lp_token_account: Pubkey,
+    pub pool_reward_token_account: Pubkey,
+}
+
+/// Handles to everything a `FarmFixture` set up. The payer is the registry admin.
+pub struct FarmContext {
+    context: ProgramTestContext,
+    pub banks_client: BanksClient,
+    pub payer: Keypair,
+    pub program_id: Pubkey,
+    pub creator: Keypair,
+    pub farms: Vec<TestFarm>,
+    pub pool_mint: Pubkey,
+    pub reward_mint: Pubkey,
+    pub usdc_mint: Pubkey,
+    pub creator_usdc_token_account: Pubkey,
+    pub fee_owner_token_account: Pubkey,
+}
+
+impl Default for FarmFixture {
+    fn default() -> Self {
+        FarmFixture {
+            program_id: Pubkey::new_unique(),
+            creator: Keypair::new(),
+            farm_count: 1,
+            created: false,
+            registry: true,
+            creator_approval: Some((1, i64::MAX)),
+        }
+    }
+}
+
+impl FarmFixture {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn farms(mut self, farm_count: usize) -> Self {
+        self.farm_count = farm_count;
+        self
+    }
+
+    /// writes the farms as already created by the creator
+    pub fn created(mut self) -> Self {
+        self.created = true;
+        self
+    }
+
+    /// leaves the creator registry uninitialized, and the creator unapproved
+    pub fn without_registry(mut self) -> Self {
+        self.registry = false;
+        self.creator_approval = None;
+        self
+    }
+
+    /// registry entry of the creator, `None` leaves the creator unapproved
+    pub fn creator_approval(mut self, creator_approval: Option<(u64, i64)>) -> Self {
+        self.creator_approval = creator_approval;
+        self
+    }
+
+    pub async fn start(self) -> FarmContext {
+        let mut program_test = ProgramTest::new(
+            "ctf-solana-farm",
+            self.program_id,
+            processor!(process_instruction),
+        );
+        program_test.add_account(
+            conformance::program_data_address(&self.program_id),
+            conformance::program_data_account(&conformance::upgrade_authority().pubkey()),
+        );
+        let pool_mint = add_mint(&mut program_test);
+        let reward_mint = add_mint(&mut program_test);
+        let usdc_mint = Pubkey::from_str(USDC_MINT_ADDRESS).unwrap();
+        program_test.add_account(usdc_mint, mint_account());
+        let creator_usdc_token_account = add_token_account(&mut program_test, &usdc_mint, &self.creator.pubkey(), CREATOR_USDC_BALANCE);
+        let fee_owner_token_account = add_token_account(&mut program_test, &usdc_mint, &Pubkey::from_str(FEE_OWNER).unwrap(), 0);
+        program_test.add_account(self.creator.pubkey(), Account {
+            lamports: LAMPORTS_PER_SOL,
+            ..Account::default()
+        });
+
+        let farms: Vec<TestFarm> = (0..self.farm_count)
+            .map(|_| {
+                let id = Pubkey::new_unique();
+                let (authority, nonce) = Pubkey::find_program_address(&[&id.to_bytes()[..32]], &self.program_id);
+                let farm = TestFarm {
+                    id,
+                    authority,
+                    nonce,
+                    pool_lp_token_account: add_token_account(&mut program_test, &pool_mint, &authority, 0),
+                    pool_reward_token_account: add_token_account(&mut program_test, &reward_mint, &authority, 0),
+                };
+                let data = if self.created {
+                    Farm {
+                        nonce,
+                        pool_lp_token_account: farm.pool_lp_token_account,
+                        pool_reward_token_account: farm.pool_reward_token_account,
+                        pool_mint_address: pool_mint,
+                        reward_mint_address: reward_mint,
+                        token_program_id: spl_token::id(),
+                        owner: self.creator.pubkey(),
+                        fee_owner: Pubkey::from_str(FEE_OWNER).unwrap(),
+                        last_timestamp: START_TIMESTAMP,
+                        start_timestamp: START_TIMESTAMP,
+                        end_timestamp: END_TIMESTAMP,
+                        ..Farm::default()
+                    }
+                    .try_to_vec()
+                    .unwrap()
+                } else {
+                    vec![0; FarmAccount::LEN]
+                };
+                program_test.add_account(id, Account {
+                    lamports: Rent::default().minimum_balance(data.len()),
+                    data,
+                    owner: self.program_id,
+                    ..Account::default()
+                });
+                farm
+            })
+            .collect();
+
+        let context = program_test.start_with_context().await;
+        let mut ctx = FarmContext {
+            banks_client: context.banks_client.clone(),
+            payer: Keypair::from_bytes(&context.payer.to_bytes()).unwrap(),
+            context,
+            program_id: self.program_id,
+            creator: self.creator,
+            farms,
+            pool_mint,
+            reward_mint,
+            usdc_mint,
+            creator_usdc_token_account,
+            fee_owner_token_account,
+        };
+        ctx.warp_to(START_TIMESTAMP as i64).await;
+        if self.registry {
+            ctx.initialize_registry().await.unwrap();
+        }
+        if let Some((quota, expiry)) = self.creator_approval {
+            ctx.approve_creator(quota, expiry).await.unwrap();
+        }
+        ctx
+    }
+}
+
+fn mint_account() -> Account {
+    let mut data = vec![0; Mint::LEN];
+    Mint::pack(Mint {
+        mint_authority: COption::None,
+        supply: u64::MAX,
+        decimals: 6,
+        is_initialized: true,
+        freeze_authority: COption::None,
+    }, &mut data).unwrap();
+    token_program_account(data)
+}
+
+fn add_mint(program_test: &mut ProgramTest) -> Pubkey {
+    let key = Pubkey::new_unique();
+    program_test.add_account(key, mint_account());
+    key
+}
+
+fn add_token_account(program_test: &mut ProgramTest, mint: &Pubkey, owner: &Pubkey, amount: u64) -> Pubkey {
+    let key = Pubkey::new_unique();
+    let mut data = vec![0; TokenAccount::LEN];
+    TokenAccount::pack(TokenAccount {
+        mint: *mint,
+        owner: *owner,
+        amount,
+        state: AccountState::Initialized,
+        ..TokenAccount::default()
+    }, &mut data).unwrap();
+    program_test.add_account(key, token_program_account(data));
+    key
+}
+
+fn token_program_account(data: Vec<u8>) -> Account {
+    Account {
+        lamports: Rent::default().minimum_balance(data.len()),
+        data,
+        owner: spl_token::id(),
+        ..Account::default()
+    }
+}
+
+impl FarmContext {
+    /// the first farm of the fixture
+    pub fn farm(&self) -> &TestFarm {
+        &self.farms[0]
+    }
+
+    /// moves the clock to `unix_timestamp`
+    pub async fn warp_to(&mut self, unix_timestamp: i64) {
+        let clock = Clock { unix_timestamp, ..self.banks_client.get_sysvar::<Clock>().await.unwrap() };
+        self.context.set_sysvar(&clock);
+    }
+
+    /// signs `instruction` with the payer and `signers` and processes it, each time with a
+    /// fresh blockhash so a retried instruction isn't taken for the earlier transaction
+    pub async fn process(&mut self, instruction: Instruction, signers: &[&Keypair]) -> Result<(), TransactionError> {
+        let recent_blockhash = self.context.get_new_latest_blockhash().await.unwrap();
+        let mut transaction = Transaction::new_with_payer(&[instruction], Some(&self.payer.pubkey()));
+        transaction.sign(&[&[&self.payer], signers].concat(), recent_blockhash);
+        self.banks_client.process_transaction(transaction).await.map_err(|e| e.unwrap())
+    }
+
+    /// processes `instruction` signed by the creator
+    pub async fn process_as_creator(&mut self, instruction: Instruction) -> Result<(), TransactionError> {
+        let creator = Keypair::from_bytes(&self.creator.to_bytes()).unwrap();
+        self.process(instruction, &[&creator]).await
+    }
+
+    /// creates the creator registry with the payer as admin, signed by the program upgrade authority
+    pub async fn initialize_registry(&mut self) -> Result<(), TransactionError> {
+        let upgrade_authority = conformance::upgrade_authority();
+        let instruction = ix_initialize_registry(&self.payer.pubkey(), &upgrade_authority.pubkey(), &self.program_id);
+        self.process(instruction, &[&upgrade_authority]).await
+    }
+
+    pub async fn approve_creator(&mut self, quota: u64, expiry: i64) -> Result<(), TransactionError> {
+        let instruction = ix_add_creator(&self.payer.pubkey(), &self.creator.pubkey(), &self.program_id, quota, expiry);
+        self.process(instruction, &[]).await
+    }
+
+    /// Create of the farm at `index` by the creator
+    pub fn create_farm(&self, index: usize) -> Instruction {
+        self.create_farm_between(index, START_TIMESTAMP, END_TIMESTAMP)
+    }
+
+    /// Create of the farm at `index` running from `start_timestamp` to `end_timestamp`
+    pub fn create_farm_between(&self, index: usize, start_timestamp: u64, end_timestamp: u64) -> Instruction {
+        let farm = &self.farms[index];
+        ix_create_farm(
+            &farm.id,
+            &farm.authority,
+            &self.creator.pubkey(),
+            &farm.pool_lp_token_account,
+            &farm.pool_reward_token_account,
+            &self.pool_mint,
+            &self.reward_mint,
+            &Pubkey::new_unique(),
+            &spl_token::id(),
+            &self.program_id,
+            farm.nonce,
+            start_timestamp,
+            end_timestamp,
+        )
+    }
+
+    /// PayFarmFee of the first farm from the creator USDC account
+    pub fn pay_farm_fee(&self, amount: u64) -> Instruction {
+        let farm = self.farm();
+        ix_pay_create_fee(
+            &farm.id,
+            &farm.authority,
+            &self.creator.pubkey(),
+            &self.creator.pubkey(),
+            &self.creator_usdc_token_account,
+            &self.fee_owner_token_account,
+            &spl_token::id(),
+            &self.program_id,
+            amount,
+        )
+    }
+
+    pub async fn get_farm(&mut self, index: usize) -> Farm {
+        let account = self.banks_client.get_account(self.farms[index].id).await.unwrap().unwrap();
+        try_from_slice_unchecked(&account.data).unwrap()
+    }
+
+    pub async fn token_balance(&mut self, address: Pubkey) -> u64 {
+        let account = self.banks_client.get_account(address).await.unwrap().unwrap();
+        TokenAccount::unpack(&account.data).unwrap().amount
+    }
+}
+
+/// The program as the farm-core conformance scenarios see it.
+pub struct ConformingFarm;
+
+impl FarmProgram for ConformingFarm {
+    const FEE: u64 = FARM_FEE;
+
+    fn process_instruction(program_id: &Pubkey, accounts: &[AccountInfo], instruction_data: &[u8]) -> ProgramResult {
+        process_instruction(program_id, accounts, instruction_data)
+    }
+
+    fn unpaid_farm(creator: &Pubkey, nonce: u8, _fee_vault: &Pubkey) -> Vec<u8> {
+        let farm = Farm { nonce, token_program_id: spl_token::id(), owner: *creator, ..Farm::default() };
+        farm.try_to_vec().unwrap()
+    }
+
+    fn is_enabled(data: &[u8]) -> bool {
+        bytemuck::from_bytes::<FarmAccount>(&data[..FarmAccount::LEN]).is_allowed == 1
+    }
+
+    fn setup(_program_id: &Pubkey, _admin: &Pubkey, _upgrade_authority: &Pubkey, _mint: &Pubkey) -> Vec<Instruction> {
+        vec![]
+    }
+
+    fn error_code(error: FarmError) -> u32 {
+        error.v1_code()
+    }
+
+    fn pay_fee(program_id: &Pubkey, accounts: &PayFee) -> Instruction {
+        ix_pay_create_fee(
+            &accounts.farm_id,
+            &accounts.authority,
+            &accounts.creator,
+            &accounts.creator,
+            &accounts.creator_token_account,
+            &accounts.fee_vault,
+            &accounts.token_program_id,
+            program_id,
+            accounts.amount,
+        )
+    }
+}
@@ -0,0 +1,218 @@
+//! JSON IDL of the farm program, built from the borsh schemas of the instruction and
+//! account types, the account metas of the `ix_*` builders and the `FarmError` codes.
+//! `cargo run --example idl > idl/ctf_solana_farm.json` regenerates the checked-in copy.
+
+use {
+    crate::{
+        error::FarmError,
+        instruction::{
+            ix_accept_creator, ix_add_creator, ix_create_farm, ix_initialize_registry, ix_pay_create_fee,
+            ix_propose_creator, ix_remove_creator, FarmInstruction,
+        },
+        state::{CreatorEntry, CreatorRegistry, Farm, Swap},
+    },
+    borsh::{
+        schema::{Declaration, Definition, Fields},
+        BorshSchema, BorshSerialize,
+    },
+    num_traits::FromPrimitive,
+    serde_json::{json, Map, Value},
+    solana_program::{instruction::Instruction, pubkey::Pubkey},
+    std::collections::HashMap,
+};
+
+/// names of the accounts each builder passes, in order
+fn instruction_accounts() -> Vec<(&'static str, Vec<&'static str>, Instruction)> {
+    let key = Pubkey::new_unique;
+    let program_id = key();
+    vec![
+        (
+            "Create",
+            vec![
+                "farm",
+                "authority",
+                "creator",
+                "pool_lp_token_account",
+                "pool_reward_token_account",
+                "pool_mint",
+                "reward_mint",
+                "amm_id",
+                "token_program",
+                "creator_entry",
+            ],
+            ix_create_farm(
+                &key(), &key(), &key(), &key(), &key(), &key(), &key(), &key(), &key(), &program_id, 0, 0, 0,
+            ),
+        ),
+        (
+            "PayFarmFee",
+            vec![
+                "farm",
+                "authority",
+                "creator",
+                "user_transfer_authority",
+                "creator_token_account",
+                "fee_vault",
+                "token_program",
+            ],
+            ix_pay_create_fee(&key(), &key(), &key(), &key(), &key(), &key(), &key(), &program_id, 0),
+        ),
+        ("ProposeCreator", vec!["farm", "owner"], ix_propose_creator(&key(), &key(), &key(), &program_id)),
+        ("AcceptCreator", vec!["farm", "pending_owner"], ix_accept_creator(&key(), &key(), &program_id)),
+        (
+            "InitializeRegistry",
+            vec!["registry", "admin", "system_program", "program_data", "upgrade_authority"],
+            ix_initialize_registry(&key(), &key(), &program_id),
+        ),
+        (
+            "AddCreator",
+            vec!["creator_entry", "registry", "admin", "creator", "system_program"],
+            ix_add_creator(&key(), &key(), &program_id, 0, 0),
+        ),
+        (
+            "RemoveCreator",
+            vec!["creator_entry", "registry", "admin", "creator"],
+            ix_remove_creator(&key(), &key(), &program_id),
+        ),
+    ]
+}
+
+/// collects the named types the instructions and accounts refer to
+struct Types<'a> {
+    definitions: &'a HashMap<Declaration, Definition>,
+    defined: Map<String, Value>,
+}
+
+impl<'a> Types<'a> {
+    /// a primitive by name, containers inline, structs and enums by reference to `types`
+    fn type_of(&mut self, declaration: &str) -> Value {
+        if declaration == "Pubkey" {
+            return json!("pubkey");
+        }
+        match self.definitions.get(declaration) {
+            None => json!(declaration),
+            Some(Definition::Array { length, elements }) => json!({ "array": [self.type_of(elements), length] }),
+            Some(Definition::Sequence { elements }) => json!({ "vec": self.type_of(elements) }),
+            Some(Definition::Tuple { elements }) => {
+                json!({ "tuple": elements.iter().map(|element| self.type_of(element)).collect::<Vec<_>>() })
+            }
+            Some(definition) => {
+                if !self.defined.contains_key(declaration) {
+                    // placeholder first, a type may refer to itself
+                    self.defined.insert(declaration.to_string(), Value::Null);
+                    let layout = self.layout(definition);
+                    self.defined.insert(declaration.to_string(), layout);
+                }
+                json!({ "defined": declaration })
+            }
+        }
+    }
+
+    fn fields(&mut self, fields: &Fields) -> Vec<Value> {
+        match fields {
+            Fields::NamedFields(fields) => {
+                fields.iter().map(|(name, declaration)| json!({ "name": name, "type": self.type_of(declaration) })).collect()
+            }
+            Fields::UnnamedFields(fields) => fields
+                .iter()
+                .enumerate()
+                .map(|(index, declaration)| json!({ "name": index.to_string(), "type": self.type_of(declaration) }))
+                .collect(),
+            Fields::Empty => vec![],
+        }
+    }
+
+    fn layout(&mut self, definition: &Definition) -> Value {
+        match definition {
+            Definition::Struct { fields } => json!({ "kind": "struct", "fields": self.fields(fields) }),
+            Definition::Enum { variants } => {
+                let variants: Vec<Value> = variants
+                    .iter()
+                    .map(|(name, declaration)| {
+                        let fields = match self.definitions.get(declaration) {
+                            Some(Definition::Struct { fields }) => self.fields(fields),
+                            _ => vec![json!({ "name": "0", "type": self.type_of(declaration) })],
+                        };
+                        json!({ "name": name, "fields": fields })
+                    })
+                    .collect();
+                json!({ "kind": "enum", "variants": variants })
+            }
+            _ => Value::Null,
+        }
+    }
+}
+
+fn instructions(types: &mut Types) -> Vec<Value> {
+    let variants = match types.definitions.get(&FarmInstruction::declaration()) {
+        Some(Definition::Enum { variants }) => variants,
+        _ => unreachable!("FarmInstruction is an enum"),
+    };
+    let mut accounts = instruction_accounts();
+    assert_eq!(accounts.len(), variants.len(), "every instruction needs its accounts");
+    variants
+        .iter()
+        .enumerate()
+        .map(|(index, (name, variant))| {
+            let position = accounts.iter().position(|(instruction, _, _)| instruction == name);
+            let (_, names, instruction) = accounts.swap_remove(position.expect("accounts of the instruction"));
+            assert_eq!(names.len(), instruction.accounts.len(), "names of the {} accounts", name);
+            let accounts: Vec<Value> = names
+                .iter()
+                .zip(&instruction.accounts)
+                .map(|(name, meta)| json!({ "name": name, "writable": meta.is_writable, "signer": meta.is_signer }))
+                .collect();
+            let args = match types.definitions.get(variant) {
+                Some(Definition::Struct { fields }) => types.fields(fields),
+                _ => vec![],
+            };
+            json!({ "name": name, "discriminant": index, "accounts": accounts, "args": args })
+        })
+        .collect()
+}
+
+fn account<T: BorshSchema + BorshSerialize + Default>(types: &mut Types) -> Value {
+    let declaration = T::declaration();
+    let fields = match types.definitions.get(&declaration) {
+        Some(Definition::Struct { fields }) => types.fields(fields),
+        _ => vec![],
+    };
+    // every account type is fixed size, the default encodes to the account length
+    let size = T::default().try_to_vec().unwrap().len();
+    json!({ "name": declaration, "size": size, "fields": fields })
+}
+
+/// the errors with the codes this program returns
+fn errors() -> Vec<Value> {
+    let mut errors: Vec<FarmError> = (0..).map_while(FarmError::from_u32).collect();
+    errors.sort_by_key(FarmError::v1_code);
+    errors
+        .into_iter()
+        .map(|error| json!({ "code": error.v1_code(), "name": format!("{:?}", error), "msg": error.to_string() }))
+        .collect()
+}
+
+pub fn idl() -> Value {
+    let mut definitions = HashMap::new();
+    FarmInstruction::add_definitions_recursively(&mut definitions);
+    Farm::add_definitions_recursively(&mut definitions);
+    Swap::add_definitions_recursively(&mut definitions);
+    CreatorRegistry::add_definitions_recursively(&mut definitions);
+    CreatorEntry::add_definitions_recursively(&mut definitions);
+    let mut types = Types { definitions: &definitions, defined: Map::new() };
+    let instructions = instructions(&mut types);
+    let accounts = vec![
+        account::<Farm>(&mut types),
+        account::<Swap>(&mut types),
+        account::<CreatorRegistry>(&mut types),
+        account::<CreatorEntry>(&mut types),
+    ];
+    json!({
+        "name": "ctf_solana_farm",
+        "version": env!("CARGO_PKG_VERSION"),
+        "instructions": instructions,
+        "accounts": accounts,
+        "types": types.defined,
+        "errors": errors(),
+    })
+}
@@ -1,10 +1,13 @@
 #![allow(clippy::too_many_arguments)]
 
 use {
+    crate::constant::{CREATOR_ENTRY_SEED, REGISTRY_SEED},
     borsh::{BorshDeserialize, BorshSchema, BorshSerialize},
     solana_program::{
+        bpf_loader_upgradeable,
         instruction::{AccountMeta, Instruction},
         pubkey::Pubkey,
+        system_program,
     },
 };
 
@@ -26,10 +29,12 @@ pub enum FarmInstruction {
     ///   6. `[]` Reward token mint address
     ///   7. `[]` Amm Id
     ///   8. `[]` Token program id
-    ///   9. `[]` nonce
-    ///   10. `[]` Farm program id
-    ///   11.'[]' start timestamp. this reflects that the farm starts at this time
-    ///   12.'[]' end timestamp. this reflects that the farm ends at this time
+    ///   9. `[w]` Creator registry entry, one unit of its quota is consumed
+    ///   10. `[]` nonce
+    ///   11. `[]` Farm program id
+    ///   12.'[]' start timestamp. this reflects that the farm starts at this time
+    ///   13.'[]' end timestamp. this reflects that the farm ends at this time
+    ///             has to be after the start timestamp and in the future
     Create {
         #[allow(dead_code)]
         /// nonce
@@ -57,12 +62,105 @@ pub enum FarmInstruction {
     ///   7. `[]` Farm program id
     ///   8. `[]` amount
     PayFarmFee(u64),
+
+    ///   Owner proposes a new owner for the farm
+    ///   Nothing changes until the new owner accepts, proposing the default pubkey cancels
+    ///
+    ///   0. `[w]` Farm to hand over
+    ///   1. `[s]` current owner
+    ///   2. `[]` new owner
+    ProposeCreator(Pubkey),
+
+    ///   Pending owner takes over the farm
+    ///
+    ///   0. `[w]` Farm to take over
+    ///   1. `[s]` pending owner
+    AcceptCreator,
+
+    ///   Creates the creator registry, the program upgrade authority appoints the registry admin
+    ///
+    ///   0. `[w]` Creator registry
+    ///   1. `[ws]` Admin, pays for the registry account
+    ///   2. `[]` System program
+    ///   3. `[]` ProgramData account of the program
+    ///   4. `[s]` Upgrade authority of the program
+    InitializeRegistry,
+
+    ///   Admin approves a creator, or updates the quota and expiry of an approved one
+    ///
+    ///   0. `[w]` Creator registry entry
+    ///   1. `[]` Creator registry
+    ///   2. `[ws]` Admin, pays for a new entry
+    ///   3. `[]` Creator
+    ///   4. `[]` System program
+    ///   5. `[]` quota, number of farms the creator may create
+    ///   6. `[]` expiry, unix timestamp after which the approval lapses
+    AddCreator {
+        #[allow(dead_code)]
+        /// quota
+        quota: u64,
+
+        #[allow(dead_code)]
+        /// expiry
+        expiry: i64,
+    },
+
+    ///   Admin removes a creator, the entry rent goes back to the admin
+    ///
+    ///   0. `[w]` Creator registry entry
+    ///   1. `[]` Creator registry
+    ///   2. `[ws]` Admin
+    ///   3. `[]` Creator
+    RemoveCreator,
+}
+
+pub fn find_registry_address(farm_program_id: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[REGISTRY_SEED], farm_program_id)
+}
+
+pub fn find_creator_entry_address(creator: &Pubkey, farm_program_id: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[CREATOR_ENTRY_SEED, creator.as_ref()], farm_program_id)
+}
+
+pub fn ix_create_farm(
+    farm_id: &Pubkey,
+    authority: &Pubkey,
+    creator: &Pubkey,
+    pool_lp_token_account: &Pubkey,
+    pool_reward_token_account: &Pubkey,
+    pool_mint: &Pubkey,
+    reward_mint: &Pubkey,
+    amm_id: &Pubkey,
+    token_program_id: &Pubkey,
+    farm_program_id: &Pubkey,
+    nonce: u8,
+    start_timestamp: u64,
+    end_timestamp: u64,
+) -> Instruction {
+    let accounts = vec![
+        AccountMeta::new(*farm_id, false),
+        AccountMeta::new_readonly(*authority, false),
+        AccountMeta::new_readonly(*creator, true),
+        AccountMeta::new(*pool_lp_token_account, false),
+        AccountMeta::new(*pool_reward_token_account, false),
+        AccountMeta::new_readonly(*pool_mint, false),
+        AccountMeta::new_readonly(*reward_mint, false),
+        AccountMeta::new_readonly(*amm_id, false),
+        AccountMeta::new_readonly(*token_program_id, false),
+        AccountMeta::new(find_creator_entry_address(creator, farm_program_id).0, false),
+    ];
+    Instruction {
+        program_id: *farm_program_id,
+        accounts,
+        data: FarmInstruction::Create { nonce, start_timestamp, end_timestamp }.try_to_vec().unwrap(),
+    }
 }
 
 pub fn ix_pay_create_fee(
     farm_id: &Pubkey,
     authority: &Pubkey,
     creator: &Pubkey,
+    user_transfer_authority: &Pubkey,
     creator_token_account: &Pubkey,
     fee_vault: &Pubkey,
     token_program_id: &Pubkey,
@@ -73,6 +171,7 @@ pub fn ix_pay_create_fee(
         AccountMeta::new(*farm_id, false),
         AccountMeta::new_readonly(*authority, false),
         AccountMeta::new(*creator, true),
+        AccountMeta::new_readonly(*user_transfer_authority, true),
         AccountMeta::new(*creator_token_account, false),
         AccountMeta::new(*fee_vault, false),
         AccountMeta::new_readonly(*token_program_id, false),
@@ -82,4 +181,96 @@ pub fn ix_pay_create_fee(
         accounts,
         data: FarmInstruction::PayFarmFee(amount).try_to_vec().unwrap(),
     }
-}
\ No newline at end of file
+}
+
+pub fn ix_propose_creator(
+    farm_id: &Pubkey,
+    owner: &Pubkey,
+    new_owner: &Pubkey,
+    farm_program_id: &Pubkey,
+) -> Instruction {
+    let accounts = vec![
+        AccountMeta::new(*farm_id, false),
+        AccountMeta::new_readonly(*owner, true),
+    ];
+    Instruction {
+        program_id: *farm_program_id,
+        accounts,
+        data: FarmInstruction::ProposeCreator(*new_owner).try_to_vec().unwrap(),
+    }
+}
+
+pub fn ix_accept_creator(
+    farm_id: &Pubkey,
+    pending_owner: &Pubkey,
+    farm_program_id: &Pubkey,
+) -> Instruction {
+    let accounts = vec![
+        AccountMeta::new(*farm_id, false),
+        AccountMeta::new_readonly(*pending_owner, true),
+    ];
+    Instruction {
+        program_id: *farm_program_id,
+        accounts,
+        data: FarmInstruction::AcceptCreator.try_to_vec().unwrap(),
+    }
+}
+
+pub fn ix_initialize_registry(
+    admin: &Pubkey,
+    upgrade_authority: &Pubkey,
+    farm_program_id: &Pubkey,
+) -> Instruction {
+    let (program_data, _) = Pubkey::find_program_address(&[farm_program_id.as_ref()], &bpf_loader_upgradeable::id());
+    let accounts = vec![
+        AccountMeta::new(find_registry_address(farm_program_id).0, false),
+        AccountMeta::new(*admin, true),
+        AccountMeta::new_readonly(system_program::id(), false),
+        AccountMeta::new_readonly(program_data, false),
+        AccountMeta::new_readonly(*upgrade_authority, true),
+    ];
+    Instruction {
+        program_id: *farm_program_id,
+        accounts,
+        data: FarmInstruction::InitializeRegistry.try_to_vec().unwrap(),
+    }
+}
+
+pub fn ix_add_creator(
+    admin: &Pubkey,
+    creator: &Pubkey,
+    farm_program_id: &Pubkey,
+    quota: u64,
+    expiry: i64,
+) -> Instruction {
+    let accounts = vec![
+        AccountMeta::new(find_creator_entry_address(creator, farm_program_id).0, false),
+        AccountMeta::new_readonly(find_registry_address(farm_program_id).0, false),
+        AccountMeta::new(*admin, true),
+        AccountMeta::new_readonly(*creator, false),
+        AccountMeta::new_readonly(system_program::id(), false),
+    ];
+    Instruction {
+        program_id: *farm_program_id,
+        accounts,
+        data: FarmInstruction::AddCreator { quota, expiry }.try_to_vec().unwrap(),
+    }
+}
+
+pub fn ix_remove_creator(
+    admin: &Pubkey,
+    creator: &Pubkey,
+    farm_program_id: &Pubkey,
+) -> Instruction {
+    let accounts = vec![
+        AccountMeta::new(find_creator_entry_address(creator, farm_program_id).0, false),
+        AccountMeta::new_readonly(find_registry_address(farm_program_id).0, false),
+        AccountMeta::new(*admin, true),
+        AccountMeta::new_readonly(*creator, false),
+    ];
+    Instruction {
+        program_id: *farm_program_id,
+        accounts,
+        data: FarmInstruction::RemoveCreator.try_to_vec().unwrap(),
+    }
+}
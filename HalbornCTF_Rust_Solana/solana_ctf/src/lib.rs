@@ -2,15 +2,21 @@ use solana_program::{
     account_info::{ AccountInfo},
     entrypoint,
     entrypoint::ProgramResult,
-    program_error::PrintProgramError,
+    program_error::ProgramError,
     pubkey::Pubkey,
 };
+use num_traits::FromPrimitive;
+use error::FarmError;
 
 pub mod error;
 pub mod instruction;
 pub mod processor;
 pub mod state;
 pub mod constant;
+#[cfg(not(target_os = "solana"))]
+pub mod idl;
+#[cfg(test)]
+mod fixture;
 
 entrypoint!(process_instruction);
 
@@ -19,146 +25,261 @@ pub fn process_instruction(
     accounts: &[AccountInfo],
     _instruction_data: &[u8],
 ) -> ProgramResult {
-    if let Err(error) = processor::Processor::process(program_id, accounts, _instruction_data) {
-        error.print::<error::FarmError>();
-        Err(error)
-    } else {
-        Ok(())
-    }
+    farm_core::entrypoint::process_instruction(processor::Processor::process, program_id, accounts, _instruction_data)
+        .map_err(|error| match error {
+            // keep returning the codes of the errors this program had before they were shared
+            ProgramError::Custom(code) => match FarmError::from_u32(code) {
+                Some(error) => ProgramError::Custom(error.v1_code()),
+                None => ProgramError::Custom(code),
+            },
+            error => error,
+        })
 }
 
 #[cfg(test)]
 mod tests {
-    use std::borrow::Borrow;
-    use std::convert::TryInto;
-    use std::mem;
     use std::str::FromStr;
 
-    use crate::constant::{FARM_FEE, FEE_OWNER, USDC_MINT_ADDRESS};
-    use crate::instruction::FarmInstruction;
-    use crate::state::Farm;
-
-    use assert_matches::assert_matches;
+    use crate::constant::{FARM_FEE, FEE_OWNER};
+    use crate::fixture::{ConformingFarm, FarmFixture, CREATOR_USDC_BALANCE, END_TIMESTAMP, START_TIMESTAMP};
+    use crate::instruction::{
+        find_creator_entry_address, find_registry_address, ix_accept_creator, ix_add_creator, ix_initialize_registry,
+        ix_propose_creator, ix_remove_creator,
+    };
+    use crate::state::{CreatorEntry, CreatorRegistry, Farm, FarmAccount};
 
     use super::*;
     use borsh::BorshSerialize;
+    use farm_core::conformance;
     use solana_program_test::*;
-    use solana_sdk::account::Account;
     use solana_sdk::borsh0_10::try_from_slice_unchecked;
-    use solana_sdk::instruction::AccountMeta;
+    use solana_sdk::instruction::InstructionError;
     use solana_sdk::signature::{Keypair, Signer};
-    use solana_sdk::system_instruction;
-    
-    use spl_token::id as TOKEN_PROGRAM_ID;
-
-    // Import the necessary types from the solana_program module
-    use solana_program::instruction::{Instruction};
-    use solana_program::program_pack::Pack;
-    use solana_program::system_program;
-    use solana_program::sysvar::rent;
-    use solana_program::sysvar::Sysvar;
-    use solana_program::hash::Hash;
-    use solana_program::clock::Epoch;
-    use solana_program::account_info::{next_account_info, AccountInfo};
-    use solana_program::msg;
-    use solana_sdk::transaction::Transaction;
-
-    use crate::instruction::ix_pay_create_fee;
-
-    // Define your test functions
+    use solana_sdk::transaction::TransactionError;
+
+    fn farm_error(error: FarmError) -> Result<(), TransactionError> {
+        Err(TransactionError::InstructionError(0, InstructionError::Custom(error.v1_code())))
+    }
+
     #[tokio::test]
     async fn test_initialize_farm() {
+        let mut ctx = FarmFixture::new().start().await;
 
-        let farm_program_id = Keypair::new();
-
-        let program_id = farm_program_id.pubkey();
-
-        let (mut banks_client, payer, recent_blockhash) = ProgramTest::new(
-            "ctf-solana-farm",
-            program_id,
-            processor!(process_instruction),
-        )
-        .start()
-        .await;
-
-        let farm_id = Keypair::new();
-        let authority = Keypair::new();
-        let creator = Keypair::new();
-        let creator_token_account = Keypair::new();
-        let fee_vault = Keypair::new();
-        let token_program_id = Keypair::new();
-
-        let accounts = vec![
-            AccountMeta::new(farm_id.pubkey(), false),
-            AccountMeta::new_readonly(authority.pubkey(), false),
-            AccountMeta::new(creator.pubkey(), true),
-            AccountMeta::new(creator_token_account.pubkey(), false),
-            AccountMeta::new(fee_vault.pubkey(), false),
-            AccountMeta::new_readonly(token_program_id.pubkey(), false),
-        ];
-
-        let instruction = Instruction {
-            program_id: farm_program_id.pubkey(),
-            accounts,
-            data: FarmInstruction::Create { nonce: 12, start_timestamp: 1711446463, end_timestamp: 1711792063 }.try_to_vec().unwrap(),
-        };
-        
-        println!("{:?}", instruction);
+        assert_eq!(ctx.process_as_creator(ctx.create_farm(0)).await, Ok(()));
+
+        let farm = ctx.get_farm(0).await;
+        assert_eq!(farm.is_allowed, 0);
+        assert_eq!(farm.nonce, ctx.farm().nonce);
+        assert_eq!(farm.owner, ctx.creator.pubkey());
+        assert_eq!(farm.pool_lp_token_account, ctx.farm().pool_lp_token_account);
+        assert_eq!(farm.reward_mint_address, ctx.reward_mint);
+    }
+
+    #[tokio::test]
+    async fn test_initialize_farm2() {
+        let mut ctx = FarmFixture::new().created().start().await;
+
+        assert_eq!(ctx.process_as_creator(ctx.pay_farm_fee(FARM_FEE - 1)).await, farm_error(FarmError::InvalidFarmFee));
+        assert_eq!(ctx.process_as_creator(ctx.pay_farm_fee(FARM_FEE)).await, Ok(()));
+
+        assert_eq!(ctx.get_farm(0).await.is_allowed, 1);
+        assert_eq!(ctx.token_balance(ctx.fee_owner_token_account).await, FARM_FEE);
+        assert_eq!(ctx.token_balance(ctx.creator_usdc_token_account).await, CREATOR_USDC_BALANCE - FARM_FEE);
+
+        // the fee is paid once, a different amount keeps the transaction distinct from the first payment
+        assert_eq!(ctx.process_as_creator(ctx.pay_farm_fee(FARM_FEE + 1)).await, farm_error(FarmError::AlreadyInUse));
+    }
+
+    #[tokio::test]
+    async fn test_propose_and_accept_creator() {
+        let mut ctx = FarmFixture::new().created().start().await;
+        let owner = Keypair::from_bytes(&ctx.creator.to_bytes()).unwrap();
+        let new_owner = Keypair::new();
+        let farm_id = ctx.farm().id;
+        let program_id = ctx.program_id;
 
-        let mut transaction = Transaction::new_with_payer(
-            &[instruction],
-            Some(&payer.pubkey()),
+        // accepting without a proposal fails
+        assert_eq!(
+            ctx.process(ix_accept_creator(&farm_id, &new_owner.pubkey(), &program_id), &[&new_owner]).await,
+            farm_error(FarmError::NoPendingCreator)
         );
 
-        // Include all three signers: payer, authority, and creator
-        transaction.sign(&[&payer, &creator, &authority], recent_blockhash);
+        ctx.process(ix_propose_creator(&farm_id, &owner.pubkey(), &new_owner.pubkey(), &program_id), &[&owner]).await.unwrap();
+        ctx.process(ix_accept_creator(&farm_id, &new_owner.pubkey(), &program_id), &[&new_owner]).await.unwrap();
 
-        assert_matches!(banks_client.process_transaction(transaction).await, Ok(()));
+        let farm = ctx.get_farm(0).await;
+        assert_eq!(farm.owner, new_owner.pubkey());
+        assert_eq!(farm.pending_owner, Pubkey::default());
     }
 
     #[tokio::test]
-    async fn test_initialize_farm2() {
+    async fn test_create_farm_consumes_creator_quota() {
+        let mut ctx = FarmFixture::new().farms(2).start().await;
+        let creator = ctx.creator.pubkey();
+        let program_id = ctx.program_id;
+
+        assert_eq!(ctx.process_as_creator(ctx.create_farm(0)).await, Ok(()));
+
+        let farm = ctx.get_farm(0).await;
+        assert_eq!(farm.owner, creator);
+        assert_eq!(farm.fee_owner, Pubkey::from_str(FEE_OWNER).unwrap());
+        assert_eq!(farm.end_timestamp, END_TIMESTAMP);
+
+        let entry_account = ctx
+            .banks_client
+            .get_account(find_creator_entry_address(&creator, &program_id).0)
+            .await.unwrap().unwrap();
+        assert_eq!(try_from_slice_unchecked::<CreatorEntry>(&entry_account.data).unwrap().quota, 0);
+
+        assert_eq!(ctx.process_as_creator(ctx.create_farm(1)).await, farm_error(FarmError::CreatorQuotaExhausted));
+        // a farm can't be created twice either
+        ctx.approve_creator(1, i64::MAX).await.unwrap();
+        assert_eq!(ctx.process_as_creator(ctx.create_farm(0)).await, farm_error(FarmError::AlreadyInUse));
+    }
+
+    #[tokio::test]
+    async fn test_initialize_registry_requires_upgrade_authority() {
+        let mut ctx = FarmFixture::new().without_registry().start().await;
+        let program_id = ctx.program_id;
+        let squatter = Keypair::new();
+
+        // the first caller doesn't get to be the registry admin
+        assert_eq!(
+            ctx.process(ix_initialize_registry(&squatter.pubkey(), &squatter.pubkey(), &program_id), &[&squatter]).await,
+            farm_error(FarmError::WrongAdmin)
+        );
+        assert_eq!(ctx.banks_client.get_account(find_registry_address(&program_id).0).await.unwrap(), None);
+
+        assert_eq!(ctx.initialize_registry().await, Ok(()));
+        let registry_account = ctx.banks_client.get_account(find_registry_address(&program_id).0).await.unwrap().unwrap();
+        assert_eq!(try_from_slice_unchecked::<CreatorRegistry>(&registry_account.data).unwrap().admin, ctx.payer.pubkey());
+    }
+
+    #[tokio::test]
+    async fn test_create_farm_requires_approved_creator() {
+        let mut ctx = FarmFixture::new().creator_approval(None).start().await;
+        let creator = ctx.creator.pubkey();
+        let program_id = ctx.program_id;
+        let intruder = Keypair::new();
+
+        assert_eq!(ctx.process_as_creator(ctx.create_farm(0)).await, farm_error(FarmError::WrongCreator));
+
+        // only the registry admin can approve creators
+        assert_eq!(
+            ctx.process(ix_add_creator(&intruder.pubkey(), &creator, &program_id, 1, i64::MAX), &[&intruder]).await,
+            farm_error(FarmError::WrongAdmin)
+        );
+
+        ctx.approve_creator(1, 1).await.unwrap();
+        assert_eq!(ctx.process_as_creator(ctx.create_farm(0)).await, farm_error(FarmError::CreatorExpired));
 
-        let farm_id = Keypair::new();
-        let authority = Keypair::new();
-        let creator = Keypair::new();
-        let creator_token_account = Keypair::new();
-        let fee_vault = Keypair::new();
-        let token_program_id = Keypair::new();
-        let farm_program_id = Keypair::new();
-        let amount = 5000;
-
-        let program_id = farm_program_id.pubkey();
-
-        let (mut banks_client, payer, recent_blockhash) = ProgramTest::new(
-            "ctf-solana-farm",
-            program_id,
-            processor!(process_instruction),
-        )
-        .start()
-        .await;
-
-        let instruction = ix_pay_create_fee(
-            &farm_id.pubkey(), 
-            &authority.pubkey(), 
-            &creator.pubkey(), 
-            &creator_token_account.pubkey(), 
-            &fee_vault.pubkey(), 
-            &token_program_id.pubkey(), 
-            &farm_program_id.pubkey(), 
-            amount);
-
-        println!("{:?}", instruction);
-
-        let mut transaction = Transaction::new_with_payer(
-            &[instruction],
-            Some(&payer.pubkey()),
+        ctx.approve_creator(1, i64::MAX).await.unwrap();
+        ctx.process(ix_remove_creator(&ctx.payer.pubkey(), &creator, &program_id), &[]).await.unwrap();
+        assert_eq!(
+            ctx.banks_client.get_account(find_creator_entry_address(&creator, &program_id).0).await.unwrap(),
+            None
         );
+        assert_eq!(ctx.process_as_creator(ctx.create_farm(0)).await, farm_error(FarmError::WrongCreator));
+    }
+
+    #[tokio::test]
+    async fn test_create_farm_checks_window() {
+        let mut ctx = FarmFixture::new().farms(2).creator_approval(Some((2, i64::MAX))).start().await;
+
+        // the farm has to start before it ends
+        assert_eq!(
+            ctx.process_as_creator(ctx.create_farm_between(0, END_TIMESTAMP, END_TIMESTAMP)).await,
+            farm_error(FarmError::InvalidFarmWindow)
+        );
+        assert_eq!(
+            ctx.process_as_creator(ctx.create_farm_between(0, END_TIMESTAMP, START_TIMESTAMP)).await,
+            farm_error(FarmError::InvalidFarmWindow)
+        );
+
+        // a farm may start later
+        assert_eq!(ctx.process_as_creator(ctx.create_farm_between(1, START_TIMESTAMP + 1, END_TIMESTAMP)).await, Ok(()));
+        assert_eq!(ctx.get_farm(1).await.last_timestamp, START_TIMESTAMP + 1);
+
+        // the farm is over once the clock reaches its end
+        ctx.warp_to(END_TIMESTAMP as i64).await;
+        assert_eq!(
+            ctx.process_as_creator(ctx.create_farm_between(0, START_TIMESTAMP + 1, END_TIMESTAMP)).await,
+            farm_error(FarmError::FarmEnded)
+        );
+        ctx.warp_to(END_TIMESTAMP as i64 + 1).await;
+        assert_eq!(
+            ctx.process_as_creator(ctx.create_farm_between(0, START_TIMESTAMP, END_TIMESTAMP - 1)).await,
+            farm_error(FarmError::FarmEnded)
+        );
+
+        // a farm that already started can be created while it still runs
+        ctx.warp_to(END_TIMESTAMP as i64 - 1).await;
+        assert_eq!(ctx.process_as_creator(ctx.create_farm_between(0, START_TIMESTAMP, END_TIMESTAMP)).await, Ok(()));
+    }
+
+    #[test]
+    fn test_farm_account_matches_borsh_layout() {
+        let farm = Farm {
+            is_allowed: 1,
+            nonce: 7,
+            owner: Pubkey::new_unique(),
+            fee_owner: Pubkey::new_unique(),
+            reward_per_share_net: 1,
+            last_timestamp: 1711446463,
+            reward_per_timestamp: u64::MAX,
+            start_timestamp: 1711446463,
+            end_timestamp: 1711792063,
+            ..Farm::default()
+        };
+        let encoded = farm.try_to_vec().unwrap();
+        assert_eq!(encoded.len(), FarmAccount::LEN);
+
+        let zero_copy: &FarmAccount = bytemuck::from_bytes(&encoded);
+        assert_eq!(zero_copy.end_timestamp(), farm.end_timestamp);
+        assert_eq!(Farm::from(zero_copy), farm);
+        assert_eq!(bytemuck::bytes_of(&FarmAccount::from(&farm)), &encoded[..]);
+    }
+
+    // the same scenarios run against the second generation program
+    #[tokio::test]
+    async fn test_conformance_pays_fee() {
+        conformance::pays_fee::<ConformingFarm>().await;
+    }
+
+    #[tokio::test]
+    async fn test_conformance_rejects_second_payment() {
+        conformance::rejects_second_payment::<ConformingFarm>().await;
+    }
+
+    #[tokio::test]
+    async fn test_conformance_rejects_missing_creator_signature() {
+        conformance::rejects_missing_creator_signature::<ConformingFarm>().await;
+    }
+
+    #[tokio::test]
+    async fn test_conformance_rejects_other_creator() {
+        conformance::rejects_other_creator::<ConformingFarm>().await;
+    }
 
-        // Include all three signers: payer, authority, and creator
-        transaction.sign(&[&payer, &creator, &authority], recent_blockhash);
+    #[tokio::test]
+    async fn test_conformance_rejects_wrong_authority() {
+        conformance::rejects_wrong_authority::<ConformingFarm>().await;
+    }
+
+    #[tokio::test]
+    async fn test_conformance_rejects_foreign_token_program() {
+        conformance::rejects_foreign_token_program::<ConformingFarm>().await;
+    }
+
+    #[tokio::test]
+    async fn test_conformance_rejects_wrong_fee() {
+        conformance::rejects_wrong_fee::<ConformingFarm>().await;
+    }
 
-        assert_matches!(banks_client.process_transaction(transaction).await, Ok(()));
+    #[test]
+    fn test_idl_is_up_to_date() {
+        let checked_in: serde_json::Value = serde_json::from_str(include_str!("../idl/ctf_solana_farm.json")).unwrap();
+        assert_eq!(checked_in, crate::idl::idl(), "run `cargo run --example idl > idl/ctf_solana_farm.json`");
     }
 
 }
@@ -5,28 +5,38 @@ use {
             FarmInstruction
         },
         state::{
-            Farm,
+            CreatorEntry,
+            CreatorRegistry,
+            FarmAccount,
         },
         constant::{
+            CREATOR_ENTRY_SEED,
             FARM_FEE,
+            FEE_OWNER,
+            REGISTRY_SEED,
         },
     },
     borsh::{BorshDeserialize, BorshSerialize},
-    num_traits::FromPrimitive,
+    farm_core::{
+        token::token_transfer,
+        validation::{check_authority, check_program_account, check_signer, check_token_account, check_token_program, check_upgrade_authority},
+    },
     solana_program::{
         account_info::{
             next_account_info,
             AccountInfo,
         },
-        borsh0_10::try_from_slice_unchecked,
-        decode_error::DecodeError,
+        clock::Clock,
         entrypoint::ProgramResult,
         msg,
         program::invoke_signed,
-        program_error::PrintProgramError,
         program_error::ProgramError,
         pubkey::Pubkey,
+        rent::Rent,
+        system_instruction,
+        sysvar::Sysvar,
     },
+    std::{convert::TryFrom, str::FromStr},
 };
 
 pub struct Processor {}
@@ -34,12 +44,205 @@ impl Processor {
     pub fn process(program_id: &Pubkey, accounts: &[AccountInfo], input: &[u8]) -> ProgramResult {
         let instruction = FarmInstruction::try_from_slice(input)?;
         match instruction {
+            FarmInstruction::Create { nonce, start_timestamp, end_timestamp } => {
+                Self::process_create_farm(program_id, accounts, nonce, start_timestamp, end_timestamp)
+            },
+
             FarmInstruction::PayFarmFee(amount) => {
                 Self::process_pay_farm_fee(program_id, accounts, amount)
             },
 
-            _ => Err(FarmError::NotAllowed.into())
+            FarmInstruction::ProposeCreator(new_owner) => {
+                Self::process_propose_creator(program_id, accounts, new_owner)
+            },
+
+            FarmInstruction::AcceptCreator => {
+                Self::process_accept_creator(program_id, accounts)
+            },
+
+            FarmInstruction::InitializeRegistry => {
+                Self::process_initialize_registry(program_id, accounts)
+            },
+
+            FarmInstruction::AddCreator { quota, expiry } => {
+                Self::process_add_creator(program_id, accounts, quota, expiry)
+            },
+
+            FarmInstruction::RemoveCreator => {
+                Self::process_remove_creator(program_id, accounts)
+            },
         }
+    }
+
+    pub fn process_create_farm(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        nonce: u8,
+        start_timestamp: u64,
+        end_timestamp: u64,
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let farm_id_info = next_account_info(account_info_iter)?;
+        let authority_info = next_account_info(account_info_iter)?;
+        let creator_info = next_account_info(account_info_iter)?;
+        let pool_lp_token_account_info = next_account_info(account_info_iter)?;
+        let pool_reward_token_account_info = next_account_info(account_info_iter)?;
+        let pool_mint_info = next_account_info(account_info_iter)?;
+        let reward_mint_info = next_account_info(account_info_iter)?;
+        let _amm_id_info = next_account_info(account_info_iter)?;
+        let token_program_info = next_account_info(account_info_iter)?;
+        let creator_entry_info = next_account_info(account_info_iter)?;
+
+        check_program_account(farm_id_info, program_id)?;
+        check_signer(creator_info)?;
+        check_authority(program_id, farm_id_info.key, nonce, authority_info)?;
+        check_token_program(token_program_info)?;
+        check_token_account(pool_lp_token_account_info, authority_info.key, pool_mint_info.key)?;
+        check_token_account(pool_reward_token_account_info, authority_info.key, reward_mint_info.key)?;
+
+        let mut farm_data = FarmAccount::load_mut(farm_id_info)?;
+        if farm_data.is_allowed == 1 || farm_data.owner != Pubkey::default() {
+            return Err(FarmError::AlreadyInUse.into());
+        }
+
+        if start_timestamp >= end_timestamp {
+            return Err(FarmError::InvalidFarmWindow.into());
+        }
+
+        // a farm may start in the past, but it has to end in the future
+        let now = Clock::get()?.unix_timestamp;
+        if u64::try_from(now).map_or(false, |now| now >= end_timestamp) {
+            return Err(FarmError::FarmEnded.into());
+        }
+
+        Self::consume_creator_quota(program_id, creator_info.key, creator_entry_info)?;
+
+        farm_data.nonce = nonce;
+        farm_data.pool_lp_token_account = *pool_lp_token_account_info.key;
+        farm_data.pool_reward_token_account = *pool_reward_token_account_info.key;
+        farm_data.pool_mint_address = *pool_mint_info.key;
+        farm_data.reward_mint_address = *reward_mint_info.key;
+        farm_data.token_program_id = *token_program_info.key;
+        farm_data.owner = *creator_info.key;
+        farm_data.fee_owner = Pubkey::from_str(FEE_OWNER).or(Err(FarmError::InvalidFeeAccount))?;
+        farm_data.set_start_timestamp(start_timestamp);
+        farm_data.set_end_timestamp(end_timestamp);
+        farm_data.set_last_timestamp(start_timestamp);
+
+        Ok(())
+    }
+
+    pub fn process_initialize_registry(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let registry_info = next_account_info(account_info_iter)?;
+        let admin_info = next_account_info(account_info_iter)?;
+        let system_program_info = next_account_info(account_info_iter)?;
+        let program_data_info = next_account_info(account_info_iter)?;
+        let upgrade_authority_info = next_account_info(account_info_iter)?;
+
+        check_signer(admin_info)?;
+        // whoever sends it first would become the registry admin otherwise
+        check_upgrade_authority(program_id, program_data_info, upgrade_authority_info)?;
+
+        let (registry_key, bump) = Pubkey::find_program_address(&[REGISTRY_SEED], program_id);
+        if *registry_info.key != registry_key {
+            return Err(FarmError::InvalidCreatorRegistry.into());
+        }
+
+        if !registry_info.data_is_empty() {
+            return Err(FarmError::AlreadyInUse.into());
+        }
+
+        let registry = CreatorRegistry {
+            is_initialized: 1,
+            bump,
+            admin: *admin_info.key,
+        };
+        Self::create_pda_account(
+            program_id,
+            admin_info,
+            registry_info,
+            system_program_info,
+            &[REGISTRY_SEED, &[bump]],
+            registry.try_to_vec()?.len(),
+        )?;
+
+        registry
+            .serialize(&mut &mut registry_info.data.borrow_mut()[..])
+            .map_err(|e| e.into())
+    }
+
+    pub fn process_add_creator(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        quota: u64,
+        expiry: i64,
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let creator_entry_info = next_account_info(account_info_iter)?;
+        let registry_info = next_account_info(account_info_iter)?;
+        let admin_info = next_account_info(account_info_iter)?;
+        let creator_info = next_account_info(account_info_iter)?;
+        let system_program_info = next_account_info(account_info_iter)?;
+
+        Self::check_registry_admin(program_id, registry_info, admin_info)?;
+
+        let (entry_key, bump) = Pubkey::find_program_address(&[CREATOR_ENTRY_SEED, creator_info.key.as_ref()], program_id);
+        if *creator_entry_info.key != entry_key {
+            return Err(FarmError::InvalidCreatorRegistry.into());
+        }
+
+        let entry = CreatorEntry {
+            is_initialized: 1,
+            bump,
+            creator: *creator_info.key,
+            quota,
+            expiry,
+        };
+        if creator_entry_info.data_is_empty() {
+            Self::create_pda_account(
+                program_id,
+                admin_info,
+                creator_entry_info,
+                system_program_info,
+                &[CREATOR_ENTRY_SEED, creator_info.key.as_ref(), &[bump]],
+                entry.try_to_vec()?.len(),
+            )?;
+        }
+
+        msg!("Creator {} approved for {} farms until {}", creator_info.key, quota, expiry);
+        entry
+            .serialize(&mut &mut creator_entry_info.data.borrow_mut()[..])
+            .map_err(|e| e.into())
+    }
+
+    pub fn process_remove_creator(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let creator_entry_info = next_account_info(account_info_iter)?;
+        let registry_info = next_account_info(account_info_iter)?;
+        let admin_info = next_account_info(account_info_iter)?;
+        let creator_info = next_account_info(account_info_iter)?;
+
+        Self::check_registry_admin(program_id, registry_info, admin_info)?;
+        Self::creator_entry(program_id, creator_info.key, creator_entry_info)?;
+
+        // the runtime purges the entry once it holds no lamports
+        let lamports = creator_entry_info.lamports();
+        **creator_entry_info.lamports.borrow_mut() = 0;
+        **admin_info.lamports.borrow_mut() = admin_info
+            .lamports()
+            .checked_add(lamports)
+            .ok_or(ProgramError::InvalidArgument)?;
+        creator_entry_info.data.borrow_mut().fill(0);
+
+        msg!("Creator {} removed", creator_info.key);
+        Ok(())
     } 
 
     pub fn process_pay_farm_fee(
@@ -47,7 +250,6 @@ impl Processor {
         accounts: &[AccountInfo],
         amount: u64,
     ) -> ProgramResult {
-        msg!("Entra");
         let account_info_iter = &mut accounts.iter();
         let farm_id_info = next_account_info(account_info_iter)?;
         let authority_info = next_account_info(account_info_iter)?;
@@ -56,26 +258,24 @@ impl Processor {
         let user_usdc_token_account_info = next_account_info(account_info_iter)?;
         let fee_owner_info = next_account_info(account_info_iter)?;
         let token_program_info = next_account_info(account_info_iter)?;
-        msg!("Entra 1b");
-        let mut farm_data = try_from_slice_unchecked::<Farm>(&farm_id_info.data.borrow())?;
-        msg!("Entra 2");
+        let farm_data = *FarmAccount::load(farm_id_info)?;
         if farm_data.is_allowed == 1 {
             return Err(FarmError::AlreadyInUse.into());
         }
 
+        check_signer(creator_info)?;
+
         if *creator_info.key != farm_data.owner {
-            return Err(FarmError::WrongManager.into());
+            return Err(FarmError::WrongCreator.into());
         }
 
-        if *authority_info.key != Self::authority_id(program_id, farm_id_info.key, farm_data.nonce)? {
-            return Err(FarmError::InvalidProgramAddress.into());
-        }
+        check_authority(program_id, farm_id_info.key, farm_data.nonce, authority_info)?;
+        check_token_program(token_program_info)?;
 
         if amount != FARM_FEE {
             return Err(FarmError::InvalidFarmFee.into());
         }
-        msg!("Entra 3");
-        Self::token_transfer(
+        token_transfer(
             farm_id_info.key,
             token_program_info.clone(), 
             user_usdc_token_account_info.clone(), 
@@ -85,77 +285,148 @@ impl Processor {
             amount
         )?;
 
-        msg!("Entra 4");
+        FarmAccount::load_mut(farm_id_info)?.is_allowed = 1;
+
+        Ok(())
+    }
+
+    pub fn process_propose_creator(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        new_owner: Pubkey,
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let farm_id_info = next_account_info(account_info_iter)?;
+        let owner_info = next_account_info(account_info_iter)?;
+
+        check_program_account(farm_id_info, program_id)?;
+        check_signer(owner_info)?;
 
-        farm_data.is_allowed = 1;
+        let mut farm_data = FarmAccount::load_mut(farm_id_info)?;
+        if *owner_info.key != farm_data.owner {
+            return Err(FarmError::WrongCreator.into());
+        }
 
-        msg!("Entra 5");
+        farm_data.pending_owner = new_owner;
+        msg!("Pending owner: {}", new_owner);
 
-        farm_data
-            .serialize(&mut *farm_id_info.data.borrow_mut())
-            .map_err(|e| e.into())
+        Ok(())
     }
 
-    pub fn authority_id(
+    pub fn process_accept_creator(
         program_id: &Pubkey,
-        my_info: &Pubkey,
-        nonce: u8,
-    ) -> Result<Pubkey, FarmError> {
-        Pubkey::create_program_address(&[&my_info.to_bytes()[..32], &[nonce]], program_id)
-            .or(Err(FarmError::InvalidProgramAddress))
+        accounts: &[AccountInfo],
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let farm_id_info = next_account_info(account_info_iter)?;
+        let pending_owner_info = next_account_info(account_info_iter)?;
+
+        check_program_account(farm_id_info, program_id)?;
+        check_signer(pending_owner_info)?;
+
+        let mut farm_data = FarmAccount::load_mut(farm_id_info)?;
+        if farm_data.pending_owner == Pubkey::default() {
+            return Err(FarmError::NoPendingCreator.into());
+        }
+
+        if *pending_owner_info.key != farm_data.pending_owner {
+            return Err(FarmError::WrongCreator.into());
+        }
+
+        farm_data.owner = farm_data.pending_owner;
+        farm_data.pending_owner = Pubkey::default();
+        msg!("New owner: {}", farm_data.owner);
+
+        Ok(())
     }
 
-    pub fn token_transfer<'a>(
-        pool: &Pubkey,
-        token_program: AccountInfo<'a>,
-        source: AccountInfo<'a>,
-        destination: AccountInfo<'a>,
-        authority: AccountInfo<'a>,
-        nonce: u8,
-        amount: u64,
-    ) -> Result<(), ProgramError> {
-        let pool_bytes = pool.to_bytes();
-        let authority_signature_seeds = [&pool_bytes[..32], &[nonce]];
-        let signers = &[&authority_signature_seeds[..]];
-        msg!("Token program id: {}", token_program.key);
-        msg!("Source pubkey: {}", source.key);
-        msg!("Destination pubkey: {}", destination.key);
-        msg!("Authority pubkey: {}", authority.key);
-        msg!("Amount: {}", amount);
-        let ix = spl_token::instruction::transfer(
-            token_program.key,
-            source.key,
-            destination.key,
-            authority.key,
-            &[],
-            amount,
-        )?;
-        msg!("SOURCE ACCOUNT LAMPORTS: {}", source.lamports());
-        msg!("DESTINATION ACCOUNT LAMPORTS: {}", destination.lamports());
-        invoke_signed(
-            &ix,
-            &[source, destination, authority, token_program],
-            signers,
-        )
-    } 
-    
-}
+    fn check_registry_admin(
+        program_id: &Pubkey,
+        registry_info: &AccountInfo,
+        admin_info: &AccountInfo,
+    ) -> ProgramResult {
+        if registry_info.owner != program_id {
+            return Err(FarmError::InvalidCreatorRegistry.into());
+        }
+
+        let registry = CreatorRegistry::try_from_slice(&registry_info.data.borrow())?;
+        let registry_key = Pubkey::create_program_address(&[REGISTRY_SEED, &[registry.bump]], program_id)
+            .or(Err(FarmError::InvalidCreatorRegistry))?;
+        if registry.is_initialized != 1 || *registry_info.key != registry_key {
+            return Err(FarmError::InvalidCreatorRegistry.into());
+        }
+
+        check_signer(admin_info)?;
+
+        if *admin_info.key != registry.admin {
+            return Err(FarmError::WrongAdmin.into());
+        }
+
+        Ok(())
+    }
+
+    fn creator_entry(
+        program_id: &Pubkey,
+        creator: &Pubkey,
+        creator_entry_info: &AccountInfo,
+    ) -> Result<CreatorEntry, ProgramError> {
+        // a missing or closed entry means the creator is not approved
+        if creator_entry_info.owner != program_id || creator_entry_info.data_is_empty() {
+            return Err(FarmError::WrongCreator.into());
+        }
+
+        let entry = CreatorEntry::try_from_slice(&creator_entry_info.data.borrow())?;
+        if entry.is_initialized != 1 || entry.creator != *creator {
+            return Err(FarmError::WrongCreator.into());
+        }
 
-impl PrintProgramError for FarmError {
-    fn print<E>(&self)
-    where
-        E: 'static + std::error::Error + DecodeError<E> + PrintProgramError + FromPrimitive,
-    {
-        match self {
-            FarmError::AlreadyInUse => msg!("Error: The account cannot be initialized because it is already being used"),
-            FarmError::InvalidProgramAddress => msg!("Error: The program address provided doesn't match the value generated by the program"),
-            FarmError::WrongManager => msg!("Error: Wrong pool manager account"),
-            FarmError::SignatureMissing => msg!("Error: Required signature is missing"),
-            FarmError::InvalidFeeAccount => msg!("Error: Invalid manager fee account"),
-            FarmError::WrongPoolMint => msg!("Error: Specified pool mint account is wrong"),
-            FarmError::NotAllowed => msg!("Error: This farm is not allowed yet. The farm creator has to pay additional fee"),
-            FarmError::InvalidFarmFee => msg!("Error: Wrong Farm Fee. Farm fee has to be {}",FARM_FEE),
-            FarmError::WrongCreator => msg!("Error: Not allowed to create the farm by this creator"),
+        let entry_key = Pubkey::create_program_address(&[CREATOR_ENTRY_SEED, creator.as_ref(), &[entry.bump]], program_id)
+            .or(Err(FarmError::InvalidCreatorRegistry))?;
+        if *creator_entry_info.key != entry_key {
+            return Err(FarmError::InvalidCreatorRegistry.into());
         }
+
+        Ok(entry)
     }
-} 
+
+    fn consume_creator_quota(
+        program_id: &Pubkey,
+        creator: &Pubkey,
+        creator_entry_info: &AccountInfo,
+    ) -> ProgramResult {
+        let mut entry = Self::creator_entry(program_id, creator, creator_entry_info)?;
+        if Clock::get()?.unix_timestamp >= entry.expiry {
+            return Err(FarmError::CreatorExpired.into());
+        }
+
+        if entry.quota == 0 {
+            return Err(FarmError::CreatorQuotaExhausted.into());
+        }
+
+        entry.quota -= 1;
+        entry
+            .serialize(&mut &mut creator_entry_info.data.borrow_mut()[..])
+            .map_err(|e| e.into())
+    }
+
+    fn create_pda_account<'a>(
+        program_id: &Pubkey,
+        payer_info: &AccountInfo<'a>,
+        pda_info: &AccountInfo<'a>,
+        system_program_info: &AccountInfo<'a>,
+        seeds: &[&[u8]],
+        space: usize,
+    ) -> ProgramResult {
+        invoke_signed(
+            &system_instruction::create_account(
+                payer_info.key,
+                pda_info.key,
+                Rent::get()?.minimum_balance(space),
+                space as u64,
+                program_id,
+            ),
+            &[payer_info.clone(), pda_info.clone(), system_program_info.clone()],
+            &[seeds],
+        )
+    }
+}
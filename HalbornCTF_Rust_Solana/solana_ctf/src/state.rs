@@ -1,9 +1,13 @@
 #![allow(clippy::too_many_arguments)]
 use {
     borsh::{BorshDeserialize, BorshSchema, BorshSerialize},
+    bytemuck::{Pod, Zeroable},
     solana_program::{
+        account_info::AccountInfo,
+        program_error::ProgramError,
         pubkey::{Pubkey},
     },
+    std::cell::{Ref, RefMut},
 };
 
 #[repr(C)]
@@ -26,6 +30,141 @@ pub struct Farm {
     pub reward_per_timestamp: u64,
     pub start_timestamp: u64,
     pub end_timestamp: u64,
+
+    pub pending_owner: Pubkey,
+}
+
+/// Zero-copy view of a Farm account.
+/// Byte for byte the same as the borsh encoding of `Farm`: the u64 fields are kept
+/// as little-endian byte arrays so the struct has no padding and alignment 1.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Pod, Zeroable)]
+pub struct FarmAccount {
+    pub is_allowed: u8,
+
+    pub nonce: u8,
+
+    pub pool_lp_token_account: Pubkey,
+    pub pool_reward_token_account: Pubkey,
+    pub pool_mint_address: Pubkey,
+    pub reward_mint_address: Pubkey,
+    pub token_program_id: Pubkey,
+    pub owner: Pubkey,
+    pub fee_owner: Pubkey,
+
+    reward_per_share_net: [u8; 8],
+    last_timestamp: [u8; 8],
+    reward_per_timestamp: [u8; 8],
+    start_timestamp: [u8; 8],
+    end_timestamp: [u8; 8],
+
+    pub pending_owner: Pubkey,
+}
+
+impl FarmAccount {
+    pub const LEN: usize = std::mem::size_of::<FarmAccount>();
+
+    /// Borrows the farm stored in the account data without copying it.
+    pub fn load<'a>(account: &'a AccountInfo) -> Result<Ref<'a, Self>, ProgramError> {
+        let data = account.try_borrow_data()?;
+        if data.len() < Self::LEN {
+            return Err(ProgramError::AccountDataTooSmall);
+        }
+        Ok(Ref::map(data, |data| bytemuck::from_bytes(&data[..Self::LEN])))
+    }
+
+    /// Mutably borrows the farm stored in the account data; writes land in place.
+    pub fn load_mut<'a>(account: &'a AccountInfo) -> Result<RefMut<'a, Self>, ProgramError> {
+        let data = account.try_borrow_mut_data()?;
+        if data.len() < Self::LEN {
+            return Err(ProgramError::AccountDataTooSmall);
+        }
+        Ok(RefMut::map(data, |data| bytemuck::from_bytes_mut(&mut data[..Self::LEN])))
+    }
+
+    pub fn reward_per_share_net(&self) -> u64 {
+        u64::from_le_bytes(self.reward_per_share_net)
+    }
+
+    pub fn set_reward_per_share_net(&mut self, value: u64) {
+        self.reward_per_share_net = value.to_le_bytes();
+    }
+
+    pub fn last_timestamp(&self) -> u64 {
+        u64::from_le_bytes(self.last_timestamp)
+    }
+
+    pub fn set_last_timestamp(&mut self, value: u64) {
+        self.last_timestamp = value.to_le_bytes();
+    }
+
+    pub fn reward_per_timestamp(&self) -> u64 {
+        u64::from_le_bytes(self.reward_per_timestamp)
+    }
+
+    pub fn set_reward_per_timestamp(&mut self, value: u64) {
+        self.reward_per_timestamp = value.to_le_bytes();
+    }
+
+    pub fn start_timestamp(&self) -> u64 {
+        u64::from_le_bytes(self.start_timestamp)
+    }
+
+    pub fn set_start_timestamp(&mut self, value: u64) {
+        self.start_timestamp = value.to_le_bytes();
+    }
+
+    pub fn end_timestamp(&self) -> u64 {
+        u64::from_le_bytes(self.end_timestamp)
+    }
+
+    pub fn set_end_timestamp(&mut self, value: u64) {
+        self.end_timestamp = value.to_le_bytes();
+    }
+}
+
+impl From<&FarmAccount> for Farm {
+    fn from(farm: &FarmAccount) -> Self {
+        Farm {
+            is_allowed: farm.is_allowed,
+            nonce: farm.nonce,
+            pool_lp_token_account: farm.pool_lp_token_account,
+            pool_reward_token_account: farm.pool_reward_token_account,
+            pool_mint_address: farm.pool_mint_address,
+            reward_mint_address: farm.reward_mint_address,
+            token_program_id: farm.token_program_id,
+            owner: farm.owner,
+            fee_owner: farm.fee_owner,
+            reward_per_share_net: farm.reward_per_share_net(),
+            last_timestamp: farm.last_timestamp(),
+            reward_per_timestamp: farm.reward_per_timestamp(),
+            start_timestamp: farm.start_timestamp(),
+            end_timestamp: farm.end_timestamp(),
+            pending_owner: farm.pending_owner,
+        }
+    }
+}
+
+impl From<&Farm> for FarmAccount {
+    fn from(farm: &Farm) -> Self {
+        FarmAccount {
+            is_allowed: farm.is_allowed,
+            nonce: farm.nonce,
+            pool_lp_token_account: farm.pool_lp_token_account,
+            pool_reward_token_account: farm.pool_reward_token_account,
+            pool_mint_address: farm.pool_mint_address,
+            reward_mint_address: farm.reward_mint_address,
+            token_program_id: farm.token_program_id,
+            owner: farm.owner,
+            fee_owner: farm.fee_owner,
+            reward_per_share_net: farm.reward_per_share_net.to_le_bytes(),
+            last_timestamp: farm.last_timestamp.to_le_bytes(),
+            reward_per_timestamp: farm.reward_per_timestamp.to_le_bytes(),
+            start_timestamp: farm.start_timestamp.to_le_bytes(),
+            end_timestamp: farm.end_timestamp.to_le_bytes(),
+            pending_owner: farm.pending_owner,
+        }
+    }
 }
 
 #[repr(C)]
@@ -34,4 +173,26 @@ pub struct Swap {
     pub pool_mint: Pubkey,
     pub token_a_mint: Pubkey,
     pub token_b_mint: Pubkey
-}
\ No newline at end of file
+}
+
+/// Admin of the creator registry, stored in the PDA derived from `REGISTRY_SEED`.
+#[repr(C)]
+#[derive(Clone, Debug, Default, PartialEq, BorshDeserialize, BorshSerialize, BorshSchema)]
+pub struct CreatorRegistry {
+    pub is_initialized: u8,
+    pub bump: u8,
+    pub admin: Pubkey,
+}
+
+/// Approved creator, stored in the PDA derived from `CREATOR_ENTRY_SEED` and the creator key.
+/// Every farm created by the creator consumes one unit of `quota`.
+#[repr(C)]
+#[derive(Clone, Debug, Default, PartialEq, BorshDeserialize, BorshSerialize, BorshSchema)]
+pub struct CreatorEntry {
+    pub is_initialized: u8,
+    pub bump: u8,
+    pub creator: Pubkey,
+    pub quota: u64,
+    /// unix timestamp after which the creator can no longer create farms
+    pub expiry: i64,
+}
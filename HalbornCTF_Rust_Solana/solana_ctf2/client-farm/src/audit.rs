@@ -0,0 +1,360 @@
+use {
+    crate::{
+        commands::{explain, farm_client, rpc_client},
+        config::Settings,
+        decode::parse_logs,
+    },
+    ctf_solana_farm::{
+        client::{get_signatures_since, history_commitment},
+        event::FarmEvent,
+        state::Farm,
+    },
+    farm_core::pda::authority_id,
+    serde::Serialize,
+    solana_client::{
+        rpc_client::RpcClient,
+        rpc_config::RpcTransactionConfig,
+        rpc_request::MAX_MULTIPLE_ACCOUNTS,
+    },
+    solana_program::program_pack::Pack,
+    solana_sdk::{
+        account::Account,
+        clock::Slot,
+        pubkey::Pubkey,
+        signature::Signature,
+    },
+    solana_transaction_status::UiTransactionEncoding,
+    spl_token::state::Account as TokenAccount,
+    std::{
+        collections::{BTreeMap, BTreeSet, HashMap, HashSet},
+        error::Error,
+        str::FromStr,
+    },
+};
+
+/// invariant a finding breaks
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Check {
+    /// the farm authority doesn't derive from the stored nonce
+    AuthorityNonce,
+    /// the fee vault account doesn't exist
+    FeeVaultMissing,
+    /// the fee vault isn't a token account owned by the farm authority
+    FeeVaultOwner,
+    /// the fee vault holds less than the FarmFeePaid events paid into it
+    FeeVaultBalance,
+    /// the farm token program isn't SPL Token
+    TokenProgram,
+    /// an enabled farm has no FarmFeePaid event in its history
+    UnpaidFarm,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Finding {
+    pub check: Check,
+    /// the farm, or the fee vault for `FeeVaultBalance`
+    pub account: String,
+    pub detail: String,
+}
+
+impl Finding {
+    fn new(check: Check, account: &Pubkey, detail: String) -> Self {
+        Finding { check, account: account.to_string(), detail }
+    }
+}
+
+/// what `audit` prints, `passed` is false as soon as there is a finding
+#[derive(Debug, Serialize)]
+pub struct Report {
+    pub program_id: String,
+    pub slot: Slot,
+    pub farms: usize,
+    pub enabled_farms: usize,
+    pub passed: bool,
+    pub findings: Vec<Finding>,
+}
+
+/// the accounts and history the checks run on
+#[derive(Debug, Default)]
+pub struct Snapshot {
+    pub farms: Vec<(Pubkey, Farm)>,
+    /// fee vaults that exist
+    pub fee_vaults: HashMap<Pubkey, Account>,
+    /// farms with a FarmFeePaid event in a successful transaction
+    pub paid_farms: HashSet<Pubkey>,
+    /// `fee_vault_amount` of those events summed per fee vault
+    pub vault_payments: HashMap<Pubkey, u64>,
+}
+
+fn token_account(account: &Account) -> Option<TokenAccount> {
+    if account.owner != spl_token::id() {
+        return None;
+    }
+    TokenAccount::unpack(&account.data).ok()
+}
+
+/// runs every check on `snapshot`, findings come in farm order, fee vault balances last
+pub fn check(program_id: &Pubkey, snapshot: &Snapshot) -> Vec<Finding> {
+    let mut findings = vec![];
+    for (farm_id, farm) in &snapshot.farms {
+        let authority = authority_id(program_id, farm_id, farm.nonce).ok();
+        if authority.is_none() {
+            findings.push(Finding::new(
+                Check::AuthorityNonce,
+                farm_id,
+                format!("nonce {} doesn't derive a program address", farm.nonce),
+            ));
+        }
+        if farm.token_program_id != spl_token::id() {
+            findings.push(Finding::new(
+                Check::TokenProgram,
+                farm_id,
+                format!("token program is {}", farm.token_program_id),
+            ));
+        }
+        match snapshot.fee_vaults.get(&farm.fee_vault).map(token_account) {
+            None => findings.push(Finding::new(
+                Check::FeeVaultMissing,
+                farm_id,
+                format!("fee vault {} doesn't exist", farm.fee_vault),
+            )),
+            Some(None) => findings.push(Finding::new(
+                Check::FeeVaultOwner,
+                farm_id,
+                format!("fee vault {} isn't an SPL Token account", farm.fee_vault),
+            )),
+            // without an authority there is nothing to compare with, AuthorityNonce already reports the farm
+            Some(Some(vault)) if authority.is_some() && Some(vault.owner) != authority => findings.push(Finding::new(
+                Check::FeeVaultOwner,
+                farm_id,
+                format!("fee vault {} is owned by {}, not by the farm authority", farm.fee_vault, vault.owner),
+            )),
+            Some(Some(_)) => {}
+        }
+        if farm.enabled == 1 && !snapshot.paid_farms.contains(farm_id) {
+            findings.push(Finding::new(
+                Check::UnpaidFarm,
+                farm_id,
+                "enabled without a FarmFeePaid event".to_string(),
+            ));
+        }
+    }
+    let vault_payments: BTreeMap<&Pubkey, &u64> = snapshot.vault_payments.iter().collect();
+    for (fee_vault, paid) in vault_payments {
+        let vault = match snapshot.fee_vaults.get(fee_vault).and_then(token_account) {
+            Some(vault) => vault,
+            None => continue,
+        };
+        if vault.amount < *paid {
+            findings.push(Finding::new(
+                Check::FeeVaultBalance,
+                fee_vault,
+                format!("holds {}, FarmFeePaid events paid {} into it", vault.amount, paid),
+            ));
+        }
+    }
+    findings
+}
+
+/// the FarmFeePaid events of `farm_id` in its successful transactions, as (fee vault, fee vault amount)
+fn fee_payments(rpc: &RpcClient, settings: &Settings, farm_id: &Pubkey) -> Result<Vec<(Pubkey, u64)>, Box<dyn Error>> {
+    let history = rpc.runtime().block_on(get_signatures_since(rpc.get_inner_client(), farm_id, None))?;
+    let config = RpcTransactionConfig {
+        encoding: Some(UiTransactionEncoding::Base64),
+        commitment: Some(history_commitment(settings.commitment)),
+        max_supported_transaction_version: Some(0),
+    };
+    let mut payments = vec![];
+    for status in history.iter().filter(|status| status.err.is_none()) {
+        let transaction = rpc.get_transaction_with_config(&Signature::from_str(&status.signature)?, config)?;
+        let logs: Option<Vec<String>> = transaction.transaction.meta.and_then(|meta| meta.log_messages.into());
+        // a transaction paying for several farms is in the history of each, only count this one
+        payments.extend(parse_logs(&logs.unwrap_or_default()).farm_events(&settings.program_id).into_iter().filter_map(
+            |FarmEvent::FarmFeePaid { farm, fee_vault, fee_vault_amount, .. }| {
+                (farm == *farm_id).then_some((fee_vault, fee_vault_amount))
+            },
+        ));
+    }
+    Ok(payments)
+}
+
+/// reads the farms, their fee vaults and, for enabled farms, their payment history
+fn snapshot(rpc: &RpcClient, settings: &Settings) -> Result<Snapshot, Box<dyn Error>> {
+    let client = farm_client(rpc, settings);
+    let farms = rpc.runtime().block_on(client.get_farms()).map_err(|err| explain(settings, err))?;
+
+    let fee_vault_ids: Vec<Pubkey> = farms.iter().map(|(_, farm)| farm.fee_vault).collect::<BTreeSet<_>>().into_iter().collect();
+    let mut fee_vaults = HashMap::new();
+    for chunk in fee_vault_ids.chunks(MAX_MULTIPLE_ACCOUNTS) {
+        for (address, account) in chunk.iter().zip(rpc.get_multiple_accounts(chunk)?) {
+            fee_vaults.extend(account.map(|account| (*address, account)));
+        }
+    }
+
+    // a farm is only enabled by paying, so the disabled ones have no payments to read
+    let mut paid_farms = HashSet::new();
+    let mut vault_payments: HashMap<Pubkey, u64> = HashMap::new();
+    for (farm_id, _) in farms.iter().filter(|(_, farm)| farm.enabled == 1) {
+        let payments = fee_payments(rpc, settings, farm_id)?;
+        if !payments.is_empty() {
+            paid_farms.insert(*farm_id);
+        }
+        for (fee_vault, amount) in payments {
+            let paid = vault_payments.entry(fee_vault).or_default();
+            *paid = paid.saturating_add(amount);
+        }
+    }
+    Ok(Snapshot { farms, fee_vaults, paid_farms, vault_payments })
+}
+
+/// prints the report as JSON and fails when it has findings, so a scheduled run exits non-zero
+pub fn audit(settings: &Settings) -> Result<(), Box<dyn Error>> {
+    let rpc = rpc_client(settings);
+    let slot = rpc.get_slot()?;
+    let snapshot = snapshot(&rpc, settings)?;
+    let findings = check(&settings.program_id, &snapshot);
+    let report = Report {
+        program_id: settings.program_id.to_string(),
+        slot,
+        farms: snapshot.farms.len(),
+        enabled_farms: snapshot.farms.iter().filter(|(_, farm)| farm.enabled == 1).count(),
+        passed: findings.is_empty(),
+        findings,
+    };
+    println!("{}", serde_json::to_string_pretty(&report)?);
+    if !report.passed {
+        return Err(format!("{} invariant violations", report.findings.len()).into());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        ctf_solana_farm::{
+            constant::{FARM_DISCRIMINATOR, FARM_FEE, FARM_VERSION},
+            instruction::find_farm_authority,
+        },
+        spl_token::state::AccountState,
+    };
+
+    fn vault(owner: Pubkey, amount: u64) -> Account {
+        let mut data = vec![0; TokenAccount::LEN];
+        let token_account = TokenAccount {
+            mint: Pubkey::new_unique(),
+            owner,
+            amount,
+            state: AccountState::Initialized,
+            ..TokenAccount::default()
+        };
+        TokenAccount::pack(token_account, &mut data).unwrap();
+        Account { lamports: 1, data, owner: spl_token::id(), executable: false, rent_epoch: 0 }
+    }
+
+    /// a farm with its own fee vault, an enabled one is paid and its vault holds the fee
+    fn add_farm(program_id: &Pubkey, snapshot: &mut Snapshot, enabled: bool) -> Pubkey {
+        let (farm_id, fee_vault) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let (authority, nonce) = find_farm_authority(&farm_id, program_id);
+        let farm = Farm {
+            discriminator: FARM_DISCRIMINATOR,
+            version: FARM_VERSION,
+            enabled: enabled as u8,
+            nonce,
+            token_program_id: spl_token::id(),
+            creator: Pubkey::new_unique(),
+            fee_vault,
+            pending_creator: Pubkey::default(),
+            duration: 86_400,
+            operator: Pubkey::default(),
+            paused: 0,
+            ends_at: 0,
+        };
+        snapshot.farms.push((farm_id, farm));
+        snapshot.fee_vaults.insert(fee_vault, vault(authority, if enabled { FARM_FEE } else { 0 }));
+        if enabled {
+            snapshot.paid_farms.insert(farm_id);
+            snapshot.vault_payments.insert(fee_vault, FARM_FEE);
+        }
+        farm_id
+    }
+
+    fn checks(findings: &[Finding]) -> Vec<Check> {
+        findings.iter().map(|finding| finding.check).collect()
+    }
+
+    #[test]
+    fn test_paid_farms_pass() {
+        let program_id = Pubkey::new_unique();
+        let mut snapshot = Snapshot::default();
+        add_farm(&program_id, &mut snapshot, true);
+        // a disabled farm needs neither a payment nor a funded vault
+        add_farm(&program_id, &mut snapshot, false);
+        assert_eq!(check(&program_id, &snapshot), vec![]);
+    }
+
+    #[test]
+    fn test_broken_farm() {
+        let program_id = Pubkey::new_unique();
+        let mut snapshot = Snapshot::default();
+        let farm_id = add_farm(&program_id, &mut snapshot, true);
+        let farm = &mut snapshot.farms[0].1;
+        farm.token_program_id = Pubkey::new_unique();
+        let fee_vault = farm.fee_vault;
+        snapshot.fee_vaults.insert(fee_vault, vault(Pubkey::new_unique(), FARM_FEE));
+        snapshot.paid_farms.clear();
+
+        let findings = check(&program_id, &snapshot);
+        assert_eq!(checks(&findings), vec![Check::TokenProgram, Check::FeeVaultOwner, Check::UnpaidFarm]);
+        assert!(findings.iter().all(|finding| finding.account == farm_id.to_string()));
+
+        // a nonce off the curve leaves no authority to compare the vault owner with
+        let nonce = (0..=u8::MAX)
+            .find(|nonce| authority_id(&program_id, &farm_id, *nonce).is_err())
+            .unwrap();
+        snapshot.farms[0].1.nonce = nonce;
+        snapshot.fee_vaults.clear();
+        assert_eq!(
+            checks(&check(&program_id, &snapshot)),
+            vec![Check::AuthorityNonce, Check::TokenProgram, Check::FeeVaultMissing, Check::UnpaidFarm]
+        );
+    }
+
+    #[test]
+    fn test_shared_fee_vault_balance() {
+        let program_id = Pubkey::new_unique();
+        let mut snapshot = Snapshot::default();
+        add_farm(&program_id, &mut snapshot, true);
+        // a second enabled farm pays into the same vault, which only holds one fee
+        let (other_id, mut other) = (Pubkey::new_unique(), snapshot.farms[0].1.clone());
+        other.nonce = find_farm_authority(&other_id, &program_id).1;
+        snapshot.farms.push((other_id, other.clone()));
+        snapshot.paid_farms.insert(other_id);
+        *snapshot.vault_payments.get_mut(&other.fee_vault).unwrap() += FARM_FEE;
+
+        let findings = check(&program_id, &snapshot);
+        // the vault belongs to the authority of the first farm
+        assert_eq!(checks(&findings), vec![Check::FeeVaultOwner, Check::FeeVaultBalance]);
+        assert_eq!(findings[0].account, other_id.to_string());
+        assert_eq!(findings[1].account, other.fee_vault.to_string());
+    }
+
+    #[test]
+    fn test_referral_split_fee_vault_balance() {
+        let program_id = Pubkey::new_unique();
+        let mut snapshot = Snapshot::default();
+        add_farm(&program_id, &mut snapshot, true);
+        // a referrer took 500 of the fee, so the vault only got the rest
+        let fee_vault = snapshot.farms[0].1.fee_vault;
+        let authority = find_farm_authority(&snapshot.farms[0].0, &program_id).0;
+        snapshot.vault_payments.insert(fee_vault, FARM_FEE - 500);
+        snapshot.fee_vaults.insert(fee_vault, vault(authority, FARM_FEE - 500));
+        assert_eq!(check(&program_id, &snapshot), vec![]);
+
+        snapshot.fee_vaults.insert(fee_vault, vault(authority, FARM_FEE - 501));
+        let findings = check(&program_id, &snapshot);
+        assert_eq!(checks(&findings), vec![Check::FeeVaultBalance]);
+        assert_eq!(findings[0].account, fee_vault.to_string());
+    }
+}
@@ -0,0 +1,258 @@
+use {
+    crate::{config::Settings, decode::TransactionFailure},
+    borsh::BorshDeserialize,
+    ctf_solana_farm::{
+        client::{farm_filters, FarmClient, FarmClientError},
+        instruction::{find_farm_authority, find_fee_schedule_address, ix_create_farm, ix_pay_create_fee, ix_pay_create_fee_with_referrer},
+        state::{Farm, FarmAccount, FeeSchedule},
+    },
+    farm_core::pda::authority_id,
+    solana_client::{
+        nonblocking::rpc_client::RpcClient as NonblockingRpcClient,
+        rpc_client::RpcClient,
+        rpc_config::{RpcAccountInfoConfig, RpcProgramAccountsConfig},
+    },
+    solana_program::program_pack::Pack,
+    solana_sdk::{
+        instruction::Instruction,
+        native_token::{lamports_to_sol, sol_to_lamports},
+        pubkey::Pubkey,
+        signature::{read_keypair_file, Keypair, Signature, Signer},
+        system_instruction,
+    },
+    spl_token::state::Account as TokenAccount,
+    std::{error::Error, sync::Arc},
+};
+
+type CommandResult = Result<(), Box<dyn Error>>;
+
+pub fn rpc_client(settings: &Settings) -> RpcClient {
+    RpcClient::new_with_commitment(settings.json_rpc_url.clone(), settings.commitment)
+}
+
+pub fn read_keypair(path: &str) -> Result<Keypair, Box<dyn Error>> {
+    read_keypair_file(path).map_err(|err| format!("can't read keypair {}: {}", path, err).into())
+}
+
+/// fee payer and creator, the creator is the payer unless another keypair is given
+fn payer_and_creator(settings: &Settings, creator: Option<&str>) -> Result<(Keypair, Option<Keypair>), Box<dyn Error>> {
+    let payer = read_keypair(&settings.keypair_path)?;
+    let creator = creator.map(read_keypair).transpose()?;
+    Ok((payer, creator))
+}
+
+/// signs with the payer and `signers`, sends and waits for confirmation
+/// goes through `FarmClient::send`, so the compute budget and resends follow `settings.send_options`
+/// a failed transaction comes back as a `TransactionFailure` explaining what the farm program rejected
+pub fn send(
+    rpc: &RpcClient,
+    settings: &Settings,
+    payer: &Keypair,
+    instructions: &[Instruction],
+    signers: &[&Keypair],
+) -> Result<Signature, Box<dyn Error>> {
+    let client = farm_client(rpc, settings);
+    rpc.runtime()
+        .block_on(client.send(payer, instructions, signers))
+        .map_err(|err| explain(settings, err))
+}
+
+/// `FarmClient` sharing the connection of the blocking `rpc`, run its futures on `rpc.runtime()`
+pub fn farm_client(rpc: &RpcClient, settings: &Settings) -> FarmClient<Arc<NonblockingRpcClient>> {
+    FarmClient::new(settings.program_id, rpc.get_inner_client().clone()).send_options(settings.send_options.clone())
+}
+
+/// a rejected transaction becomes a `TransactionFailure`, other errors are kept as they are
+pub fn explain(settings: &Settings, err: FarmClientError) -> Box<dyn Error> {
+    match TransactionFailure::from_farm_client_error(&settings.program_id, &err) {
+        Some(failure) => failure.into(),
+        None => err.into(),
+    }
+}
+
+/// fails on an account the program doesn't own
+pub fn get_farm(rpc: &RpcClient, settings: &Settings, farm_id: &Pubkey) -> Result<Farm, Box<dyn Error>> {
+    rpc.runtime().block_on(farm_client(rpc, settings).get_farm(farm_id)).map_err(|err| explain(settings, err))
+}
+
+/// addresses of a farm made by `create_farm_accounts`
+pub struct CreatedFarm {
+    pub farm: Pubkey,
+    pub authority: Pubkey,
+    pub nonce: u8,
+    pub fee_vault: Pubkey,
+    pub signature: Signature,
+}
+
+/// allocates the farm and a fee vault owned by its authority, then creates the farm
+pub fn create_farm_accounts(
+    rpc: &RpcClient,
+    settings: &Settings,
+    payer: &Keypair,
+    creator: &Keypair,
+    fee_mint: &Pubkey,
+    duration: u64,
+) -> Result<CreatedFarm, Box<dyn Error>> {
+    let farm = Keypair::new();
+    let fee_vault = Keypair::new();
+    let program_id = &settings.program_id;
+    let (authority, nonce) = find_farm_authority(&farm.pubkey(), program_id);
+
+    let instructions = [
+        system_instruction::create_account(
+            &payer.pubkey(),
+            &farm.pubkey(),
+            rpc.get_minimum_balance_for_rent_exemption(FarmAccount::LEN)?,
+            FarmAccount::LEN as u64,
+            program_id,
+        ),
+        system_instruction::create_account(
+            &payer.pubkey(),
+            &fee_vault.pubkey(),
+            rpc.get_minimum_balance_for_rent_exemption(TokenAccount::LEN)?,
+            TokenAccount::LEN as u64,
+            &spl_token::id(),
+        ),
+        spl_token::instruction::initialize_account3(&spl_token::id(), &fee_vault.pubkey(), fee_mint, &authority)?,
+        ix_create_farm(
+            &farm.pubkey(),
+            &authority,
+            &creator.pubkey(),
+            &fee_vault.pubkey(),
+            &spl_token::id(),
+            program_id,
+            nonce,
+            duration,
+        ),
+    ];
+    let signature = send(rpc, settings, payer, &instructions, &[&farm, &fee_vault, creator])?;
+    Ok(CreatedFarm { farm: farm.pubkey(), authority, nonce, fee_vault: fee_vault.pubkey(), signature })
+}
+
+pub fn create_farm(settings: &Settings, fee_mint: &Pubkey, duration: u64, creator: Option<&str>) -> CommandResult {
+    let rpc = rpc_client(settings);
+    let (payer, creator) = payer_and_creator(settings, creator)?;
+    let creator = creator.as_ref().unwrap_or(&payer);
+    let created = create_farm_accounts(&rpc, settings, &payer, creator, fee_mint, duration)?;
+    println!("Signature: {}", created.signature);
+    println!("Farm: {}", created.farm);
+    println!("Authority: {}", created.authority);
+    println!("Fee vault: {}", created.fee_vault);
+    Ok(())
+}
+
+/// fee the schedule asks of `farm` when paid from `token_account`
+pub fn expected_fee(rpc: &RpcClient, settings: &Settings, farm: &Farm, token_account: &Pubkey) -> Result<u64, Box<dyn Error>> {
+    let mint = TokenAccount::unpack(&rpc.get_account_data(token_account)?)?.mint;
+    let fee_schedule_data = rpc.get_account_data(&find_fee_schedule_address(&settings.program_id).0)?;
+    FeeSchedule::deserialize(&mut &fee_schedule_data[..])?
+        .expected_fee(&mint, farm.duration)
+        .ok_or_else(|| format!("the fee schedule doesn't accept {}", mint).into())
+}
+
+pub fn pay_fee(
+    settings: &Settings,
+    farm_id: &Pubkey,
+    token_account: &Pubkey,
+    referrer: Option<&Pubkey>,
+    amount: Option<u64>,
+    creator: Option<&str>,
+) -> CommandResult {
+    let rpc = rpc_client(settings);
+    let (payer, creator) = payer_and_creator(settings, creator)?;
+    let creator = creator.as_ref().unwrap_or(&payer);
+    let farm = get_farm(&rpc, settings, farm_id)?;
+    let authority = authority_id(&settings.program_id, farm_id, farm.nonce)?;
+
+    let amount = match amount {
+        Some(amount) => amount,
+        None => expected_fee(&rpc, settings, &farm, token_account)?,
+    };
+    let instruction = match referrer {
+        Some(referrer) => ix_pay_create_fee_with_referrer(
+            farm_id,
+            &authority,
+            &creator.pubkey(),
+            token_account,
+            &farm.fee_vault,
+            &farm.token_program_id,
+            &settings.program_id,
+            referrer,
+            amount,
+        ),
+        None => ix_pay_create_fee(
+            farm_id,
+            &authority,
+            &creator.pubkey(),
+            token_account,
+            &farm.fee_vault,
+            &farm.token_program_id,
+            &settings.program_id,
+            amount,
+        ),
+    };
+    let signature = send(&rpc, settings, &payer, &[instruction], &[creator])?;
+    println!("Signature: {}", signature);
+    println!("Paid {} for farm {}", amount, farm_id);
+    Ok(())
+}
+
+fn print_farm(farm_id: &Pubkey, farm: &Farm) {
+    println!("Farm: {}", farm_id);
+    println!("  Enabled: {}", farm.enabled == 1);
+    println!("  Nonce: {}", farm.nonce);
+    println!("  Creator: {}", farm.creator);
+    if farm.pending_creator != Pubkey::default() {
+        println!("  Pending creator: {}", farm.pending_creator);
+    }
+    println!("  Fee vault: {}", farm.fee_vault);
+    println!("  Token program: {}", farm.token_program_id);
+    println!("  Duration: {}s", farm.duration);
+    if farm.ends_at != 0 {
+        println!("  Paid until: {} (unix time)", farm.ends_at);
+    }
+    if farm.operator != Pubkey::default() {
+        println!("  Operator: {}", farm.operator);
+    }
+    println!("  Paused: {}", farm.paused == 1);
+}
+
+pub fn show_farm(settings: &Settings, farm_id: &Pubkey) -> CommandResult {
+    let rpc = rpc_client(settings);
+    print_farm(farm_id, &get_farm(&rpc, settings, farm_id)?);
+    Ok(())
+}
+
+pub fn list_farms(settings: &Settings) -> CommandResult {
+    let rpc = rpc_client(settings);
+    let config = RpcProgramAccountsConfig {
+        filters: Some(farm_filters()),
+        account_config: RpcAccountInfoConfig {
+            commitment: Some(settings.commitment),
+            ..RpcAccountInfoConfig::default()
+        },
+        ..RpcProgramAccountsConfig::default()
+    };
+    let accounts = rpc.get_program_accounts_with_config(&settings.program_id, config)?;
+    for (farm_id, account) in &accounts {
+        match Farm::try_from_slice(&account.data) {
+            Ok(farm) => print_farm(farm_id, &farm),
+            Err(err) => eprintln!("Skipping {}: {}", farm_id, err),
+        }
+    }
+    println!("{} farms", accounts.len());
+    Ok(())
+}
+
+pub fn airdrop(settings: &Settings, amount: f64, to: Option<&Pubkey>) -> CommandResult {
+    let rpc = rpc_client(settings);
+    let recipient = match to {
+        Some(recipient) => *recipient,
+        None => read_keypair(&settings.keypair_path)?.pubkey(),
+    };
+    let signature = rpc.request_airdrop(&recipient, sol_to_lamports(amount))?;
+    rpc.poll_for_signature_with_commitment(&signature, settings.commitment)?;
+    println!("Signature: {}", signature);
+    println!("Balance of {}: {} SOL", recipient, lamports_to_sol(rpc.get_balance(&recipient)?));
+    Ok(())
+}
@@ -0,0 +1,154 @@
+use {
+    ctf_solana_farm::client::SendOptions,
+    serde::Deserialize,
+    solana_sdk::{commitment_config::CommitmentConfig, pubkey::Pubkey},
+    std::{error::Error, fs::File, path::Path, str::FromStr},
+};
+
+/// program id of the farm deployed on the shared localnet
+pub const DEFAULT_PROGRAM_ID: &str = "x5i6Ec4fuLkEBB9cr7PRRndzLk4uPFkskDzWnPeyXHi";
+
+/// client config file, every field is optional
+///
+/// ```yaml
+/// json_rpc_url: http://127.0.0.1:8899
+/// keypair_path: ./keypair_payer
+/// program_id: x5i6Ec4fuLkEBB9cr7PRRndzLk4uPFkskDzWnPeyXHi
+/// commitment: confirmed
+/// ```
+#[derive(Debug, Default, Deserialize)]
+pub struct ConfigFile {
+    pub json_rpc_url: Option<String>,
+    pub keypair_path: Option<String>,
+    pub program_id: Option<String>,
+    pub commitment: Option<String>,
+}
+
+impl ConfigFile {
+    pub fn load(path: &Path) -> Result<Self, Box<dyn Error>> {
+        let file = File::open(path).map_err(|err| format!("can't open {}: {}", path.display(), err))?;
+        Ok(serde_yaml::from_reader(file)?)
+    }
+}
+
+/// settings every subcommand runs with
+#[derive(Debug)]
+pub struct Settings {
+    pub json_rpc_url: String,
+    pub keypair_path: String,
+    pub program_id: Pubkey,
+    pub commitment: CommitmentConfig,
+    /// compute budget and resends, only set from flags
+    pub send_options: SendOptions,
+}
+
+impl Settings {
+    /// flags win over the client config file, which wins over the solana CLI config
+    pub fn resolve(
+        config_file: Option<&Path>,
+        json_rpc_url: Option<String>,
+        keypair_path: Option<String>,
+        program_id: Option<String>,
+        commitment: Option<String>,
+    ) -> Result<Self, Box<dyn Error>> {
+        let file = match config_file {
+            Some(path) => ConfigFile::load(path)?,
+            None => ConfigFile::default(),
+        };
+        let solana_config = solana_cli_config::CONFIG_FILE
+            .as_ref()
+            .and_then(|path| solana_cli_config::Config::load(path).ok())
+            .unwrap_or_default();
+        let flags = ConfigFile { json_rpc_url, keypair_path, program_id, commitment };
+        Self::layered(flags, file, solana_config)
+    }
+
+    /// each setting comes from the first layer that has it
+    fn layered(
+        flags: ConfigFile,
+        file: ConfigFile,
+        solana_config: solana_cli_config::Config,
+    ) -> Result<Self, Box<dyn Error>> {
+        let program_id = flags
+            .program_id
+            .or(file.program_id)
+            .unwrap_or_else(|| DEFAULT_PROGRAM_ID.to_string());
+        let commitment = flags
+            .commitment
+            .or(file.commitment)
+            .unwrap_or(solana_config.commitment);
+        Ok(Settings {
+            json_rpc_url: flags
+                .json_rpc_url
+                .or(file.json_rpc_url)
+                .unwrap_or(solana_config.json_rpc_url),
+            keypair_path: flags
+                .keypair_path
+                .or(file.keypair_path)
+                .unwrap_or(solana_config.keypair_path),
+            program_id: Pubkey::from_str(&program_id)
+                .map_err(|err| format!("invalid program id {}: {}", program_id, err))?,
+            commitment: CommitmentConfig::from_str(&commitment)
+                .map_err(|err| format!("invalid commitment {}: {}", commitment, err))?,
+            send_options: SendOptions::default(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, std::fs};
+
+    fn solana_config() -> solana_cli_config::Config {
+        solana_cli_config::Config {
+            json_rpc_url: "http://solana-cli:8899".to_string(),
+            keypair_path: "solana-cli.json".to_string(),
+            commitment: "finalized".to_string(),
+            ..solana_cli_config::Config::default()
+        }
+    }
+
+    fn config_file() -> ConfigFile {
+        let path = std::env::temp_dir().join(format!("client-farm-config-{}.yml", std::process::id()));
+        fs::write(
+            &path,
+            "json_rpc_url: http://config-file:8899\nkeypair_path: config-file.json\ncommitment: confirmed\n",
+        )
+        .unwrap();
+        let file = ConfigFile::load(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        file
+    }
+
+    #[test]
+    fn test_settings_precedence() {
+        let program_id = Pubkey::new_unique();
+        let flags = ConfigFile {
+            json_rpc_url: Some("http://flag:8899".to_string()),
+            program_id: Some(program_id.to_string()),
+            ..ConfigFile::default()
+        };
+
+        // flags win over the config file, which wins over the solana CLI config
+        let settings = Settings::layered(flags, config_file(), solana_config()).unwrap();
+        assert_eq!(settings.json_rpc_url, "http://flag:8899");
+        assert_eq!(settings.program_id, program_id);
+        assert_eq!(settings.keypair_path, "config-file.json");
+        assert_eq!(settings.commitment, CommitmentConfig::confirmed());
+
+        // without a config file the solana CLI config fills in
+        let settings = Settings::layered(ConfigFile::default(), ConfigFile::default(), solana_config()).unwrap();
+        assert_eq!(settings.json_rpc_url, "http://solana-cli:8899");
+        assert_eq!(settings.keypair_path, "solana-cli.json");
+        assert_eq!(settings.commitment, CommitmentConfig::finalized());
+        assert_eq!(settings.program_id, Pubkey::from_str(DEFAULT_PROGRAM_ID).unwrap());
+    }
+
+    #[test]
+    fn test_settings_reject_invalid_values() {
+        let flags = ConfigFile { commitment: Some("soon".to_string()), ..ConfigFile::default() };
+        assert!(Settings::layered(flags, ConfigFile::default(), solana_config()).is_err());
+        let file = ConfigFile { program_id: Some("not a pubkey".to_string()), ..ConfigFile::default() };
+        assert!(Settings::layered(ConfigFile::default(), file, solana_config()).is_err());
+    }
+}
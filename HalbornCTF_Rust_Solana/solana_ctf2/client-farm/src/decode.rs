@@ -0,0 +1,399 @@
+use {
+    base64::{prelude::BASE64_STANDARD, Engine},
+    borsh::BorshDeserialize,
+    ctf_solana_farm::{client::FarmClientError, error::FarmError, event::FarmEvent, instruction::FarmInstruction},
+    num_traits::FromPrimitive,
+    solana_sdk::{
+        instruction::InstructionError, message::Message, pubkey::Pubkey, transaction::TransactionError,
+    },
+    spl_token::error::TokenError,
+    std::{fmt, str::FromStr},
+};
+
+/// the farm error a failed transaction carries
+/// a custom error code doesn't tell which program raised it, a token program error
+/// inside PayFarmFee comes back with the same code, `TransactionFailure` uses the logs to tell
+pub fn decode_farm_error(error: &TransactionError) -> Option<FarmError> {
+    match error {
+        TransactionError::InstructionError(_, InstructionError::Custom(code)) => FarmError::from_u32(*code),
+        _ => None,
+    }
+}
+
+/// one `Program log:` or `Program data:` line and the program that wrote it
+#[derive(Clone, Debug, PartialEq)]
+pub struct ProgramLog {
+    pub program_id: Pubkey,
+    /// invocation depth, 1 for the instruction itself, more for cross-program invocations
+    pub depth: usize,
+    pub message: String,
+    /// a `Program data:` line, the message is base64
+    pub data: bool,
+}
+
+/// the first program that failed and the reason the runtime logged
+#[derive(Clone, Debug, PartialEq)]
+pub struct ProgramFailure {
+    pub program_id: Pubkey,
+    pub depth: usize,
+    pub reason: String,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ParsedLogs {
+    pub logs: Vec<ProgramLog>,
+    pub failure: Option<ProgramFailure>,
+}
+
+impl ParsedLogs {
+    /// messages the farm program logged, without the ones of the programs it invoked
+    pub fn program_messages<'a>(&'a self, program_id: &'a Pubkey) -> impl Iterator<Item = &'a str> {
+        self.logs.iter().filter(move |log| log.program_id == *program_id).map(|log| log.message.as_str())
+    }
+
+    /// events the farm program logged, data it doesn't decode as a `FarmEvent` is skipped
+    pub fn farm_events(&self, program_id: &Pubkey) -> Vec<FarmEvent> {
+        self.logs
+            .iter()
+            .filter(|log| log.data && log.program_id == *program_id)
+            .filter_map(|log| BASE64_STANDARD.decode(log.message.split(' ').next()?).ok())
+            .filter_map(|bytes| FarmEvent::try_from_slice(&bytes).ok())
+            .collect()
+    }
+}
+
+/// follows the invocation stack through the transaction logs
+pub fn parse_logs(logs: &[String]) -> ParsedLogs {
+    let mut parsed = ParsedLogs::default();
+    let mut stack: Vec<Pubkey> = vec![];
+    for line in logs {
+        let message = match line.strip_prefix("Program data: ") {
+            Some(data) => Some((data, true)),
+            None => line.strip_prefix("Program log: ").map(|message| (message, false)),
+        };
+        if let Some((message, data)) = message {
+            if let Some(program_id) = stack.last() {
+                parsed.logs.push(ProgramLog { program_id: *program_id, depth: stack.len(), message: message.to_string(), data });
+            }
+            continue;
+        }
+        let mut words = match line.strip_prefix("Program ") {
+            Some(rest) => rest.splitn(2, ' '),
+            None => continue,
+        };
+        let (program_id, rest) = match (words.next().map(Pubkey::from_str), words.next()) {
+            (Some(Ok(program_id)), Some(rest)) => (program_id, rest),
+            _ => continue,
+        };
+        if rest.starts_with("invoke [") {
+            stack.push(program_id);
+        } else if rest == "success" {
+            stack.pop();
+        } else if let Some(reason) = rest.strip_prefix("failed: ") {
+            if parsed.failure.is_none() {
+                parsed.failure = Some(ProgramFailure { program_id, depth: stack.len(), reason: reason.to_string() });
+            }
+            stack.pop();
+        }
+    }
+    parsed
+}
+
+/// names of the accounts an instruction takes, in order, as documented on `FarmInstruction`
+pub fn account_names(instruction: &FarmInstruction) -> &'static [&'static str] {
+    match instruction {
+        FarmInstruction::Create { .. } => &["farm", "farm authority", "farm creator", "program config", "fee vault", "token program"],
+        FarmInstruction::PayFarmFee(_) => &[
+            "farm",
+            "farm authority",
+            "farm creator",
+            "creator token account",
+            "fee vault",
+            "token program",
+            "program config",
+            "fee schedule",
+            "referrer token account",
+        ],
+        FarmInstruction::InitializeConfig => {
+            &["program config", "admin", "system program", "program data", "upgrade authority"]
+        }
+        FarmInstruction::SetPaused(_) | FarmInstruction::TransferAdmin(_) => &["program config", "admin"],
+        FarmInstruction::SetReferralShare(_) => &["program config", "admin", "system program"],
+        FarmInstruction::SetFeeSchedule { .. } => &["fee schedule", "program config", "admin", "system program"],
+        FarmInstruction::ProposeCreator(_) => &["farm", "farm creator", "program config"],
+        FarmInstruction::AcceptCreator => &["farm", "pending creator", "program config"],
+        FarmInstruction::SetFarmMetadata { .. } | FarmInstruction::UpdateFarmMetadata { .. } => {
+            &["farm metadata", "farm", "farm creator", "program config", "system program"]
+        }
+        FarmInstruction::SetOperator(_) => &["farm", "farm creator", "program config"],
+        FarmInstruction::RevokeOperator | FarmInstruction::SetFarmPaused(_) => {
+            &["farm", "farm creator or operator", "program config"]
+        }
+    }
+}
+
+/// position of the account whose check raised `error`, when one account is to blame
+pub fn blamed_account(instruction: &FarmInstruction, error: &FarmError) -> Option<usize> {
+    if matches!(error, FarmError::ProgramPaused) {
+        return instruction.config_account_index();
+    }
+    if matches!(error, FarmError::Unauthorized) {
+        return instruction.signer_account_index();
+    }
+    match (instruction, error) {
+        (FarmInstruction::Create { .. }, FarmError::AlreadyInUse) => Some(0),
+        (FarmInstruction::Create { .. }, FarmError::InvalidProgramAddress) => Some(1),
+        (FarmInstruction::Create { .. }, FarmError::SignatureMissing) => Some(2),
+        (FarmInstruction::Create { .. }, FarmError::InvalidProgramConfig) => Some(3),
+        (FarmInstruction::Create { .. }, FarmError::InvalidFeeAccount) => Some(4),
+        (FarmInstruction::PayFarmFee(_), FarmError::AlreadyInUse | FarmError::FarmPaused) => Some(0),
+        (FarmInstruction::PayFarmFee(_), FarmError::InvalidProgramAddress) => Some(1),
+        (FarmInstruction::PayFarmFee(_), FarmError::SignatureMissing) => Some(2),
+        (FarmInstruction::PayFarmFee(_), FarmError::UnsupportedFeeMint) => Some(3),
+        (FarmInstruction::PayFarmFee(_), FarmError::InvalidFeeAccount) => Some(4),
+        (FarmInstruction::PayFarmFee(_), FarmError::InvalidProgramConfig) => Some(6),
+        (FarmInstruction::PayFarmFee(_), FarmError::InvalidFeeSchedule) => Some(7),
+        (FarmInstruction::PayFarmFee(_), FarmError::InvalidReferrer) => Some(8),
+        (FarmInstruction::InitializeConfig, FarmError::AlreadyInUse | FarmError::InvalidProgramConfig) => Some(0),
+        (FarmInstruction::InitializeConfig, FarmError::SignatureMissing) => Some(1),
+        (FarmInstruction::InitializeConfig, FarmError::WrongAdmin) => Some(4),
+        (
+            FarmInstruction::SetPaused(_) | FarmInstruction::TransferAdmin(_) | FarmInstruction::SetReferralShare(_),
+            FarmError::InvalidProgramConfig,
+        ) => Some(0),
+        (
+            FarmInstruction::SetPaused(_) | FarmInstruction::TransferAdmin(_) | FarmInstruction::SetReferralShare(_),
+            FarmError::SignatureMissing | FarmError::WrongAdmin,
+        ) => Some(1),
+        (FarmInstruction::SetFeeSchedule { .. }, FarmError::InvalidProgramConfig) => Some(1),
+        (FarmInstruction::SetFeeSchedule { .. }, FarmError::SignatureMissing | FarmError::WrongAdmin) => Some(2),
+        (FarmInstruction::ProposeCreator(_), FarmError::SignatureMissing) => Some(1),
+        (FarmInstruction::AcceptCreator, FarmError::NoPendingCreator) => Some(0),
+        (FarmInstruction::AcceptCreator, FarmError::SignatureMissing) => Some(1),
+        (
+            FarmInstruction::SetFarmMetadata { .. } | FarmInstruction::UpdateFarmMetadata { .. },
+            FarmError::InvalidFarmMetadata | FarmError::AlreadyInUse,
+        ) => Some(0),
+        (
+            FarmInstruction::SetFarmMetadata { .. } | FarmInstruction::UpdateFarmMetadata { .. },
+            FarmError::SignatureMissing,
+        ) => Some(2),
+        (
+            FarmInstruction::SetOperator(_) | FarmInstruction::RevokeOperator | FarmInstruction::SetFarmPaused(_),
+            FarmError::SignatureMissing,
+        ) => Some(1),
+        _ => None,
+    }
+}
+
+/// a failed transaction explained in terms of the farm program
+#[derive(Debug)]
+pub struct TransactionFailure {
+    pub error: TransactionError,
+    /// failing instruction and its program
+    pub instruction_index: Option<usize>,
+    pub program_id: Option<Pubkey>,
+    pub instruction: Option<FarmInstruction>,
+    pub farm_error: Option<FarmError>,
+    /// error of the token program when it is the one that failed
+    pub token_error: Option<TokenError>,
+    /// position, name and address of the account whose check failed
+    pub account: Option<(usize, &'static str, Pubkey)>,
+    /// what the failing program logged, its last `Error:` line is the human-readable reason
+    pub program_messages: Vec<String>,
+    pub failure: Option<ProgramFailure>,
+}
+
+impl TransactionFailure {
+    /// explains `error`, raised by `message`, with the logs the RPC node returned
+    pub fn new(farm_program_id: &Pubkey, message: &Message, error: TransactionError, logs: &[String]) -> Self {
+        let parsed = parse_logs(logs);
+        let (instruction_index, instruction_error) = match &error {
+            TransactionError::InstructionError(index, instruction_error) => (Some(*index as usize), Some(instruction_error)),
+            _ => (None, None),
+        };
+        let compiled = instruction_index.and_then(|index| message.instructions.get(index));
+        let program_id = compiled.map(|compiled| message.account_keys[compiled.program_id_index as usize]);
+        let instruction = compiled
+            .filter(|_| program_id == Some(*farm_program_id))
+            .and_then(|compiled| FarmInstruction::try_from_slice(&compiled.data).ok());
+
+        // a failure logged deeper than the instruction comes from a program the farm invoked
+        let failed_program = parsed.failure.as_ref().map(|failure| failure.program_id).or(program_id);
+        let code = match instruction_error {
+            Some(InstructionError::Custom(code)) => Some(*code),
+            _ => None,
+        };
+        let farm_error = decode_farm_error(&error).filter(|_| failed_program == Some(*farm_program_id));
+        let token_error = code.filter(|_| failed_program == Some(spl_token::id())).and_then(TokenError::from_u32);
+
+        let account = match (&instruction, &farm_error, compiled) {
+            (Some(instruction), Some(farm_error), Some(compiled)) => blamed_account(instruction, farm_error).and_then(|index| {
+                let key = message.account_keys.get(*compiled.accounts.get(index)? as usize)?;
+                Some((index, *account_names(instruction).get(index)?, *key))
+            }),
+            _ => None,
+        };
+        let program_messages = match failed_program {
+            Some(failed_program) => parsed.program_messages(&failed_program).map(str::to_string).collect(),
+            None => vec![],
+        };
+
+        TransactionFailure {
+            error,
+            instruction_index,
+            program_id,
+            instruction,
+            farm_error,
+            token_error,
+            account,
+            program_messages,
+            failure: parsed.failure,
+        }
+    }
+
+    /// explains a `FarmClient::send` error, None when no transaction was rejected
+    pub fn from_farm_client_error(farm_program_id: &Pubkey, error: &FarmClientError) -> Option<Self> {
+        match error {
+            FarmClientError::Rejected(rejected) => {
+                Some(Self::new(farm_program_id, &rejected.message, rejected.error.clone(), &rejected.logs))
+            }
+            _ => None,
+        }
+    }
+
+    /// explains `error` from the logs alone, as a logs subscription delivers them
+    /// without the message the failing instruction and account stay unknown
+    pub fn from_logs(farm_program_id: &Pubkey, error: TransactionError, logs: &[String]) -> Self {
+        Self::new(farm_program_id, &Message::default(), error, logs)
+    }
+
+    /// the last `Error: ...` line the failing program logged
+    pub fn reason(&self) -> Option<&str> {
+        self.program_messages.iter().rev().find_map(|message| message.strip_prefix("Error: "))
+    }
+}
+
+impl fmt::Display for TransactionFailure {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "transaction failed")?;
+        if let Some(index) = self.instruction_index {
+            write!(f, " in instruction {}", index)?;
+        }
+        if let Some(instruction) = &self.instruction {
+            let name = format!("{:?}", instruction);
+            write!(f, " ({})", name.split(|c: char| !c.is_alphanumeric()).next().unwrap_or_default())?;
+        }
+        match (&self.farm_error, &self.token_error) {
+            (Some(farm_error), _) => write!(f, ": farm error {:?}", farm_error)?,
+            (_, Some(token_error)) => write!(f, ": token program error: {}", token_error)?,
+            _ => write!(f, ": {}", self.error)?,
+        }
+        if let Some(reason) = self.reason() {
+            write!(f, ", {}", reason)?;
+        }
+        if let Some((index, name, key)) = &self.account {
+            write!(f, "\n  check failed on account {} ({}): {}", index, name, key)?;
+        }
+        if let Some(failure) = &self.failure {
+            write!(f, "\n  program {} failed: {}", failure.program_id, failure.reason)?;
+        }
+        for message in &self.program_messages {
+            write!(f, "\n  log: {}", message)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for TransactionFailure {}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        ctf_solana_farm::instruction::ix_pay_create_fee,
+        solana_sdk::{instruction::Instruction, signature::Keypair, signer::Signer},
+    };
+
+    fn logs(lines: &[&str]) -> Vec<String> {
+        lines.iter().map(|line| line.to_string()).collect()
+    }
+
+    fn pay_fee_message(program_id: &Pubkey, creator: &Pubkey) -> (Message, Instruction) {
+        let instruction = ix_pay_create_fee(
+            &Pubkey::new_unique(),
+            &Pubkey::new_unique(),
+            creator,
+            &Pubkey::new_unique(),
+            &Pubkey::new_unique(),
+            &spl_token::id(),
+            program_id,
+            5000,
+        );
+        (Message::new(&[instruction.clone()], Some(creator)), instruction)
+    }
+
+    #[test]
+    fn test_parse_logs_follows_invocations() {
+        let program_id = Pubkey::new_unique();
+        let parsed = parse_logs(&logs(&[
+            &format!("Program {} invoke [1]", program_id),
+            "Program log: Program paused: false",
+            &format!("Program {} invoke [2]", spl_token::id()),
+            "Program log: Instruction: Transfer",
+            "Program log: Error: insufficient funds",
+            &format!("Program {} consumed 2000 of 190000 compute units", spl_token::id()),
+            &format!("Program {} failed: custom program error: 0x1", spl_token::id()),
+            &format!("Program {} failed: custom program error: 0x1", program_id),
+        ]));
+        assert_eq!(parsed.program_messages(&program_id).collect::<Vec<_>>(), vec!["Program paused: false"]);
+        assert_eq!(parsed.logs[1], ProgramLog { program_id: spl_token::id(), depth: 2, message: "Instruction: Transfer".to_string(), data: false });
+        let failure = parsed.failure.unwrap();
+        assert_eq!((failure.program_id, failure.depth), (spl_token::id(), 2));
+    }
+
+    #[test]
+    fn test_failure_blames_account() {
+        let program_id = Pubkey::new_unique();
+        let creator = Keypair::new().pubkey();
+        let (message, instruction) = pay_fee_message(&program_id, &creator);
+        let error = TransactionError::InstructionError(0, InstructionError::Custom(FarmError::Unauthorized as u32));
+        assert_eq!(decode_farm_error(&error), Some(FarmError::Unauthorized));
+
+        let failure = TransactionFailure::new(
+            &program_id,
+            &message,
+            error,
+            &logs(&[
+                &format!("Program {} invoke [1]", program_id),
+                "Program log: Error: signer has no role on the farm that allows this instruction",
+                &format!("Program {} failed: custom program error: {:#x}", program_id, FarmError::Unauthorized as u32),
+            ]),
+        );
+        assert_eq!(failure.farm_error, Some(FarmError::Unauthorized));
+        assert_eq!(failure.reason(), Some("signer has no role on the farm that allows this instruction"));
+        assert_eq!(failure.account, Some((2, "farm creator", instruction.accounts[2].pubkey)));
+        assert!(failure.to_string().starts_with("transaction failed in instruction 0 (PayFarmFee): farm error Unauthorized, signer has no role"));
+    }
+
+    #[test]
+    fn test_token_program_error_is_not_a_farm_error() {
+        let program_id = Pubkey::new_unique();
+        let (message, _) = pay_fee_message(&program_id, &Pubkey::new_unique());
+        let failure = TransactionFailure::new(
+            &program_id,
+            &message,
+            TransactionError::InstructionError(0, InstructionError::Custom(1)),
+            &logs(&[
+                &format!("Program {} invoke [1]", program_id),
+                &format!("Program {} invoke [2]", spl_token::id()),
+                "Program log: Error: insufficient funds",
+                &format!("Program {} failed: custom program error: 0x1", spl_token::id()),
+                &format!("Program {} failed: custom program error: 0x1", program_id),
+            ]),
+        );
+        assert_eq!(failure.farm_error, None);
+        assert_eq!(failure.token_error, Some(TokenError::InsufficientFunds));
+        assert_eq!(failure.account, None);
+    }
+}
@@ -0,0 +1,278 @@
+use {
+    crate::{
+        commands::{create_farm_accounts, send},
+        config::Settings,
+    },
+    ctf_solana_farm::{
+        constant::FARM_FEE,
+        instruction::{find_config_address, find_fee_schedule_address, ix_initialize_config, ix_set_fee_schedule},
+        state::FeeMint,
+    },
+    serde::Serialize,
+    solana_client::rpc_client::RpcClient,
+    solana_program::program_pack::Pack,
+    solana_sdk::{
+        bpf_loader_upgradeable,
+        native_token::sol_to_lamports,
+        pubkey::Pubkey,
+        signature::{write_keypair_file, Keypair, Signer},
+        system_instruction,
+    },
+    solana_validator::test_validator::{TestValidator, TestValidatorGenesis, UpgradeableProgramInfo},
+    spl_token::state::{Account as TokenAccount, Mint},
+    std::{error::Error, fs, path::Path, thread, time::Duration},
+};
+
+/// decimals of the localnet fee mint
+const FEE_MINT_DECIMALS: u8 = 6;
+
+/// fee tokens minted to the creator, enough for a thousand farms
+const CREATOR_FEE_TOKENS: u64 = 1_000 * FARM_FEE;
+
+/// SOL moved from the genesis mint to the localnet payer
+const PAYER_SOL: f64 = 100.0;
+
+/// what `localnet` prints once the environment is ready
+/// the payer is the admin of the program and the creator of the farm
+#[derive(Debug, Serialize)]
+pub struct Manifest {
+    pub json_rpc_url: String,
+    pub websocket_url: String,
+    pub ledger_path: String,
+    pub client_config_path: String,
+    pub program_id: String,
+    pub program_config: String,
+    pub fee_schedule: String,
+    pub payer: String,
+    pub payer_keypair_path: String,
+    pub fee_mint: String,
+    pub creator_token_account: String,
+    pub farm: String,
+    pub farm_authority: String,
+    pub farm_nonce: u8,
+    pub farm_duration: u64,
+    pub fee_vault: String,
+    pub farm_fee: u64,
+}
+
+/// creates a mint with `authority` as mint authority
+fn create_mint(rpc: &RpcClient, settings: &Settings, payer: &Keypair, authority: &Pubkey) -> Result<Pubkey, Box<dyn Error>> {
+    let mint = Keypair::new();
+    let instructions = [
+        system_instruction::create_account(
+            &payer.pubkey(),
+            &mint.pubkey(),
+            rpc.get_minimum_balance_for_rent_exemption(Mint::LEN)?,
+            Mint::LEN as u64,
+            &spl_token::id(),
+        ),
+        spl_token::instruction::initialize_mint2(&spl_token::id(), &mint.pubkey(), authority, None, FEE_MINT_DECIMALS)?,
+    ];
+    send(rpc, settings, payer, &instructions, &[&mint])?;
+    Ok(mint.pubkey())
+}
+
+/// creates a token account of `mint` owned by `owner` and mints `amount` to it, the payer is the mint authority
+fn create_funded_token_account(
+    rpc: &RpcClient,
+    settings: &Settings,
+    payer: &Keypair,
+    mint: &Pubkey,
+    owner: &Pubkey,
+    amount: u64,
+) -> Result<Pubkey, Box<dyn Error>> {
+    let account = Keypair::new();
+    let instructions = [
+        system_instruction::create_account(
+            &payer.pubkey(),
+            &account.pubkey(),
+            rpc.get_minimum_balance_for_rent_exemption(TokenAccount::LEN)?,
+            TokenAccount::LEN as u64,
+            &spl_token::id(),
+        ),
+        spl_token::instruction::initialize_account3(&spl_token::id(), &account.pubkey(), mint, owner)?,
+        spl_token::instruction::mint_to(&spl_token::id(), mint, &account.pubkey(), &payer.pubkey(), &[], amount)?,
+    ];
+    send(rpc, settings, payer, &instructions, &[&account])?;
+    Ok(account.pubkey())
+}
+
+/// a localnet `bootstrap` set up, the validator stops when it is dropped
+pub struct Localnet {
+    pub test_validator: TestValidator,
+    /// admin of the program and creator of the farm
+    pub payer: Keypair,
+    pub manifest: Manifest,
+}
+
+/// boots an in-process validator with the farm program and SPL Token and sets up a farm waiting
+/// for its fee, writes the payer keypair, a client config and the manifest to `out_dir`
+/// the validator picks free ports when `rpc_port` is None
+pub fn bootstrap(
+    settings: &Settings,
+    program_so: &Path,
+    ledger: &Path,
+    rpc_port: Option<u16>,
+    out_dir: &Path,
+    duration: u64,
+) -> Result<Localnet, Box<dyn Error>> {
+    if !program_so.exists() {
+        return Err(format!("{} not found, build the program with `cargo build-sbf` first", program_so.display()).into());
+    }
+    fs::create_dir_all(out_dir)?;
+    // the setup below expects a fresh ledger, only wipe directories that hold a validator ledger
+    if TestValidatorGenesis::ledger_exists(ledger) {
+        fs::remove_dir_all(ledger)?;
+    }
+
+    // the genesis mint keypair only lives in this process, hand a payer keypair to the user,
+    // it is also the upgrade authority that signs off on the program config
+    let payer = Keypair::new();
+
+    // SPL Token is part of every test validator genesis
+    eprintln!("Starting validator, ledger in {}", ledger.display());
+    let mut genesis = TestValidatorGenesis::default();
+    genesis.ledger_path(ledger).add_upgradeable_programs_with_path(&[UpgradeableProgramInfo {
+        program_id: settings.program_id,
+        loader: bpf_loader_upgradeable::id(),
+        upgrade_authority: payer.pubkey(),
+        program_path: program_so.to_path_buf(),
+    }]);
+    if let Some(rpc_port) = rpc_port {
+        genesis.rpc_port(rpc_port);
+    }
+    let (test_validator, mint_keypair) = genesis.start();
+    let rpc = RpcClient::new_with_commitment(test_validator.rpc_url(), settings.commitment);
+
+    let payer_keypair_path = out_dir.join("payer.json");
+    write_keypair_file(&payer, &payer_keypair_path)?;
+    send(
+        &rpc,
+        settings,
+        &mint_keypair,
+        &[system_instruction::transfer(&mint_keypair.pubkey(), &payer.pubkey(), sol_to_lamports(PAYER_SOL))],
+        &[],
+    )?;
+
+    eprintln!("Initializing the program config and fee schedule");
+    send(&rpc, settings, &payer, &[ix_initialize_config(&payer.pubkey(), &payer.pubkey(), &settings.program_id)], &[])?;
+    let fee_mint = create_mint(&rpc, settings, &payer, &payer.pubkey())?;
+    let fee_mints = vec![FeeMint { mint: fee_mint, amount: FARM_FEE }];
+    send(&rpc, settings, &payer, &[ix_set_fee_schedule(&payer.pubkey(), &settings.program_id, vec![], fee_mints)], &[])?;
+
+    eprintln!("Creating the farm");
+    let creator_token_account = create_funded_token_account(&rpc, settings, &payer, &fee_mint, &payer.pubkey(), CREATOR_FEE_TOKENS)?;
+    let created = create_farm_accounts(&rpc, settings, &payer, &payer, &fee_mint, duration)?;
+
+    // lets the other subcommands talk to this validator with `-C <out_dir>/config.yml`
+    let client_config_path = out_dir.join("config.yml");
+    fs::write(
+        &client_config_path,
+        format!(
+            "json_rpc_url: {}\nkeypair_path: {}\nprogram_id: {}\ncommitment: {}\n",
+            test_validator.rpc_url(),
+            payer_keypair_path.display(),
+            settings.program_id,
+            settings.commitment.commitment,
+        ),
+    )?;
+
+    let manifest = Manifest {
+        json_rpc_url: test_validator.rpc_url(),
+        websocket_url: test_validator.rpc_pubsub_url(),
+        ledger_path: ledger.display().to_string(),
+        client_config_path: client_config_path.display().to_string(),
+        program_id: settings.program_id.to_string(),
+        program_config: find_config_address(&settings.program_id).0.to_string(),
+        fee_schedule: find_fee_schedule_address(&settings.program_id).0.to_string(),
+        payer: payer.pubkey().to_string(),
+        payer_keypair_path: payer_keypair_path.display().to_string(),
+        fee_mint: fee_mint.to_string(),
+        creator_token_account: creator_token_account.to_string(),
+        farm: created.farm.to_string(),
+        farm_authority: created.authority.to_string(),
+        farm_nonce: created.nonce,
+        farm_duration: duration,
+        fee_vault: created.fee_vault.to_string(),
+        farm_fee: FARM_FEE,
+    };
+    fs::write(out_dir.join("manifest.json"), serde_json::to_string_pretty(&manifest)?)?;
+    Ok(Localnet { test_validator, payer, manifest })
+}
+
+/// runs `bootstrap`, prints the manifest and keeps the validator running until Ctrl-C
+pub fn localnet(
+    settings: &Settings,
+    program_so: &Path,
+    ledger: &Path,
+    rpc_port: u16,
+    out_dir: &Path,
+    duration: u64,
+) -> Result<(), Box<dyn Error>> {
+    let localnet = bootstrap(settings, program_so, ledger, Some(rpc_port), out_dir, duration)?;
+    println!("{}", serde_json::to_string_pretty(&localnet.manifest)?);
+
+    eprintln!("Localnet ready, press Ctrl-C to stop");
+    loop {
+        thread::sleep(Duration::from_secs(60));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        borsh::BorshDeserialize,
+        crate::{
+            commands::get_farm,
+            config::{ConfigFile, DEFAULT_PROGRAM_ID},
+        },
+        ctf_solana_farm::{
+            client::SendOptions,
+            state::{FeeSchedule, ProgramConfig},
+        },
+        solana_sdk::{commitment_config::CommitmentConfig, signature::read_keypair_file},
+        std::{path::PathBuf, str::FromStr},
+    };
+
+    #[test]
+    fn test_bootstrap_sets_up_a_farm_waiting_for_its_fee() {
+        let dir = std::env::temp_dir().join(format!("client-farm-localnet-{}", std::process::id()));
+        let settings = Settings {
+            json_rpc_url: String::new(),
+            keypair_path: String::new(),
+            program_id: Pubkey::from_str(DEFAULT_PROGRAM_ID).unwrap(),
+            commitment: CommitmentConfig::confirmed(),
+            send_options: SendOptions::default(),
+        };
+        let program_so = PathBuf::from("../ctf/target/deploy/ctf_solana_farm.so");
+        let localnet = bootstrap(&settings, &program_so, &dir.join("ledger"), None, &dir.join("out"), 86_400).unwrap();
+        let manifest = &localnet.manifest;
+        let rpc = RpcClient::new_with_commitment(manifest.json_rpc_url.clone(), settings.commitment);
+        let pubkey = |address: &str| Pubkey::from_str(address).unwrap();
+
+        // the payer handed to the user is the admin and the creator
+        let payer = read_keypair_file(&manifest.payer_keypair_path).unwrap();
+        assert_eq!(payer.pubkey(), localnet.payer.pubkey());
+        let config = rpc.get_account(&pubkey(&manifest.program_config)).unwrap();
+        assert_eq!(ProgramConfig::unpack(&config.data).unwrap().admin, payer.pubkey());
+        let fee_schedule = rpc.get_account(&pubkey(&manifest.fee_schedule)).unwrap();
+        let fee_schedule = FeeSchedule::deserialize(&mut &fee_schedule.data[..]).unwrap();
+        assert_eq!(fee_schedule.mints, vec![FeeMint { mint: pubkey(&manifest.fee_mint), amount: FARM_FEE }]);
+
+        let farm = get_farm(&rpc, &settings, &pubkey(&manifest.farm)).unwrap();
+        assert_eq!((farm.creator, farm.enabled, farm.duration), (payer.pubkey(), 0, 86_400));
+        assert_eq!(farm.fee_vault, pubkey(&manifest.fee_vault));
+        let creator_tokens = rpc.get_account(&pubkey(&manifest.creator_token_account)).unwrap();
+        assert_eq!(TokenAccount::unpack(&creator_tokens.data).unwrap().amount, CREATOR_FEE_TOKENS);
+
+        // the client config points the other subcommands at this validator
+        let client_config = ConfigFile::load(Path::new(&manifest.client_config_path)).unwrap();
+        assert_eq!(client_config.json_rpc_url.as_deref(), Some(manifest.json_rpc_url.as_str()));
+        assert_eq!(client_config.program_id.as_deref(), Some(manifest.program_id.as_str()));
+        assert!(dir.join("out/manifest.json").exists());
+
+        drop(localnet);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,149 +1,244 @@
+mod audit;
+mod commands;
+mod config;
+mod decode;
+#[cfg(feature = "localnet")]
+mod localnet;
+mod offline;
+#[cfg(feature = "replay")]
+mod replay;
+mod watch;
+
 use {
-    assert_matches::*, borsh::BorshSerialize, ctf_solana_farm::{constant::FARM_FEE, error::FarmError, instruction::{ix_create_farm, ix_pay_create_fee, FarmInstruction}, state::Farm}, solana_program::{instruction::{AccountMeta, Instruction}, 
-        program_pack::Pack}, solana_program_test::{tokio::time::sleep, *}, solana_sdk::{account::{Account, AccountSharedData}, clock::Epoch, config::program, fee, msg, native_token::LAMPORTS_PER_SOL, program_option::COption, pubkey::Pubkey, rent::Rent, signature::{Keypair, Signer}, system_program, transaction::Transaction}, solana_validator::test_validator::TestValidatorGenesis, spl_token::state::{Account as TokenAccount, 
-        AccountState, 
-        GenericTokenAccount}, std::{convert::TryInto, 
-        mem::size_of, 
-        str::FromStr, 
-        time::Duration}
+    clap::{Args, Parser, Subcommand},
+    config::Settings,
+    ctf_solana_farm::client::SendOptions,
+    solana_sdk::pubkey::Pubkey,
+    std::{error::Error, path::PathBuf},
 };
 
-use solana_client::rpc_client::RpcClient;
-use solana_sdk::account::ReadableAccount;
-use solana_sdk::account_info::AccountInfo;
-use solana_sdk::signature::read_keypair_file;
-use solana_sdk::system_instruction;
+/// command line client of the farm program
+#[derive(Debug, Parser)]
+#[command(name = "client-farm", version)]
+struct Cli {
+    /// client config file (yaml) with json_rpc_url, keypair_path, program_id and commitment
+    #[arg(long, short = 'C', global = true, env = "FARM_CONFIG")]
+    config: Option<PathBuf>,
+
+    /// JSON RPC url of the cluster
+    #[arg(long, short = 'u', global = true, env = "FARM_RPC_URL")]
+    url: Option<String>,
+
+    /// fee payer keypair, also the default creator
+    #[arg(long, short = 'k', global = true, env = "FARM_KEYPAIR")]
+    keypair: Option<String>,
+
+    /// farm program id
+    #[arg(long, global = true, env = "FARM_PROGRAM_ID")]
+    program_id: Option<String>,
+
+    /// processed, confirmed or finalized
+    #[arg(long, global = true, env = "FARM_COMMITMENT")]
+    commitment: Option<String>,
+
+    #[command(flatten)]
+    send: SendArgs,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+/// how transactions are sent, see `SendOptions`
+#[derive(Debug, Args)]
+struct SendArgs {
+    /// compute unit limit, estimated by simulating the transaction when omitted
+    #[arg(long, global = true, env = "FARM_COMPUTE_UNIT_LIMIT")]
+    compute_unit_limit: Option<u32>,
+
+    /// priority fee in micro-lamports per compute unit
+    #[arg(long, global = true, env = "FARM_COMPUTE_UNIT_PRICE")]
+    compute_unit_price: Option<u64>,
+
+    /// send without simulating, the transaction keeps the default compute unit limit
+    #[arg(long, global = true)]
+    no_simulate: bool,
+
+    /// times an expired transaction is signed again with a fresh blockhash
+    #[arg(long, global = true, default_value_t = SendOptions::default().max_resends)]
+    max_resends: usize,
+}
+
+impl SendArgs {
+    fn send_options(&self) -> SendOptions {
+        SendOptions {
+            compute_unit_limit: self.compute_unit_limit,
+            compute_unit_price: self.compute_unit_price,
+            simulate: !self.no_simulate,
+            max_resends: self.max_resends,
+            ..SendOptions::default()
+        }
+    }
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// create a farm and its fee vault, the farm stays disabled until the fee is paid
+    CreateFarm {
+        /// mint the fee vault holds, the fee is paid in this mint
+        #[arg(long)]
+        fee_mint: Pubkey,
+
+        /// farm duration in seconds, picks the fee tier
+        #[arg(long)]
+        duration: u64,
+
+        /// creator keypair, defaults to the fee payer
+        #[arg(long)]
+        creator: Option<String>,
+    },
+
+    /// pay the farm fee to enable a farm
+    PayFee {
+        /// farm to enable
+        #[arg(long)]
+        farm: Pubkey,
+
+        /// creator token account the fee is paid from
+        #[arg(long)]
+        token_account: Pubkey,
+
+        /// referrer token account, receives the referral share of the fee
+        #[arg(long)]
+        referrer: Option<Pubkey>,
+
+        /// fee amount, looked up in the fee schedule when omitted
+        #[arg(long)]
+        amount: Option<u64>,
+
+        /// creator keypair, defaults to the fee payer
+        #[arg(long)]
+        creator: Option<String>,
+    },
+
+    /// print a farm
+    ShowFarm {
+        farm: Pubkey,
+    },
+
+    /// print every farm of the program
+    ListFarms,
+
+    /// stream farm events and failed transactions as they land, one JSON line each
+    Watch {
+        /// PubSub websocket url, derived from the JSON RPC url when omitted
+        #[arg(long)]
+        ws_url: Option<String>,
+
+        /// where records go: `-` for stdout, an http(s) url to POST them to, or a file to append to
+        #[arg(long, default_value = "-")]
+        sink: String,
+
+        /// skip successful transactions without farm events
+        #[arg(long)]
+        events_only: bool,
+    },
+
+    /// check the invariants of every farm and print a JSON report, exits non-zero on findings
+    Audit,
+
+    /// build, sign and send a PayFarmFee on a durable nonce, for air-gapped and multisig signers
+    Offline {
+        #[command(subcommand)]
+        command: offline::OfflineCommand,
+    },
+
+    /// request SOL from the cluster faucet
+    Airdrop {
+        /// amount in SOL
+        #[arg(long, default_value_t = 1.0)]
+        amount: f64,
+
+        /// recipient, defaults to the fee payer
+        #[arg(long)]
+        to: Option<Pubkey>,
+    },
+
+    /// replay a message against accounts dumped with `solana account --output json`, without a network,
+    /// prints the logs and what changed in every account of the message
+    #[cfg(feature = "replay")]
+    Replay {
+        /// encoded message, - reads it from stdin
+        message: String,
+
+        /// account dump, one account or an array of them per file, repeat for every file
+        #[arg(long = "account", required = true)]
+        accounts: Vec<PathBuf>,
+
+        #[arg(long, value_enum, default_value_t = offline::Encoding::Base58)]
+        encoding: offline::Encoding,
+
+        /// farm program to run, e.g. from `solana program dump`, the processor of this build when omitted
+        #[arg(long)]
+        program_so: Option<PathBuf>,
+    },
+
+    /// run a local validator with the farm program, a fee mint and a farm waiting for its fee
+    #[cfg(feature = "localnet")]
+    Localnet {
+        /// compiled farm program
+        #[arg(long, default_value = "../ctf/target/deploy/ctf_solana_farm.so")]
+        program_so: PathBuf,
+
+        /// ledger directory, a ledger left by a previous run is wiped
+        #[arg(long, default_value = "test-ledger")]
+        ledger: PathBuf,
+
+        /// JSON RPC port, the websocket listens on the next one
+        #[arg(long, default_value_t = 8899)]
+        rpc_port: u16,
+
+        /// directory for the payer keypair, the client config and the manifest
+        #[arg(long, default_value = "localnet")]
+        out_dir: PathBuf,
+
+        /// duration of the farm in seconds
+        #[arg(long, default_value_t = 7 * 86_400)]
+        duration: u64,
+    },
+}
 
 fn main() {
-
-    println!("Here it is the Rust Client!");
-    let keypair_payer = read_keypair_file("./keypair_payer").unwrap();
-    println!("🔑 The pubkey is: {}", keypair_payer.pubkey());
-
-    let rpc = RpcClient::new("http://127.0.0.1:8899".to_string());
-    //let rpc = RpcClient::new("https://api.devnet.solana.com".to_string());
-    let recent_blockhash = rpc.get_latest_blockhash().unwrap();
-    println!("The latest blockhash is: {}", recent_blockhash);
-
-    let lamports_airdrop = 1 * LAMPORTS_PER_SOL;
-
-    let result = rpc.request_airdrop(&keypair_payer.pubkey(), lamports_airdrop);
-
-    println!("Signature of payer airdrop: {:?}", result);
-    println!("Payer balance: {:?}", rpc.get_balance(&keypair_payer.pubkey()));
-
-    let program_id = Pubkey::from_str(&"x5i6Ec4fuLkEBB9cr7PRRndzLk4uPFkskDzWnPeyXHi".to_string()).unwrap();
-
-    println!("The program ID of the CTF is: {}", program_id);
-
-    let program_from_rpc = rpc.get_account(&program_id).unwrap();
-
-    println!("{:?}", program_from_rpc.lamports());
-
-    let creator = Keypair::new();
-    let creator_token = Keypair::new();
-    let fee_vault = Keypair::new();
-    let token_program_id = Keypair::new();
-    let farm_program_id = Keypair::new();
-    let amount = 5000;
-
-    // Create and initialize the accounts
-
-    // Farm id account initialization
-    let farm_id_key = Pubkey::new_unique();
-    let farm_id_account_data_size = size_of::<Farm>();
-    let farm_id_lamports = 10000;
-    let farm_id_owner = Pubkey::new_unique();
-    let mut farm_id_account = Account::new(farm_id_lamports, farm_id_account_data_size, &farm_id_owner);
-    let farm_data = Farm {
-        enabled: 0,
-        nonce: 42,
-        token_program_id: token_program_id.pubkey(),
-        creator: creator.pubkey(),
-        fee_vault: fee_vault.pubkey(),
-    };
-    farm_id_account.data[..].copy_from_slice(&farm_data.try_to_vec().unwrap());
- 
-    // Authority account initialization
-    let authority_key = match Pubkey::create_program_address(&[&farm_id_key.to_bytes()[..32], &[farm_data.nonce]], &program_id) {
-        Ok(address) => address,
-        Err(err) => panic!("Failed to generate authority address: {:?}", err),
-    };
-    let authority_account_data_size = 0;
-    let authority_account_lamports = 1 * LAMPORTS_PER_SOL;
-    let authority_owner = Pubkey::new_unique();
-    let mut authority_account = Account::new(authority_account_lamports, authority_account_data_size, &authority_owner);
-
-    // Creator account initialization
-    let creator_account_data_size = 0;
-    let creator_account_lamports = 1 * LAMPORTS_PER_SOL;
-    let creator_account_owner = Pubkey::new_unique();
-    let mut creator_account = Account::new(creator_account_lamports, creator_account_data_size, &creator_account_owner);
-
-    // Creator token account initialization
-    let creator_token_account_data_size = 0;
-    let creator_token_account_lamports = 1 * LAMPORTS_PER_SOL;
-    let creator_token_account_owner = Pubkey::new_unique();
-    let mut creator_token_account = Account::new(creator_token_account_lamports, creator_token_account_data_size, &creator_token_account_owner);
-
-    // Fee vault account initialization
-    let fee_vault_data = TokenAccount {
-        mint: Pubkey::new_unique(),
-        owner: authority_key,
-        amount: 10000000u64,
-        delegate: COption::Some(authority_key),
-        state: AccountState::Uninitialized,
-        is_native: COption::Some(1),
-        delegated_amount: 10000000,
-        close_authority: COption::Some(authority_key)
-    };
-
-    // Serialize the Farm struct instance
-    let mut serialized_fee_vault_data_vec : [u8; 165] = [0; 165];
-    TokenAccount::pack_into_slice(&fee_vault_data, &mut serialized_fee_vault_data_vec);
-    let fee_vault_account_data_size = 165;
-    let fee_vault_account_lamports = 1 * LAMPORTS_PER_SOL;
-    let fee_vault_account_owner = Pubkey::new_unique();
-    let mut fee_vault_account = Account::new(fee_vault_account_lamports, fee_vault_account_data_size, &fee_vault_account_owner);
-    fee_vault_account.data[..].copy_from_slice(&serialized_fee_vault_data_vec);
-
-    // Farm program id account initialization
-    let farm_program_id_account_data_size = 0;
-    let farm_program_id_account_lamports = 1 * LAMPORTS_PER_SOL;
-    let mut farm_program_id_account = Account::new(farm_program_id_account_lamports, farm_program_id_account_data_size, &program_id);
-    let farm_program_id_key = program_id;
-
-    // Token program id account initialization
-    let token_program_id_account_data_size = 0;
-    let token_program_id_account_lamports = 1 * LAMPORTS_PER_SOL;
-    let mut token_program_id_account = Account::new(token_program_id_account_lamports, token_program_id_account_data_size, &program_id);
-    token_program_id_account.executable = true;
-    let token_program_id_key = spl_token::ID;
-    println!("{}", token_program_id_key);
-
-    // Create the instruction
-    let instruction = ix_pay_create_fee(
-        &farm_id_key,
-        &authority_key,
-        &creator.pubkey(),
-        &creator_token.pubkey(),
-        &fee_vault.pubkey(),
-        &token_program_id_key,
-        &farm_program_id_key,
-        amount
-    );
-
-    println!("{:?}", instruction);
-
-    // Create the transaction
-    let mut transaction = Transaction::new_with_payer(
-        &[instruction],
-        Some(&keypair_payer.pubkey()),
-    );
-
-    // Sign the transaction
-    transaction.sign(&[&keypair_payer, &creator], recent_blockhash);
-
-    // Check that the result of the transaction is ok
-    assert_matches!(rpc.send_and_confirm_transaction(&transaction), Ok(_));
-}
\ No newline at end of file
+    // Display, not the Debug output of returning the error, so failures read as sentences
+    if let Err(err) = run(Cli::parse()) {
+        eprintln!("Error: {}", err);
+        std::process::exit(1);
+    }
+}
+
+fn run(cli: Cli) -> Result<(), Box<dyn Error>> {
+    let mut settings = Settings::resolve(cli.config.as_deref(), cli.url, cli.keypair, cli.program_id, cli.commitment)?;
+    settings.send_options = cli.send.send_options();
+
+    match cli.command {
+        Command::CreateFarm { fee_mint, duration, creator } => {
+            commands::create_farm(&settings, &fee_mint, duration, creator.as_deref())
+        }
+        Command::PayFee { farm, token_account, referrer, amount, creator } => {
+            commands::pay_fee(&settings, &farm, &token_account, referrer.as_ref(), amount, creator.as_deref())
+        }
+        Command::ShowFarm { farm } => commands::show_farm(&settings, &farm),
+        Command::ListFarms => commands::list_farms(&settings),
+        Command::Audit => audit::audit(&settings),
+        Command::Watch { ws_url, sink, events_only } => watch::watch(&settings, ws_url.as_deref(), &sink, events_only),
+        Command::Offline { command } => offline::run(&settings, &command),
+        Command::Airdrop { amount, to } => commands::airdrop(&settings, amount, to.as_ref()),
+        #[cfg(feature = "replay")]
+        Command::Replay { message, accounts, encoding, program_so } => {
+            replay::run(&settings, &accounts, &message, encoding, program_so.as_deref())
+        }
+        #[cfg(feature = "localnet")]
+        Command::Localnet { program_so, ledger, rpc_port, out_dir, duration } => {
+            localnet::localnet(&settings, &program_so, &ledger, rpc_port, &out_dir, duration)
+        }
+    }
+}
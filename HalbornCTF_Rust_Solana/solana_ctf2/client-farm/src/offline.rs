@@ -0,0 +1,330 @@
+use {
+    crate::{
+        commands::{expected_fee, explain, farm_client, get_farm, read_keypair, rpc_client},
+        config::Settings,
+    },
+    base64::{prelude::BASE64_STANDARD, Engine},
+    borsh::BorshDeserialize,
+    clap::{Args, Subcommand, ValueEnum},
+    ctf_solana_farm::{
+        client::{compute_budget_instructions, PayFarmFee},
+        instruction::FarmInstruction,
+    },
+    solana_sdk::{
+        message::Message,
+        pubkey::Pubkey,
+        sanitize::Sanitize,
+        signature::{Keypair, Signature, Signer},
+        system_instruction::SystemInstruction,
+        system_program,
+        transaction::Transaction,
+    },
+    std::{
+        error::Error,
+        io::{self, Read},
+        str::FromStr,
+    },
+};
+
+/// PayFarmFee signed away from the network: the message is built on a durable nonce,
+/// so it stays valid until the nonce account advances, however long the signers take
+#[derive(Debug, Subcommand)]
+pub enum OfflineCommand {
+    /// build the unsigned PayFarmFee message and print it encoded
+    BuildPayFee(BuildPayFee),
+
+    /// sign a message, prints the signature as PUBKEY=SIGNATURE, doesn't touch the network
+    Sign {
+        /// encoded message, - reads it from stdin
+        message: String,
+
+        /// keypair to sign with, defaults to the fee payer keypair
+        #[arg(long)]
+        signer: Option<String>,
+
+        #[arg(long, value_enum, default_value_t = Encoding::Base58)]
+        encoding: Encoding,
+    },
+
+    /// put the signatures on the message and send the transaction
+    Broadcast {
+        /// encoded message, - reads it from stdin
+        message: String,
+
+        /// PUBKEY=SIGNATURE printed by `sign`, once per signer
+        #[arg(long = "signer", required = true)]
+        signers: Vec<String>,
+
+        #[arg(long, value_enum, default_value_t = Encoding::Base58)]
+        encoding: Encoding,
+    },
+}
+
+#[derive(Debug, Args)]
+pub struct BuildPayFee {
+    /// farm to enable
+    #[arg(long)]
+    farm: Pubkey,
+
+    /// creator token account the fee is paid from
+    #[arg(long)]
+    token_account: Pubkey,
+
+    /// durable nonce account, its blockhash becomes the one of the transaction
+    #[arg(long)]
+    nonce_account: Pubkey,
+
+    /// authority of the nonce account, defaults to the creator
+    #[arg(long)]
+    nonce_authority: Option<Pubkey>,
+
+    /// creator of the farm, defaults to the fee payer
+    #[arg(long)]
+    creator: Option<Pubkey>,
+
+    /// fee payer, defaults to the pubkey of the fee payer keypair
+    #[arg(long)]
+    fee_payer: Option<Pubkey>,
+
+    /// referrer token account, receives the referral share of the fee
+    #[arg(long)]
+    referrer: Option<Pubkey>,
+
+    /// fee amount, looked up in the fee schedule when omitted
+    #[arg(long)]
+    amount: Option<u64>,
+
+    #[arg(long, value_enum, default_value_t = Encoding::Base58)]
+    encoding: Encoding,
+}
+
+/// how messages travel to and from the offline signers
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
+pub enum Encoding {
+    Base58,
+    Base64,
+}
+
+pub fn encode_message(message: &Message, encoding: Encoding) -> String {
+    let data = message.serialize();
+    match encoding {
+        Encoding::Base58 => bs58::encode(data).into_string(),
+        Encoding::Base64 => BASE64_STANDARD.encode(data),
+    }
+}
+
+pub fn decode_message(encoded: &str, encoding: Encoding) -> Result<Message, Box<dyn Error>> {
+    let data = match encoding {
+        Encoding::Base58 => bs58::decode(encoded.trim()).into_vec()?,
+        Encoding::Base64 => BASE64_STANDARD.decode(encoded.trim())?,
+    };
+    let message: Message = bincode::deserialize(&data).map_err(|err| format!("not a transaction message: {}", err))?;
+    message.sanitize()?;
+    Ok(message)
+}
+
+/// signers the message needs, the fee payer first
+fn required_signers(message: &Message) -> &[Pubkey] {
+    &message.account_keys[..message.header.num_required_signatures as usize]
+}
+
+/// signature of `keypair` over `message`, which has to expect it
+pub fn sign_message(message: &Message, keypair: &Keypair) -> Result<(Pubkey, Signature), Box<dyn Error>> {
+    if !required_signers(message).contains(&keypair.pubkey()) {
+        return Err(format!("{} doesn't sign this message", keypair.pubkey()).into());
+    }
+    Ok((keypair.pubkey(), keypair.sign_message(&message.serialize())))
+}
+
+/// a `PUBKEY=SIGNATURE` pair printed by `sign`
+pub fn parse_signer(signer: &str) -> Result<(Pubkey, Signature), Box<dyn Error>> {
+    let (pubkey, signature) = signer
+        .split_once('=')
+        .ok_or_else(|| format!("expected PUBKEY=SIGNATURE, got {}", signer))?;
+    Ok((Pubkey::from_str(pubkey)?, Signature::from_str(signature)?))
+}
+
+/// puts every signature at the position of its signer, the transaction has to be fully and validly signed
+pub fn assemble(message: Message, signatures: &[(Pubkey, Signature)]) -> Result<Transaction, Box<dyn Error>> {
+    let mut transaction = Transaction::new_unsigned(message);
+    for (pubkey, signature) in signatures {
+        let position = required_signers(&transaction.message)
+            .iter()
+            .position(|signer| signer == pubkey)
+            .ok_or_else(|| format!("{} doesn't sign this message", pubkey))?;
+        transaction.signatures[position] = *signature;
+    }
+    let verified = transaction.verify_with_results();
+    for ((signer, signature), valid) in required_signers(&transaction.message).iter().zip(&transaction.signatures).zip(verified) {
+        if *signature == Signature::default() {
+            return Err(format!("missing the signature of {}", signer).into());
+        }
+        if !valid {
+            return Err(format!("the signature of {} doesn't match the message", signer).into());
+        }
+    }
+    Ok(transaction)
+}
+
+/// what a signer is about to approve, one line each
+pub fn describe(message: &Message, farm_program_id: &Pubkey) -> Vec<String> {
+    let mut lines = vec![
+        format!("Fee payer: {}", message.account_keys[0]),
+        format!("Blockhash: {}", message.recent_blockhash),
+        format!("Signers: {}", required_signers(message).iter().map(Pubkey::to_string).collect::<Vec<_>>().join(", ")),
+    ];
+    for (index, instruction) in message.instructions.iter().enumerate() {
+        let program_id = message.account_keys[instruction.program_id_index as usize];
+        let decoded = if program_id == *farm_program_id {
+            FarmInstruction::try_from_slice(&instruction.data).ok().map(|instruction| format!("farm {:?}", instruction))
+        } else if program_id == system_program::id() {
+            bincode::deserialize::<SystemInstruction>(&instruction.data)
+                .ok()
+                .map(|instruction| format!("system {:?}", instruction))
+        } else {
+            None
+        };
+        lines.push(format!(
+            "Instruction {}: {}",
+            index,
+            decoded.unwrap_or_else(|| format!("program {}, {} bytes of data", program_id, instruction.data.len()))
+        ));
+    }
+    lines
+}
+
+/// `-` stands for stdin
+pub fn read_message(message: &str, encoding: Encoding) -> Result<Message, Box<dyn Error>> {
+    if message == "-" {
+        let mut encoded = String::new();
+        io::stdin().read_to_string(&mut encoded)?;
+        decode_message(&encoded, encoding)
+    } else {
+        decode_message(message, encoding)
+    }
+}
+
+fn build_pay_fee(settings: &Settings, args: &BuildPayFee) -> Result<(), Box<dyn Error>> {
+    let rpc = rpc_client(settings);
+    let fee_payer = match args.fee_payer {
+        Some(fee_payer) => fee_payer,
+        None => read_keypair(&settings.keypair_path)?.pubkey(),
+    };
+    let creator = args.creator.unwrap_or(fee_payer);
+    let nonce_authority = args.nonce_authority.unwrap_or(creator);
+
+    let client = farm_client(&rpc, settings);
+    let nonce = rpc.runtime().block_on(client.get_nonce_data(&args.nonce_account))?;
+    if nonce.authority != nonce_authority {
+        return Err(format!("the authority of nonce account {} is {}", args.nonce_account, nonce.authority).into());
+    }
+    let farm = get_farm(&rpc, settings, &args.farm)?;
+    let amount = match args.amount {
+        Some(amount) => amount,
+        None => expected_fee(&rpc, settings, &farm, &args.token_account)?,
+    };
+    let mut pay = PayFarmFee::new(args.farm, creator, args.token_account, farm.fee_vault, amount)
+        .token_program_id(farm.token_program_id);
+    if let Some(referrer) = args.referrer {
+        pay = pay.referrer(referrer);
+    }
+
+    // nothing can be simulated for the offline signers, only a limit given on the command line is set
+    let options = &settings.send_options;
+    let mut instructions = compute_budget_instructions(options.compute_unit_limit, options.compute_unit_price);
+    instructions.push(pay.instruction(&settings.program_id));
+    let mut message = Message::new_with_nonce(instructions, Some(&fee_payer), &args.nonce_account, &nonce_authority);
+    message.recent_blockhash = nonce.blockhash();
+
+    for line in describe(&message, &settings.program_id) {
+        eprintln!("{}", line);
+    }
+    println!("{}", encode_message(&message, args.encoding));
+    Ok(())
+}
+
+pub fn run(settings: &Settings, command: &OfflineCommand) -> Result<(), Box<dyn Error>> {
+    match command {
+        OfflineCommand::BuildPayFee(args) => build_pay_fee(settings, args),
+        OfflineCommand::Sign { message, signer, encoding } => {
+            let message = read_message(message, *encoding)?;
+            let keypair = read_keypair(signer.as_deref().unwrap_or(&settings.keypair_path))?;
+            for line in describe(&message, &settings.program_id) {
+                eprintln!("{}", line);
+            }
+            let (pubkey, signature) = sign_message(&message, &keypair)?;
+            println!("{}={}", pubkey, signature);
+            Ok(())
+        }
+        OfflineCommand::Broadcast { message, signers, encoding } => {
+            let message = read_message(message, *encoding)?;
+            let signatures = signers.iter().map(|signer| parse_signer(signer)).collect::<Result<Vec<_>, _>>()?;
+            let transaction = assemble(message, &signatures)?;
+            let rpc = rpc_client(settings);
+            let signature = rpc
+                .runtime()
+                .block_on(farm_client(&rpc, settings).send_signed(&transaction))
+                .map_err(|err| explain(settings, err))?;
+            println!("Signature: {}", signature);
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, solana_sdk::hash::Hash};
+
+    /// PayFarmFee on a durable nonce, paid by `fee_payer` for a farm of `creator`
+    fn nonce_message(fee_payer: &Pubkey, creator: &Pubkey) -> Message {
+        let program_id = Pubkey::new_unique();
+        let pay = PayFarmFee::new(Pubkey::new_unique(), *creator, Pubkey::new_unique(), Pubkey::new_unique(), 5000);
+        let mut message = Message::new_with_nonce(vec![pay.instruction(&program_id)], Some(fee_payer), &Pubkey::new_unique(), creator);
+        message.recent_blockhash = Hash::new_unique();
+        message
+    }
+
+    #[test]
+    fn test_message_encodings_round_trip() {
+        let message = nonce_message(&Pubkey::new_unique(), &Pubkey::new_unique());
+        for encoding in [Encoding::Base58, Encoding::Base64] {
+            assert_eq!(decode_message(&encode_message(&message, encoding), encoding).unwrap(), message);
+        }
+        assert!(decode_message("not a message", Encoding::Base64).is_err());
+    }
+
+    #[test]
+    fn test_signatures_from_separate_signers_assemble() {
+        let (fee_payer, creator) = (Keypair::new(), Keypair::new());
+        let message = nonce_message(&fee_payer.pubkey(), &creator.pubkey());
+        let encoded = encode_message(&message, Encoding::Base58);
+
+        // each signer decodes the message on its own and hands back PUBKEY=SIGNATURE
+        let signers: Vec<String> = [&creator, &fee_payer]
+            .iter()
+            .map(|keypair| {
+                let (pubkey, signature) = sign_message(&decode_message(&encoded, Encoding::Base58).unwrap(), keypair).unwrap();
+                format!("{}={}", pubkey, signature)
+            })
+            .collect();
+        let signatures: Vec<_> = signers.iter().map(|signer| parse_signer(signer).unwrap()).collect();
+        let transaction = assemble(message.clone(), &signatures).unwrap();
+        assert!(transaction.is_signed());
+        assert_eq!(transaction.signatures[0], signatures[1].1);
+
+        let missing = assemble(message.clone(), &signatures[..1]).unwrap_err();
+        assert_eq!(missing.to_string(), format!("missing the signature of {}", fee_payer.pubkey()));
+        let forged = [(fee_payer.pubkey(), signatures[0].1), signatures[0]];
+        assert!(assemble(message, &forged).unwrap_err().to_string().contains("doesn't match the message"));
+    }
+
+    #[test]
+    fn test_only_required_signers_sign() {
+        let message = nonce_message(&Pubkey::new_unique(), &Pubkey::new_unique());
+        let outsider = Keypair::new();
+        assert!(sign_message(&message, &outsider).is_err());
+        let signature = (outsider.pubkey(), outsider.sign_message(&message.serialize()));
+        assert!(assemble(message, &[signature]).is_err());
+        assert!(parse_signer("no-separator").is_err());
+    }
+}
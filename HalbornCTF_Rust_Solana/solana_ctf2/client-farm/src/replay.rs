@@ -0,0 +1,405 @@
+use {
+    crate::{
+        config::Settings,
+        decode::TransactionFailure,
+        offline::{read_message, Encoding},
+    },
+    borsh::BorshDeserialize,
+    ctf_solana_farm::{process_instruction, state::Farm},
+    serde::Deserialize,
+    solana_account_decoder::UiAccount,
+    solana_program::program_pack::Pack,
+    solana_program_test::{processor, BanksClient, ProgramTest},
+    solana_sdk::{
+        account::Account,
+        bpf_loader,
+        clock::Clock,
+        message::Message,
+        native_token::LAMPORTS_PER_SOL,
+        pubkey::Pubkey,
+        rent::Rent,
+        system_program, sysvar,
+        transaction::{Transaction, TransactionError},
+    },
+    spl_token::state::Account as TokenAccount,
+    std::{error::Error, fs, path::Path, str::FromStr},
+};
+
+/// one account as `solana account --output json` prints it
+#[derive(Debug, Deserialize)]
+struct DumpedAccount {
+    pubkey: String,
+    account: UiAccount,
+}
+
+/// a dump file holds one account, or an array of them to keep a whole scenario in one file
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum Dump {
+    One(DumpedAccount),
+    Many(Vec<DumpedAccount>),
+}
+
+pub fn parse_dump(json: &str) -> Result<Vec<(Pubkey, Account)>, Box<dyn Error>> {
+    let dumped = match serde_json::from_str(json).map_err(|err| format!("not an account dump: {}", err))? {
+        Dump::One(dumped) => vec![dumped],
+        Dump::Many(dumped) => dumped,
+    };
+    dumped
+        .into_iter()
+        .map(|dumped| {
+            let pubkey = Pubkey::from_str(&dumped.pubkey)?;
+            // jsonParsed data can't be turned back into bytes
+            let account = dumped
+                .account
+                .decode::<Account>()
+                .ok_or_else(|| format!("can't decode the data of {}, dump it as base64", pubkey))?;
+            Ok((pubkey, account))
+        })
+        .collect()
+}
+
+/// the accounts the replay starts from, dumped sysvars aside
+#[derive(Debug, Default)]
+pub struct Snapshot {
+    pub accounts: Vec<(Pubkey, Account)>,
+    /// the bank keeps its own sysvars, only a dumped clock is carried over
+    pub clock: Option<Clock>,
+}
+
+impl Snapshot {
+    pub fn load(paths: &[impl AsRef<Path>]) -> Result<Self, Box<dyn Error>> {
+        let mut snapshot = Snapshot::default();
+        for path in paths {
+            let path = path.as_ref();
+            let json = fs::read_to_string(path).map_err(|err| format!("can't open {}: {}", path.display(), err))?;
+            for (pubkey, account) in parse_dump(&json).map_err(|err| format!("{}: {}", path.display(), err))? {
+                if pubkey == sysvar::clock::id() {
+                    snapshot.clock = Some(bincode::deserialize(&account.data)?);
+                } else if sysvar::is_sysvar_id(&pubkey) {
+                    eprintln!("Skipping sysvar {}", pubkey);
+                } else {
+                    snapshot.accounts.push((pubkey, account));
+                }
+            }
+        }
+        Ok(snapshot)
+    }
+}
+
+/// one account of the message, `None` when it doesn't exist
+#[derive(Debug, PartialEq)]
+pub struct AccountDiff {
+    pub pubkey: Pubkey,
+    pub before: Option<Account>,
+    pub after: Option<Account>,
+}
+
+impl AccountDiff {
+    pub fn changed(&self) -> bool {
+        self.before != self.after
+    }
+
+    /// what changed, one line each
+    pub fn describe(&self, farm_program_id: &Pubkey) -> Vec<String> {
+        let (before, after) = match (&self.before, &self.after) {
+            (None, None) => return vec![],
+            (None, Some(_)) => return vec!["created".to_string()],
+            (Some(_), None) => return vec!["closed".to_string()],
+            (Some(before), Some(after)) => (before, after),
+        };
+        let mut lines = vec![];
+        if before.lamports != after.lamports {
+            lines.push(format!(
+                "lamports: {} -> {} ({:+})",
+                before.lamports,
+                after.lamports,
+                after.lamports as i128 - before.lamports as i128
+            ));
+        }
+        if before.owner != after.owner {
+            lines.push(format!("owner: {} -> {}", before.owner, after.owner));
+        }
+        if before.data.len() != after.data.len() {
+            lines.push(format!("data length: {} -> {}", before.data.len(), after.data.len()));
+        }
+        let ranges = changed_ranges(&before.data, &after.data);
+        if !ranges.is_empty() {
+            let ranges = ranges.iter().map(|(start, end)| format!("{}..{}", start, end)).collect::<Vec<_>>();
+            lines.push(format!("data changed at {}", ranges.join(", ")));
+        }
+        if after.owner == *farm_program_id {
+            if let (Ok(before), Ok(after)) = (Farm::try_from_slice(&before.data), Farm::try_from_slice(&after.data)) {
+                lines.push(format!("farm before: {:?}", before));
+                lines.push(format!("farm after: {:?}", after));
+            }
+        } else if after.owner == spl_token::id() {
+            if let (Ok(before), Ok(after)) = (TokenAccount::unpack(&before.data), TokenAccount::unpack(&after.data)) {
+                lines.push(format!("token amount: {} -> {}", before.amount, after.amount));
+            }
+        }
+        lines
+    }
+}
+
+/// byte ranges that differ, a grown or shrunk tail counts as changed
+fn changed_ranges(before: &[u8], after: &[u8]) -> Vec<(usize, usize)> {
+    let len = before.len().max(after.len());
+    let mut ranges: Vec<(usize, usize)> = vec![];
+    for offset in (0..len).filter(|offset| before.get(*offset) != after.get(*offset)) {
+        match ranges.last_mut() {
+            Some((_, end)) if *end == offset => *end = offset + 1,
+            _ => ranges.push((offset, offset + 1)),
+        }
+    }
+    ranges
+}
+
+#[derive(Debug)]
+pub struct Replay {
+    pub result: Result<(), TransactionError>,
+    pub logs: Vec<String>,
+    pub compute_units: Option<u64>,
+    /// every account of the message, in message order
+    pub diffs: Vec<AccountDiff>,
+}
+
+async fn get_accounts(banks_client: &mut BanksClient, addresses: &[Pubkey]) -> Result<Vec<Option<Account>>, Box<dyn Error>> {
+    let mut accounts = vec![];
+    for address in addresses {
+        accounts.push(banks_client.get_account(*address).await?);
+    }
+    Ok(accounts)
+}
+
+/// processes `message` on a fresh bank holding the snapshot accounts
+/// the farm program is the processor of this build, or `program_so` when given;
+/// the message keeps its signers but carries no signatures, nobody has to hold the keys
+pub async fn replay(
+    farm_program_id: &Pubkey,
+    program_so: Option<&Path>,
+    snapshot: Snapshot,
+    mut message: Message,
+) -> Result<Replay, Box<dyn Error>> {
+    let mut program_test = ProgramTest::default();
+    program_test.prefer_bpf(false);
+    match program_so {
+        Some(path) => {
+            let data = fs::read(path).map_err(|err| format!("can't open {}: {}", path.display(), err))?;
+            program_test.add_account(
+                *farm_program_id,
+                Account {
+                    lamports: Rent::default().minimum_balance(data.len()),
+                    data,
+                    owner: bpf_loader::id(),
+                    executable: true,
+                    ..Account::default()
+                },
+            );
+        }
+        None => program_test.add_program("ctf_solana_farm", *farm_program_id, processor!(process_instruction)),
+    }
+    let fee_payer = *message.account_keys.first().ok_or("message has no fee payer")?;
+    if !snapshot.accounts.iter().any(|(pubkey, _)| *pubkey == fee_payer) {
+        program_test.add_account(fee_payer, Account::new(LAMPORTS_PER_SOL, 0, &system_program::id()));
+    }
+    for (pubkey, account) in snapshot.accounts {
+        // the dumped program would replace the one picked above
+        if pubkey != *farm_program_id {
+            program_test.add_account(pubkey, account);
+        }
+    }
+
+    let mut context = program_test.start_with_context().await;
+    if let Some(clock) = &snapshot.clock {
+        context.set_sysvar(clock);
+    }
+    let addresses = message.account_keys.clone();
+    let before = get_accounts(&mut context.banks_client, &addresses).await?;
+    // the original blockhash is unknown to the fresh bank
+    message.recent_blockhash = context.banks_client.get_latest_blockhash().await?;
+    // processing with metadata skips signature verification
+    let outcome = context
+        .banks_client
+        .process_transaction_with_metadata(Transaction::new_unsigned(message))
+        .await?;
+    let after = get_accounts(&mut context.banks_client, &addresses).await?;
+
+    let (logs, compute_units) = match outcome.metadata {
+        Some(metadata) => (metadata.log_messages, Some(metadata.compute_units_consumed)),
+        None => (vec![], None),
+    };
+    Ok(Replay {
+        result: outcome.result,
+        logs,
+        compute_units,
+        diffs: addresses
+            .into_iter()
+            .zip(before.into_iter().zip(after))
+            .map(|(pubkey, (before, after))| AccountDiff { pubkey, before, after })
+            .collect(),
+    })
+}
+
+pub fn run(
+    settings: &Settings,
+    accounts: &[impl AsRef<Path>],
+    message: &str,
+    encoding: Encoding,
+    program_so: Option<&Path>,
+) -> Result<(), Box<dyn Error>> {
+    let snapshot = Snapshot::load(accounts)?;
+    let message = read_message(message, encoding)?;
+    let runtime = tokio::runtime::Runtime::new()?;
+    let replay = runtime.block_on(replay(&settings.program_id, program_so, snapshot, message.clone()))?;
+
+    for log in &replay.logs {
+        println!("{}", log);
+    }
+    match replay.result {
+        Ok(()) => println!("Result: success"),
+        Err(error) => println!("Result: {}", TransactionFailure::new(&settings.program_id, &message, error, &replay.logs)),
+    }
+    if let Some(compute_units) = replay.compute_units {
+        println!("Compute units: {}", compute_units);
+    }
+    let unchanged = replay.diffs.iter().filter(|diff| !diff.changed()).count();
+    for diff in replay.diffs.iter().filter(|diff| diff.changed()) {
+        println!("{}", diff.pubkey);
+        for line in diff.describe(&settings.program_id) {
+            println!("  {}", line);
+        }
+    }
+    println!("{} accounts unchanged", unchanged);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        base64::{prelude::BASE64_STANDARD, Engine},
+        borsh::BorshSerialize,
+        ctf_solana_farm::{
+            constant::{FARM_DISCRIMINATOR, FARM_VERSION},
+            error::FarmError,
+            instruction::{find_config_address, ix_propose_creator},
+            state::ProgramConfig,
+        },
+        serde_json::json,
+        solana_sdk::instruction::InstructionError,
+    };
+
+    /// `account` the way `solana account --output json` prints it
+    fn dump(pubkey: &Pubkey, account: &Account) -> serde_json::Value {
+        json!({
+            "pubkey": pubkey.to_string(),
+            "account": {
+                "lamports": account.lamports,
+                "data": [BASE64_STANDARD.encode(&account.data), "base64"],
+                "owner": account.owner.to_string(),
+                "executable": account.executable,
+                "rentEpoch": account.rent_epoch,
+                "space": account.data.len(),
+            },
+        })
+    }
+
+    fn program_account(program_id: &Pubkey, data: Vec<u8>) -> Account {
+        Account {
+            lamports: Rent::default().minimum_balance(data.len()),
+            data,
+            owner: *program_id,
+            executable: false,
+            rent_epoch: 0,
+        }
+    }
+
+    /// the program config and a farm of `creator`, as dumped from a cluster
+    fn farm_snapshot(program_id: &Pubkey, farm_id: &Pubkey, creator: &Pubkey) -> Snapshot {
+        let (config_id, bump) = find_config_address(program_id);
+        let config = ProgramConfig { is_initialized: 1, bump, ..ProgramConfig::default() };
+        let farm = Farm {
+            discriminator: FARM_DISCRIMINATOR,
+            version: FARM_VERSION,
+            creator: *creator,
+            token_program_id: spl_token::id(),
+            ..Farm::default()
+        };
+        let json = json!([
+            dump(&config_id, &program_account(program_id, config.try_to_vec().unwrap())),
+            dump(farm_id, &program_account(program_id, farm.try_to_vec().unwrap())),
+        ]);
+        Snapshot { accounts: parse_dump(&json.to_string()).unwrap(), clock: None }
+    }
+
+    fn run_replay(program_id: &Pubkey, snapshot: Snapshot, message: Message) -> Replay {
+        tokio::runtime::Runtime::new().unwrap().block_on(replay(program_id, None, snapshot, message)).unwrap()
+    }
+
+    #[test]
+    fn test_parse_dump() {
+        let (pubkey, owner) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let account = Account { lamports: 42, data: vec![1, 2, 3], owner, executable: false, rent_epoch: u64::MAX };
+        assert_eq!(parse_dump(&dump(&pubkey, &account).to_string()).unwrap(), vec![(pubkey, account.clone())]);
+        let both = json!([dump(&pubkey, &account), dump(&owner, &account)]).to_string();
+        assert_eq!(parse_dump(&both).unwrap().len(), 2);
+
+        let mut parsed = dump(&pubkey, &account);
+        parsed["account"]["data"] = json!({ "program": "spl-token", "parsed": {}, "space": 3 });
+        assert!(parse_dump(&parsed.to_string()).unwrap_err().to_string().contains("base64"));
+    }
+
+    #[test]
+    fn test_changed_ranges() {
+        assert_eq!(changed_ranges(&[1, 2, 3], &[1, 2, 3]), vec![]);
+        assert_eq!(changed_ranges(&[1, 2, 3, 4], &[0, 0, 3, 5]), vec![(0, 2), (3, 4)]);
+        assert_eq!(changed_ranges(&[1], &[1, 0, 0]), vec![(1, 3)]);
+    }
+
+    #[test]
+    fn test_replay_without_signer_keys() {
+        let program_id = Pubkey::new_unique();
+        let (farm_id, creator, new_creator) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+        let snapshot = farm_snapshot(&program_id, &farm_id, &creator);
+        let message = Message::new(&[ix_propose_creator(&farm_id, &creator, &new_creator, &program_id)], Some(&creator));
+
+        let replay = run_replay(&program_id, snapshot, message);
+        assert_eq!(replay.result, Ok(()));
+        assert!(replay.logs.iter().any(|log| log.contains(&new_creator.to_string())));
+        // the creator isn't in the dump, it's funded to pay the fee
+        let changed = replay.diffs.iter().filter(|diff| diff.changed()).map(|diff| diff.pubkey).collect::<Vec<_>>();
+        assert_eq!(changed, vec![creator, farm_id]);
+        let farm_diff = &replay.diffs[1];
+        let farm = Farm::try_from_slice(&farm_diff.after.as_ref().unwrap().data).unwrap();
+        assert_eq!(farm.pending_creator, new_creator);
+        let lines = farm_diff.describe(&program_id);
+        assert!(lines[0].starts_with("data changed at"));
+        assert!(lines[2].contains(&new_creator.to_string()));
+    }
+
+    #[test]
+    fn test_replay_without_fee_payer() {
+        let program_id = Pubkey::new_unique();
+        let snapshot = farm_snapshot(&program_id, &Pubkey::new_unique(), &Pubkey::new_unique());
+        let result = tokio::runtime::Runtime::new().unwrap().block_on(replay(&program_id, None, snapshot, Message::default()));
+        assert_eq!(result.err().unwrap().to_string(), "message has no fee payer");
+    }
+
+    #[test]
+    fn test_replay_failure_keeps_accounts() {
+        let program_id = Pubkey::new_unique();
+        let (farm_id, creator, intruder) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+        let snapshot = farm_snapshot(&program_id, &farm_id, &creator);
+        let message = Message::new(&[ix_propose_creator(&farm_id, &intruder, &intruder, &program_id)], Some(&intruder));
+
+        let replay = run_replay(&program_id, snapshot, message);
+        assert_eq!(
+            replay.result,
+            Err(TransactionError::InstructionError(0, InstructionError::Custom(FarmError::Unauthorized as u32)))
+        );
+        // only the fee is charged
+        let changed = replay.diffs.iter().filter(|diff| diff.changed()).map(|diff| diff.pubkey).collect::<Vec<_>>();
+        assert_eq!(changed, vec![intruder]);
+    }
+}
@@ -0,0 +1,241 @@
+use {
+    crate::{
+        config::Settings,
+        decode::{parse_logs, TransactionFailure},
+    },
+    ctf_solana_farm::event::FarmEvent,
+    serde::Serialize,
+    solana_client::{
+        pubsub_client::PubsubClient,
+        rpc_config::{RpcTransactionLogsConfig, RpcTransactionLogsFilter},
+        rpc_response::RpcLogsResponse,
+    },
+    solana_sdk::{clock::Slot, pubkey::Pubkey},
+    std::{
+        error::Error,
+        fs::{File, OpenOptions},
+        io::{self, Write},
+        path::Path,
+    },
+};
+
+/// a farm event as it is printed, pubkeys in base58
+#[derive(Debug, PartialEq, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum EventRecord {
+    FarmFeePaid {
+        farm: String,
+        creator: String,
+        fee_vault: String,
+        fee_vault_amount: u64,
+        referrer: Option<String>,
+        referrer_amount: u64,
+    },
+}
+
+impl From<&FarmEvent> for EventRecord {
+    fn from(event: &FarmEvent) -> Self {
+        match event {
+            FarmEvent::FarmFeePaid { farm, creator, fee_vault, fee_vault_amount, referrer, referrer_amount } => {
+                EventRecord::FarmFeePaid {
+                    farm: farm.to_string(),
+                    creator: creator.to_string(),
+                    fee_vault: fee_vault.to_string(),
+                    fee_vault_amount: *fee_vault_amount,
+                    referrer: referrer.map(|referrer| referrer.to_string()),
+                    referrer_amount: *referrer_amount,
+                }
+            }
+        }
+    }
+}
+
+/// why a transaction failed, as far as its logs tell
+#[derive(Debug, PartialEq, Serialize)]
+pub struct ErrorRecord {
+    pub error: String,
+    /// program that failed first, a program the farm invoked for a token error
+    pub program_id: Option<String>,
+    pub farm_error: Option<String>,
+    pub token_error: Option<String>,
+    pub reason: Option<String>,
+}
+
+impl From<&TransactionFailure> for ErrorRecord {
+    fn from(failure: &TransactionFailure) -> Self {
+        ErrorRecord {
+            error: failure.error.to_string(),
+            program_id: failure.failure.as_ref().map(|failure| failure.program_id.to_string()),
+            farm_error: failure.farm_error.as_ref().map(|error| format!("{:?}", error)),
+            token_error: failure.token_error.as_ref().map(|error| format!("{:?}", error)),
+            reason: failure.reason().map(str::to_string),
+        }
+    }
+}
+
+/// one JSON line per transaction that mentions the program
+#[derive(Debug, PartialEq, Serialize)]
+pub struct WatchRecord {
+    pub slot: Slot,
+    pub signature: String,
+    pub success: bool,
+    /// events of a failed transaction are left out, its state changes were rolled back
+    pub events: Vec<EventRecord>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<ErrorRecord>,
+}
+
+impl WatchRecord {
+    pub fn new(farm_program_id: &Pubkey, slot: Slot, response: RpcLogsResponse) -> Self {
+        let (events, error) = match response.err {
+            Some(error) => {
+                let failure = TransactionFailure::from_logs(farm_program_id, error, &response.logs);
+                (vec![], Some(ErrorRecord::from(&failure)))
+            }
+            None => {
+                let events = parse_logs(&response.logs).farm_events(farm_program_id);
+                (events.iter().map(EventRecord::from).collect(), None)
+            }
+        };
+        WatchRecord { slot, signature: response.signature, success: error.is_none(), events, error }
+    }
+}
+
+/// where records go
+pub enum Sink {
+    Stdout,
+    /// appended as JSON lines
+    File(File),
+    /// POSTed one by one as JSON
+    Webhook { url: String, client: reqwest::blocking::Client },
+}
+
+impl Sink {
+    /// an http(s) url is a webhook, anything else a file, `-` is stdout
+    pub fn open(target: &str) -> Result<Self, Box<dyn Error>> {
+        if target == "-" {
+            return Ok(Sink::Stdout);
+        }
+        if target.starts_with("http://") || target.starts_with("https://") {
+            return Ok(Sink::Webhook { url: target.to_string(), client: reqwest::blocking::Client::new() });
+        }
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(Path::new(target))
+            .map_err(|err| format!("can't open {}: {}", target, err))?;
+        Ok(Sink::File(file))
+    }
+
+    pub fn send(&mut self, record: &WatchRecord) -> Result<(), Box<dyn Error>> {
+        match self {
+            Sink::Stdout => {
+                let mut stdout = io::stdout().lock();
+                serde_json::to_writer(&mut stdout, record)?;
+                writeln!(stdout)?;
+                // a pipe buffers otherwise, and the reader wants the record now
+                stdout.flush()?;
+            }
+            Sink::File(file) => {
+                let mut line = serde_json::to_vec(record)?;
+                line.push(b'\n');
+                file.write_all(&line)?;
+            }
+            Sink::Webhook { url, client } => {
+                client.post(url.as_str()).json(record).send()?.error_for_status()?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// subscribes to the logs of every transaction mentioning the program and forwards a record
+/// for each until the subscription closes, only errors and fee payments with `events_only`
+pub fn watch(settings: &Settings, websocket_url: Option<&str>, sink: &str, events_only: bool) -> Result<(), Box<dyn Error>> {
+    let websocket_url = match websocket_url {
+        Some(url) => url.to_string(),
+        None => solana_cli_config::Config::compute_websocket_url(&settings.json_rpc_url),
+    };
+    let mut sink = Sink::open(sink)?;
+    let (_subscription, receiver) = PubsubClient::logs_subscribe(
+        &websocket_url,
+        RpcTransactionLogsFilter::Mentions(vec![settings.program_id.to_string()]),
+        RpcTransactionLogsConfig { commitment: Some(settings.commitment) },
+    )?;
+    eprintln!("Watching {} on {}", settings.program_id, websocket_url);
+    for response in receiver {
+        let record = WatchRecord::new(&settings.program_id, response.context.slot, response.value);
+        if events_only && record.success && record.events.is_empty() {
+            continue;
+        }
+        // a sink that went away shouldn't stop the stream
+        if let Err(err) = sink.send(&record) {
+            eprintln!("Can't forward {}: {}", record.signature, err);
+        }
+    }
+    Err("the logs subscription closed".into())
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        base64::{prelude::BASE64_STANDARD, Engine},
+        borsh::BorshSerialize,
+        ctf_solana_farm::error::FarmError,
+        solana_sdk::{instruction::InstructionError, transaction::TransactionError},
+    };
+
+    fn logs(lines: &[&str]) -> Vec<String> {
+        lines.iter().map(|line| line.to_string()).collect()
+    }
+
+    #[test]
+    fn test_fee_payment_record() {
+        let program_id = Pubkey::new_unique();
+        let event = FarmEvent::FarmFeePaid {
+            farm: Pubkey::new_unique(),
+            creator: Pubkey::new_unique(),
+            fee_vault: Pubkey::new_unique(),
+            fee_vault_amount: 5_000,
+            referrer: None,
+            referrer_amount: 0,
+        };
+        let response = RpcLogsResponse {
+            signature: "sig".to_string(),
+            err: None,
+            logs: logs(&[
+                &format!("Program {} invoke [1]", program_id),
+                &format!("Program data: {}", BASE64_STANDARD.encode(event.try_to_vec().unwrap())),
+                &format!("Program {} success", program_id),
+            ]),
+        };
+        let record = WatchRecord::new(&program_id, 7, response);
+        assert_eq!(record.events, vec![EventRecord::from(&event)]);
+
+        let json = serde_json::to_value(&record).unwrap();
+        assert_eq!(json["events"][0]["event"], "farm_fee_paid");
+        assert_eq!(json["events"][0]["fee_vault_amount"], 5_000);
+        assert!(json.get("error").is_none());
+    }
+
+    #[test]
+    fn test_failed_attempt_record() {
+        let program_id = Pubkey::new_unique();
+        let response = RpcLogsResponse {
+            signature: "sig".to_string(),
+            err: Some(TransactionError::InstructionError(0, InstructionError::Custom(FarmError::InvalidFarmFee as u32))),
+            logs: logs(&[
+                &format!("Program {} invoke [1]", program_id),
+                "Program log: Error: fee doesn't match the fee schedule",
+                &format!("Program {} failed: custom program error: 0x9", program_id),
+            ]),
+        };
+        let record = WatchRecord::new(&program_id, 7, response);
+        assert!(!record.success);
+        let error = record.error.unwrap();
+        assert_eq!(error.farm_error.as_deref(), Some("InvalidFarmFee"));
+        assert_eq!(error.program_id, Some(program_id.to_string()));
+        assert_eq!(error.reason.as_deref(), Some("fee doesn't match the fee schedule"));
+    }
+}
@@ -0,0 +1,19 @@
+//! feeds arbitrary instruction data and account sets into `Processor::process`
+//!
+//!     cargo +nightly-2024-01-31 fuzz run process_instruction
+//!
+//! solana-program 1.16 pins ahash 0.8.4, which doesn't build on nightlies from February 2024 on
+//!
+//! a panic, in the processor or in one of the invariant checks, is a finding
+
+#![no_main]
+
+use {
+    ctf_solana_farm_fuzz::{check_invariants, execute, FuzzInput},
+    libfuzzer_sys::fuzz_target,
+};
+
+fuzz_target!(|input: FuzzInput| {
+    let execution = execute(&input);
+    check_invariants(&input, &execution);
+});
@@ -0,0 +1,501 @@
+//! in-memory account model the fuzz targets run `Processor::process` in
+//!
+//! the model starts from a valid farm world (program config, fee schedule, a created farm,
+//! its fee vault and a funded creator) and lets the fuzzer pick which of these accounts an
+//! instruction gets, in which order, with which signer and writable flags, and with which
+//! owner and data overrides. cross-program invocations go through syscall stubs:
+//! SPL Token instructions run the real token processor on the model accounts,
+//! any other executable account is treated as a program that succeeds without doing anything
+
+use {
+    arbitrary::Arbitrary,
+    borsh::BorshSerialize,
+    ctf_solana_farm::{
+        constant::{FARM_DISCRIMINATOR, FARM_FEE, FARM_VERSION},
+        instruction::{find_config_address, find_fee_schedule_address, FarmInstruction},
+        processor::Processor,
+        state::{Farm, FarmAccount, FeeMint, FeeSchedule, ProgramConfig},
+    },
+    solana_program::{
+        account_info::AccountInfo,
+        clock::Clock,
+        entrypoint::ProgramResult,
+        instruction::Instruction,
+        program_error::ProgramError,
+        program_option::COption,
+        program_pack::Pack,
+        program_stubs::{self, SyscallStubs},
+        pubkey::Pubkey,
+        rent::Rent,
+        system_program,
+    },
+    spl_token::{
+        instruction::TokenInstruction,
+        state::{Account as TokenAccount, AccountState, Mint},
+    },
+    std::{cell::RefCell, collections::HashMap, convert::TryFrom, sync::Once},
+};
+
+pub const PROGRAM_ID: Pubkey = Pubkey::new_from_array([7; 32]);
+/// unix timestamp of the clock the instructions run at
+pub const NOW: i64 = 1_700_000_000;
+/// tokens the creator starts with
+pub const CREATOR_BALANCE: u64 = 1_000_000;
+
+/// accounts of the valid farm world, `Random` stands for an unrelated key
+#[derive(Arbitrary, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Slot {
+    Farm,
+    Authority,
+    Creator,
+    CreatorTokenAccount,
+    FeeVault,
+    ReferrerTokenAccount,
+    Mint,
+    TokenProgram,
+    SystemProgram,
+    /// a program anyone could have deployed
+    OtherProgram,
+    Config,
+    FeeSchedule,
+    Admin,
+    Random(u8),
+}
+
+#[derive(Arbitrary, Clone, Copy, Debug)]
+pub enum Owner {
+    Program,
+    TokenProgram,
+    SystemProgram,
+    Random(u8),
+}
+
+/// one entry of the instruction account list
+/// the overrides apply to the account itself, so they show in every entry naming the same slot
+#[derive(Arbitrary, Debug)]
+pub struct FuzzAccount {
+    pub slot: Slot,
+    pub is_signer: bool,
+    pub is_writable: bool,
+    pub owner: Option<Owner>,
+    pub data: Option<Vec<u8>>,
+}
+
+/// instruction data, either raw bytes or a well-formed instruction with fuzzed arguments
+#[derive(Arbitrary, Debug)]
+pub enum FuzzData {
+    Raw(Vec<u8>),
+    Create { nonce: u8, duration: u64 },
+    PayFarmFee(u64),
+    SetPaused(bool),
+    SetReferralShare(u16),
+    ProposeCreator(Slot),
+    AcceptCreator,
+    SetOperator(Slot),
+    RevokeOperator,
+    SetFarmPaused(bool),
+}
+
+/// state of the world before the instruction
+#[derive(Arbitrary, Debug)]
+pub struct FuzzWorld {
+    pub farm_created: bool,
+    pub farm_enabled: bool,
+    pub farm_duration: u64,
+    pub farm_ends_at: i64,
+    pub paused: bool,
+    pub referral_bps: u16,
+    pub pending_creator: Option<Slot>,
+    pub operator: Option<Slot>,
+    pub farm_paused: bool,
+}
+
+#[derive(Arbitrary, Debug)]
+pub struct FuzzInput {
+    pub world: FuzzWorld,
+    pub data: FuzzData,
+    pub accounts: Vec<FuzzAccount>,
+}
+
+/// a token transfer the token program carried out during the instruction
+#[derive(Clone, Debug, PartialEq)]
+pub struct Transfer {
+    pub source: Pubkey,
+    pub destination: Pubkey,
+    pub authority: Pubkey,
+    pub amount: u64,
+    /// the authority signed the outer instruction, it didn't sign through a PDA
+    pub authority_signed: bool,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ModelAccount {
+    pub lamports: u64,
+    pub data: Vec<u8>,
+    pub owner: Pubkey,
+    pub executable: bool,
+}
+
+/// outcome of running one instruction in the model
+#[derive(Debug)]
+pub struct Execution {
+    pub result: ProgramResult,
+    pub before: HashMap<Pubkey, ModelAccount>,
+    pub after: HashMap<Pubkey, ModelAccount>,
+    pub transfers: Vec<Transfer>,
+    /// the instruction wrote to an account the runtime wouldn't let it write to,
+    /// the transaction would fail on chain whatever the program returned
+    pub rejected_by_runtime: bool,
+}
+
+thread_local! {
+    static TRANSFERS: RefCell<Vec<Transfer>> = const { RefCell::new(Vec::new()) };
+    /// data of the accounts as the last cross-program invocation left them
+    static CPI_WRITES: RefCell<HashMap<Pubkey, Vec<u8>>> = RefCell::new(HashMap::new());
+    /// signers of the outer instruction
+    static OUTER_SIGNERS: RefCell<Vec<Pubkey>> = const { RefCell::new(Vec::new()) };
+}
+
+pub fn slot_key(slot: Slot) -> Pubkey {
+    match slot {
+        Slot::Farm => Pubkey::new_from_array([1; 32]),
+        Slot::Authority => farm_authority().0,
+        Slot::Creator => Pubkey::new_from_array([2; 32]),
+        Slot::CreatorTokenAccount => Pubkey::new_from_array([3; 32]),
+        Slot::FeeVault => Pubkey::new_from_array([4; 32]),
+        Slot::ReferrerTokenAccount => Pubkey::new_from_array([5; 32]),
+        Slot::Mint => Pubkey::new_from_array([6; 32]),
+        Slot::TokenProgram => spl_token::id(),
+        Slot::SystemProgram => system_program::id(),
+        Slot::OtherProgram => Pubkey::new_from_array([9; 32]),
+        Slot::Config => find_config_address(&PROGRAM_ID).0,
+        Slot::FeeSchedule => find_fee_schedule_address(&PROGRAM_ID).0,
+        Slot::Admin => Pubkey::new_from_array([8; 32]),
+        Slot::Random(seed) => {
+            let mut bytes = [0xff; 32];
+            bytes[0] = seed;
+            Pubkey::new_from_array(bytes)
+        }
+    }
+}
+
+fn owner_key(owner: Owner) -> Pubkey {
+    match owner {
+        Owner::Program => PROGRAM_ID,
+        Owner::TokenProgram => spl_token::id(),
+        Owner::SystemProgram => system_program::id(),
+        Owner::Random(seed) => slot_key(Slot::Random(seed)),
+    }
+}
+
+pub fn farm_authority() -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[&slot_key(Slot::Farm).to_bytes()[..32]], &PROGRAM_ID)
+}
+
+fn token_account(owner: Slot, amount: u64) -> ModelAccount {
+    let mut data = vec![0; TokenAccount::LEN];
+    TokenAccount {
+        mint: slot_key(Slot::Mint),
+        owner: slot_key(owner),
+        amount,
+        delegate: COption::None,
+        state: AccountState::Initialized,
+        is_native: COption::None,
+        delegated_amount: 0,
+        close_authority: COption::None,
+    }
+    .pack_into_slice(&mut data);
+    ModelAccount { lamports: rent(data.len()), data, owner: spl_token::id(), executable: false }
+}
+
+fn rent(len: usize) -> u64 {
+    Rent::default().minimum_balance(len)
+}
+
+/// the account `slot` holds in `world`, before any override
+pub fn world_account(world: &FuzzWorld, slot: Slot) -> ModelAccount {
+    let program_account = |data: Vec<u8>| ModelAccount { lamports: rent(data.len()), data, owner: PROGRAM_ID, executable: false };
+    let wallet = ModelAccount { lamports: 1_000_000_000, data: vec![], owner: system_program::id(), executable: false };
+    match slot {
+        Slot::Farm => {
+            let farm = if world.farm_created {
+                Farm {
+                    discriminator: FARM_DISCRIMINATOR,
+                    version: FARM_VERSION,
+                    enabled: world.farm_enabled as u8,
+                    nonce: farm_authority().1,
+                    token_program_id: spl_token::id(),
+                    creator: slot_key(Slot::Creator),
+                    fee_vault: slot_key(Slot::FeeVault),
+                    pending_creator: world.pending_creator.map(slot_key).unwrap_or_default(),
+                    duration: world.farm_duration,
+                    operator: world.operator.map(slot_key).unwrap_or_default(),
+                    paused: world.farm_paused as u8,
+                    ends_at: world.farm_ends_at,
+                }
+            } else {
+                Farm::default()
+            };
+            program_account(farm.try_to_vec().unwrap())
+        }
+        Slot::CreatorTokenAccount => token_account(Slot::Creator, CREATOR_BALANCE),
+        Slot::FeeVault => token_account(Slot::Authority, 0),
+        Slot::ReferrerTokenAccount => token_account(Slot::Random(0), 0),
+        Slot::Mint => {
+            let mut data = vec![0; Mint::LEN];
+            Mint { mint_authority: COption::None, supply: u64::MAX, decimals: 6, is_initialized: true, freeze_authority: COption::None }
+                .pack_into_slice(&mut data);
+            ModelAccount { lamports: rent(data.len()), data, owner: spl_token::id(), executable: false }
+        }
+        Slot::TokenProgram | Slot::SystemProgram | Slot::OtherProgram => ModelAccount { lamports: 1, data: vec![], owner: Pubkey::default(), executable: true },
+        Slot::Config => {
+            let mut config = ProgramConfig {
+                is_initialized: 1,
+                paused: world.paused as u8,
+                bump: find_config_address(&PROGRAM_ID).1,
+                admin: slot_key(Slot::Admin),
+                ..ProgramConfig::default()
+            };
+            config.set_referral_bps(world.referral_bps);
+            program_account(config.try_to_vec().unwrap())
+        }
+        Slot::FeeSchedule => {
+            let mut data = FeeSchedule {
+                is_initialized: true,
+                bump: find_fee_schedule_address(&PROGRAM_ID).1,
+                tiers: vec![],
+                mints: vec![FeeMint { mint: slot_key(Slot::Mint), amount: FARM_FEE }],
+            }
+            .try_to_vec()
+            .unwrap();
+            data.resize(FeeSchedule::LEN, 0);
+            program_account(data)
+        }
+        Slot::Authority | Slot::Creator | Slot::Admin | Slot::Random(_) => wallet,
+    }
+}
+
+struct ModelStubs;
+
+impl SyscallStubs for ModelStubs {
+    fn sol_log(&self, _message: &str) {}
+
+    fn sol_log_compute_units(&self) {}
+
+    fn sol_log_data(&self, _fields: &[&[u8]]) {}
+
+    fn sol_get_rent_sysvar(&self, var_addr: *mut u8) -> u64 {
+        unsafe { *(var_addr as *mut Rent) = Rent::default() };
+        solana_program::entrypoint::SUCCESS
+    }
+
+    fn sol_get_clock_sysvar(&self, var_addr: *mut u8) -> u64 {
+        unsafe { *(var_addr as *mut Clock) = Clock { unix_timestamp: NOW, ..Clock::default() } };
+        solana_program::entrypoint::SUCCESS
+    }
+
+    fn sol_invoke_signed(&self, instruction: &Instruction, account_infos: &[AccountInfo], signers_seeds: &[&[&[u8]]]) -> ProgramResult {
+        let pda_signers = signers_seeds
+            .iter()
+            .map(|seeds| Pubkey::create_program_address(seeds, &PROGRAM_ID))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        // the callee gets the caller's account infos with the privileges the instruction asks for,
+        // a signer has to be a signer of the caller or one of its PDAs, as the runtime enforces
+        let mut callee_infos = Vec::with_capacity(instruction.accounts.len());
+        for meta in &instruction.accounts {
+            let info = account_infos.iter().find(|info| *info.key == meta.pubkey).ok_or(ProgramError::NotEnoughAccountKeys)?;
+            if meta.is_signer && !info.is_signer && !pda_signers.contains(info.key) {
+                return Err(ProgramError::MissingRequiredSignature);
+            }
+            if meta.is_writable && !info.is_writable {
+                return Err(ProgramError::InvalidArgument);
+            }
+            let mut callee_info = info.clone();
+            callee_info.is_signer = meta.is_signer;
+            callee_info.is_writable = meta.is_writable;
+            callee_infos.push(callee_info);
+        }
+        let program_info = account_infos
+            .iter()
+            .find(|info| *info.key == instruction.program_id)
+            .ok_or(ProgramError::NotEnoughAccountKeys)?;
+        if !program_info.executable {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        if instruction.program_id != spl_token::id() {
+            return Ok(());
+        }
+
+        // the token program only writes accounts it owns
+        for info in callee_infos.iter().filter(|info| info.is_writable) {
+            if *info.owner != spl_token::id() {
+                return Err(ProgramError::IllegalOwner);
+            }
+        }
+        spl_token::processor::Processor::process(&spl_token::id(), &callee_infos, &instruction.data)?;
+        if let Ok(TokenInstruction::Transfer { amount }) = TokenInstruction::unpack(&instruction.data) {
+            let authority = *callee_infos[2].key;
+            let authority_signed = OUTER_SIGNERS.with(|signers| signers.borrow().contains(&authority));
+            TRANSFERS.with(|transfers| {
+                transfers.borrow_mut().push(Transfer {
+                    source: *callee_infos[0].key,
+                    destination: *callee_infos[1].key,
+                    authority,
+                    amount,
+                    authority_signed,
+                })
+            });
+        }
+        CPI_WRITES.with(|writes| {
+            let mut writes = writes.borrow_mut();
+            for info in &callee_infos {
+                writes.insert(*info.key, info.data.borrow().to_vec());
+            }
+        });
+        Ok(())
+    }
+}
+
+impl FuzzData {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let instruction = match self {
+            FuzzData::Raw(data) => return data.clone(),
+            FuzzData::Create { nonce, duration } => FarmInstruction::Create { nonce: *nonce, duration: *duration },
+            FuzzData::PayFarmFee(amount) => FarmInstruction::PayFarmFee(*amount),
+            FuzzData::SetPaused(paused) => FarmInstruction::SetPaused(*paused),
+            FuzzData::SetReferralShare(referral_bps) => FarmInstruction::SetReferralShare(*referral_bps),
+            FuzzData::ProposeCreator(slot) => FarmInstruction::ProposeCreator(slot_key(*slot)),
+            FuzzData::AcceptCreator => FarmInstruction::AcceptCreator,
+            FuzzData::SetOperator(slot) => FarmInstruction::SetOperator(slot_key(*slot)),
+            FuzzData::RevokeOperator => FarmInstruction::RevokeOperator,
+            FuzzData::SetFarmPaused(paused) => FarmInstruction::SetFarmPaused(*paused),
+        };
+        instruction.try_to_vec().unwrap()
+    }
+}
+
+/// runs the instruction of `input` through the farm processor
+pub fn execute(input: &FuzzInput) -> Execution {
+    static STUBS: Once = Once::new();
+    STUBS.call_once(|| {
+        program_stubs::set_syscall_stubs(Box::new(ModelStubs));
+    });
+    TRANSFERS.with(|transfers| transfers.borrow_mut().clear());
+    CPI_WRITES.with(|writes| writes.borrow_mut().clear());
+
+    // one account per key, the first entry naming a slot decides its overrides
+    let mut accounts: HashMap<Pubkey, ModelAccount> = HashMap::new();
+    for entry in &input.accounts {
+        accounts.entry(slot_key(entry.slot)).or_insert_with(|| {
+            let mut account = world_account(&input.world, entry.slot);
+            if let Some(owner) = entry.owner {
+                account.owner = owner_key(owner);
+            }
+            if let Some(data) = &entry.data {
+                account.data = data.clone();
+            }
+            account
+        });
+    }
+    // the token program is always loaded, whether the instruction names it or not
+    accounts.entry(spl_token::id()).or_insert_with(|| world_account(&input.world, Slot::TokenProgram));
+    let before = accounts.clone();
+
+    // a key is a signer or writable in every entry once one entry marks it, as in a transaction
+    let keys: Vec<Pubkey> = input.accounts.iter().map(|entry| slot_key(entry.slot)).collect();
+    let is_signer = |key: &Pubkey| input.accounts.iter().any(|entry| entry.is_signer && slot_key(entry.slot) == *key);
+    let is_writable = |key: &Pubkey| input.accounts.iter().any(|entry| entry.is_writable && slot_key(entry.slot) == *key);
+    OUTER_SIGNERS.with(|signers| *signers.borrow_mut() = keys.iter().copied().filter(|key| is_signer(key)).collect());
+
+    let mut storage: HashMap<Pubkey, (u64, Vec<u8>, Pubkey)> = accounts
+        .iter()
+        .map(|(key, account)| (*key, (account.lamports, account.data.clone(), account.owner)))
+        .collect();
+    let infos_by_key: HashMap<Pubkey, AccountInfo> = storage
+        .iter_mut()
+        .map(|(key, (lamports, data, owner))| {
+            let info = AccountInfo::new(key, is_signer(key), is_writable(key), lamports, data, owner, accounts[key].executable, 0);
+            (*key, info)
+        })
+        .collect();
+    let mut infos: Vec<AccountInfo> = keys.iter().map(|key| infos_by_key[key].clone()).collect();
+    // cross-program invocations see the token program account, like the runtime would
+    infos.push(infos_by_key[&spl_token::id()].clone());
+    let instruction_infos = &infos[..keys.len()];
+
+    let result = Processor::process(&PROGRAM_ID, instruction_infos, &input.data.to_bytes());
+
+    let after: HashMap<Pubkey, ModelAccount> = infos_by_key
+        .iter()
+        .map(|(key, info)| {
+            (*key, ModelAccount { lamports: info.lamports(), data: info.data.borrow().to_vec(), owner: *info.owner, executable: info.executable })
+        })
+        .collect();
+    drop(infos);
+    drop(infos_by_key);
+
+    // the runtime rejects data changes the program wasn't allowed to make itself,
+    // changes the token program made through a cross-program invocation are legitimate
+    let cpi_writes = CPI_WRITES.with(|writes| writes.borrow().clone());
+    let rejected_by_runtime = after.iter().any(|(key, account)| {
+        let expected = cpi_writes.get(key).unwrap_or(&before[key].data);
+        account.data != *expected && (account.owner != PROGRAM_ID || !is_writable(key))
+    });
+
+    Execution {
+        result,
+        before,
+        after,
+        transfers: TRANSFERS.with(|transfers| transfers.borrow().clone()),
+        rejected_by_runtime,
+    }
+}
+
+/// panics when the execution breaks one of the farm invariants
+pub fn check_invariants(input: &FuzzInput, execution: &Execution) {
+    if execution.result.is_err() || execution.rejected_by_runtime {
+        return;
+    }
+    // the processor takes the farm from the first account, whichever slot that is
+    let farm_key = match input.accounts.first() {
+        Some(entry) => slot_key(entry.slot),
+        None => return,
+    };
+    let farm_before = load_farm(&execution.before[&farm_key].data);
+    let farm_after = load_farm(&execution.after[&farm_key].data);
+
+    // no token movement without the creator signature
+    for transfer in &execution.transfers {
+        let creator = farm_before.map(|farm| farm.creator);
+        assert!(transfer.authority_signed, "tokens moved without a signature of their owner: {:?}", transfer);
+        assert_eq!(Some(transfer.authority), creator, "tokens moved by someone else than the creator: {:?}", transfer);
+    }
+
+    // enabled only flips, and the paid window only moves, after a transfer of the fee into the farm's fee vault
+    if let (Some(before), Some(after)) = (farm_before, farm_after) {
+        if (before.enabled == 0 && after.enabled == 1) || before.ends_at != after.ends_at {
+            let paid: u64 = execution
+                .transfers
+                .iter()
+                .filter(|transfer| transfer.destination == before.fee_vault)
+                .map(|transfer| transfer.amount)
+                .sum();
+            assert!(paid > 0, "farm enabled without a fee transfer: {:?}", input);
+            // the fee pays for the farm duration from now on, not for a longer window
+            assert!(after.ends_at() <= NOW.saturating_add(i64::try_from(before.duration()).unwrap_or(i64::MAX)), "window longer than paid for: {:?}", input);
+        }
+        // a farm whose window is still open isn't paid for again
+        if before.is_active(NOW) {
+            assert_eq!(before.ends_at, after.ends_at, "active farm paid for again: {:?}", input);
+        }
+        // a paused farm can't be paid for
+        if before.paused == 1 {
+            assert_eq!(before.enabled, after.enabled, "paused farm enabled: {:?}", input);
+        }
+    }
+}
+
+fn load_farm(data: &[u8]) -> Option<FarmAccount> {
+    data.get(..FarmAccount::LEN).map(|data| *bytemuck::from_bytes(data))
+}
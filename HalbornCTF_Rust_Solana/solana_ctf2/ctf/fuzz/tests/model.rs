@@ -0,0 +1,77 @@
+use {
+    borsh::BorshDeserialize,
+    ctf_solana_farm::{constant::FARM_FEE, state::Farm},
+    ctf_solana_farm_fuzz::{
+        check_invariants, execute, slot_key, FuzzAccount, FuzzData, FuzzInput, FuzzWorld, Slot, CREATOR_BALANCE, NOW,
+    },
+    solana_program::{program_error::ProgramError, program_pack::Pack},
+    spl_token::state::Account as TokenAccount,
+};
+
+fn account(slot: Slot, is_signer: bool, is_writable: bool) -> FuzzAccount {
+    FuzzAccount { slot, is_signer, is_writable, owner: None, data: None }
+}
+
+/// PayFarmFee of a created, disabled farm with the accounts in the documented order
+fn pay_fee(token_program: Slot, creator_signs: bool) -> FuzzInput {
+    FuzzInput {
+        world: FuzzWorld {
+            farm_created: true,
+            farm_enabled: false,
+            farm_duration: 86_400,
+            farm_ends_at: 0,
+            paused: false,
+            referral_bps: 0,
+            pending_creator: None,
+            operator: None,
+            farm_paused: false,
+        },
+        data: FuzzData::PayFarmFee(FARM_FEE),
+        accounts: vec![
+            account(Slot::Farm, false, true),
+            account(Slot::Authority, false, false),
+            account(Slot::Creator, creator_signs, false),
+            account(Slot::CreatorTokenAccount, false, true),
+            account(Slot::FeeVault, false, true),
+            account(token_program, false, false),
+            account(Slot::Config, false, false),
+            account(Slot::FeeSchedule, false, false),
+        ],
+    }
+}
+
+fn token_balance(execution: &ctf_solana_farm_fuzz::Execution, slot: Slot) -> u64 {
+    TokenAccount::unpack(&execution.after[&slot_key(slot)].data).unwrap().amount
+}
+
+#[test]
+fn test_model_pays_the_fee() {
+    let input = pay_fee(Slot::TokenProgram, true);
+    let execution = execute(&input);
+    assert_eq!(execution.result, Ok(()));
+    assert!(!execution.rejected_by_runtime);
+    assert_eq!(execution.transfers.len(), 1);
+    assert_eq!(token_balance(&execution, Slot::FeeVault), FARM_FEE);
+    assert_eq!(token_balance(&execution, Slot::CreatorTokenAccount), CREATOR_BALANCE - FARM_FEE);
+    let farm = Farm::try_from_slice(&execution.after[&slot_key(Slot::Farm)].data).unwrap();
+    assert_eq!(farm.enabled, 1);
+    assert_eq!(farm.ends_at, NOW + 86_400);
+    check_invariants(&input, &execution);
+}
+
+#[test]
+fn test_model_requires_creator_signature() {
+    let input = pay_fee(Slot::TokenProgram, false);
+    let execution = execute(&input);
+    assert!(execution.result.is_err());
+    assert!(execution.transfers.is_empty());
+}
+
+#[test]
+fn test_fee_has_to_go_through_the_token_program() {
+    // another program would "transfer" the fee without moving a token
+    let input = pay_fee(Slot::OtherProgram, true);
+    let execution = execute(&input);
+    assert_eq!(execution.result, Err(ProgramError::IncorrectProgramId));
+    check_invariants(&input, &execution);
+}
@@ -0,0 +1,50 @@
+//! compute unit benchmark for the farm account representation
+//! build with `cargo build-sbf --features cu-bench` and run `tests/compute_units.rs`
+//! the benchmark program replaces the farm entrypoint, it is never part of a deployed build
+
+use {
+    crate::state::{Farm, FarmAccount},
+    borsh::{BorshDeserialize, BorshSerialize},
+    solana_program::{
+        account_info::{next_account_info, AccountInfo},
+        borsh0_10::try_from_slice_unchecked,
+        entrypoint::ProgramResult,
+        pubkey::Pubkey,
+    },
+};
+
+#[derive(Clone, Debug, PartialEq, BorshSerialize, BorshDeserialize)]
+pub enum BenchInstruction {
+    ///   Deserializes the whole farm with borsh, flips `enabled` and serializes it back
+    ///
+    ///   0. `[w]` farm account
+    Borsh,
+
+    ///   Flips `enabled` in place through `FarmAccount`
+    ///
+    ///   0. `[w]` farm account
+    ZeroCopy,
+}
+
+/// benchmark entrypoint, mirrors what `process_pay_farm_fee` does with the farm account
+pub fn process_instruction(
+    _program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    input: &[u8],
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let farm_id_info = next_account_info(account_info_iter)?;
+    match BenchInstruction::try_from_slice(input)? {
+        BenchInstruction::Borsh => {
+            let mut farm_data = try_from_slice_unchecked::<Farm>(&farm_id_info.data.borrow())?;
+            farm_data.enabled ^= 1;
+            farm_data
+                .serialize(&mut *farm_id_info.data.borrow_mut())
+                .map_err(|e| e.into())
+        }
+        BenchInstruction::ZeroCopy => {
+            FarmAccount::load_mut(farm_id_info)?.enabled ^= 1;
+            Ok(())
+        }
+    }
+}
@@ -0,0 +1,823 @@
+//! off-chain side of the farm program, enabled with the `client` feature
+//! typed builders derive the PDAs and keep the account order of every instruction in one place,
+//! `FarmClient` reads farm accounts and sends transactions over an `RpcClient` or a `BanksClient`
+
+mod send;
+
+use {
+    crate::{
+        constant::{FARM_DISCRIMINATOR, FARM_METADATA_DISCRIMINATOR},
+        error::FarmError,
+        instruction::{
+            ix_accept_creator, ix_create_farm, ix_initialize_config, ix_pay_create_fee,
+            ix_pay_create_fee_with_referrer, ix_propose_creator, ix_revoke_operator, ix_set_farm_metadata,
+            ix_set_farm_paused, ix_set_fee_schedule, ix_set_operator, ix_set_paused, ix_set_referral_share,
+            ix_transfer_admin, ix_update_farm_metadata,
+        },
+        state::{Farm, FarmAccount, FarmMetadata, FeeMint, FeeSchedule, FeeTier, ProgramConfig},
+    },
+    async_trait::async_trait,
+    borsh::BorshDeserialize,
+    num_traits::FromPrimitive,
+    solana_banks_client::{BanksClient, BanksClientError},
+    solana_client::{
+        client_error::{ClientError, ClientErrorKind},
+        nonblocking::rpc_client::RpcClient,
+        nonce_utils::nonblocking::data_from_account,
+        rpc_client::GetConfirmedSignaturesForAddress2Config,
+        rpc_config::{RpcAccountInfoConfig, RpcProgramAccountsConfig},
+        rpc_filter::{Memcmp, RpcFilterType},
+        rpc_request::{RpcError, RpcResponseErrorData},
+        rpc_response::RpcConfirmedTransactionStatusWithSignature,
+    },
+    solana_sdk::{
+        account::Account,
+        commitment_config::CommitmentConfig,
+        hash::Hash,
+        instruction::{Instruction, InstructionError},
+        message::Message,
+        nonce::state::Data as NonceData,
+        pubkey::Pubkey,
+        signature::{Keypair, Signature, Signer},
+        transaction::{uses_durable_nonce, Transaction, TransactionError},
+    },
+    std::{str::FromStr, sync::Arc, time::Duration},
+    thiserror::Error,
+};
+
+pub use {
+    crate::instruction::{
+        find_config_address, find_farm_authority, find_farm_metadata_address, find_fee_schedule_address,
+    },
+    send::{compute_budget_instructions, SendOptions, MAX_COMPUTE_UNIT_LIMIT},
+};
+
+/// how long the RPC connection waits between two signature status checks
+const CONFIRM_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// most signatures getSignaturesForAddress returns at once
+pub const SIGNATURES_PAGE: usize = 1_000;
+
+/// Create of a farm account already allocated for the program
+/// the farm authority and its nonce are derived from the farm address
+#[derive(Clone, Debug, PartialEq)]
+pub struct CreateFarm {
+    pub farm: Pubkey,
+    pub creator: Pubkey,
+    /// token account of the farm authority
+    pub fee_vault: Pubkey,
+    pub duration: u64,
+    pub token_program_id: Pubkey,
+}
+
+impl CreateFarm {
+    pub fn new(farm: Pubkey, creator: Pubkey, fee_vault: Pubkey, duration: u64) -> Self {
+        CreateFarm {
+            farm,
+            creator,
+            fee_vault,
+            duration,
+            token_program_id: spl_token::id(),
+        }
+    }
+
+    pub fn token_program_id(mut self, token_program_id: Pubkey) -> Self {
+        self.token_program_id = token_program_id;
+        self
+    }
+
+    pub fn instruction(&self, farm_program_id: &Pubkey) -> Instruction {
+        let (authority, nonce) = find_farm_authority(&self.farm, farm_program_id);
+        ix_create_farm(
+            &self.farm,
+            &authority,
+            &self.creator,
+            &self.fee_vault,
+            &self.token_program_id,
+            farm_program_id,
+            nonce,
+            self.duration,
+        )
+    }
+}
+
+/// PayFarmFee of `amount`, which has to match the fee schedule
+#[derive(Clone, Debug, PartialEq)]
+pub struct PayFarmFee {
+    pub farm: Pubkey,
+    pub creator: Pubkey,
+    pub creator_token_account: Pubkey,
+    pub fee_vault: Pubkey,
+    pub amount: u64,
+    pub token_program_id: Pubkey,
+    /// receives the referral share of the fee
+    pub referrer_token_account: Option<Pubkey>,
+}
+
+impl PayFarmFee {
+    pub fn new(farm: Pubkey, creator: Pubkey, creator_token_account: Pubkey, fee_vault: Pubkey, amount: u64) -> Self {
+        PayFarmFee {
+            farm,
+            creator,
+            creator_token_account,
+            fee_vault,
+            amount,
+            token_program_id: spl_token::id(),
+            referrer_token_account: None,
+        }
+    }
+
+    pub fn token_program_id(mut self, token_program_id: Pubkey) -> Self {
+        self.token_program_id = token_program_id;
+        self
+    }
+
+    pub fn referrer(mut self, referrer_token_account: Pubkey) -> Self {
+        self.referrer_token_account = Some(referrer_token_account);
+        self
+    }
+
+    pub fn instruction(&self, farm_program_id: &Pubkey) -> Instruction {
+        let (authority, _) = find_farm_authority(&self.farm, farm_program_id);
+        match &self.referrer_token_account {
+            Some(referrer_token_account) => ix_pay_create_fee_with_referrer(
+                &self.farm,
+                &authority,
+                &self.creator,
+                &self.creator_token_account,
+                &self.fee_vault,
+                &self.token_program_id,
+                farm_program_id,
+                referrer_token_account,
+                self.amount,
+            ),
+            None => ix_pay_create_fee(
+                &self.farm,
+                &authority,
+                &self.creator,
+                &self.creator_token_account,
+                &self.fee_vault,
+                &self.token_program_id,
+                farm_program_id,
+                self.amount,
+            ),
+        }
+    }
+}
+
+/// InitializeConfig, `admin` pays for the program config, `upgrade_authority` of the program signs
+#[derive(Clone, Debug, PartialEq)]
+pub struct InitializeConfig {
+    pub admin: Pubkey,
+    pub upgrade_authority: Pubkey,
+}
+
+impl InitializeConfig {
+    pub fn instruction(&self, farm_program_id: &Pubkey) -> Instruction {
+        ix_initialize_config(&self.admin, &self.upgrade_authority, farm_program_id)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct SetPaused {
+    pub admin: Pubkey,
+    pub paused: bool,
+}
+
+impl SetPaused {
+    pub fn instruction(&self, farm_program_id: &Pubkey) -> Instruction {
+        ix_set_paused(&self.admin, farm_program_id, self.paused)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct TransferAdmin {
+    pub admin: Pubkey,
+    pub new_admin: Pubkey,
+}
+
+impl TransferAdmin {
+    pub fn instruction(&self, farm_program_id: &Pubkey) -> Instruction {
+        ix_transfer_admin(&self.admin, &self.new_admin, farm_program_id)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct SetReferralShare {
+    pub admin: Pubkey,
+    pub referral_bps: u16,
+}
+
+impl SetReferralShare {
+    pub fn instruction(&self, farm_program_id: &Pubkey) -> Instruction {
+        ix_set_referral_share(&self.admin, farm_program_id, self.referral_bps)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct SetFeeSchedule {
+    pub admin: Pubkey,
+    pub tiers: Vec<FeeTier>,
+    pub mints: Vec<FeeMint>,
+}
+
+impl SetFeeSchedule {
+    pub fn instruction(&self, farm_program_id: &Pubkey) -> Instruction {
+        ix_set_fee_schedule(&self.admin, farm_program_id, self.tiers.clone(), self.mints.clone())
+    }
+}
+
+/// ProposeCreator, proposing the default pubkey cancels a pending transfer
+#[derive(Clone, Debug, PartialEq)]
+pub struct ProposeCreator {
+    pub farm: Pubkey,
+    pub creator: Pubkey,
+    pub new_creator: Pubkey,
+}
+
+impl ProposeCreator {
+    pub fn instruction(&self, farm_program_id: &Pubkey) -> Instruction {
+        ix_propose_creator(&self.farm, &self.creator, &self.new_creator, farm_program_id)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct AcceptCreator {
+    pub farm: Pubkey,
+    pub pending_creator: Pubkey,
+}
+
+impl AcceptCreator {
+    pub fn instruction(&self, farm_program_id: &Pubkey) -> Instruction {
+        ix_accept_creator(&self.farm, &self.pending_creator, farm_program_id)
+    }
+}
+
+/// SetFarmMetadata, the creator pays for the metadata account
+#[derive(Clone, Debug, PartialEq)]
+pub struct SetFarmMetadata {
+    pub farm: Pubkey,
+    pub creator: Pubkey,
+    pub name: String,
+    pub symbol: String,
+    pub uri: String,
+    pub tags: Vec<String>,
+}
+
+impl SetFarmMetadata {
+    pub fn instruction(&self, farm_program_id: &Pubkey) -> Instruction {
+        ix_set_farm_metadata(
+            &self.farm,
+            &self.creator,
+            farm_program_id,
+            self.name.clone(),
+            self.symbol.clone(),
+            self.uri.clone(),
+            self.tags.clone(),
+        )
+    }
+}
+
+/// UpdateFarmMetadata, the creator pays the rent of a grown metadata account
+#[derive(Clone, Debug, PartialEq)]
+pub struct UpdateFarmMetadata {
+    pub farm: Pubkey,
+    pub creator: Pubkey,
+    pub name: String,
+    pub symbol: String,
+    pub uri: String,
+    pub tags: Vec<String>,
+}
+
+impl UpdateFarmMetadata {
+    pub fn instruction(&self, farm_program_id: &Pubkey) -> Instruction {
+        ix_update_farm_metadata(
+            &self.farm,
+            &self.creator,
+            farm_program_id,
+            self.name.clone(),
+            self.symbol.clone(),
+            self.uri.clone(),
+            self.tags.clone(),
+        )
+    }
+}
+
+/// SetOperator, the default pubkey removes the operator
+#[derive(Clone, Debug, PartialEq)]
+pub struct SetOperator {
+    pub farm: Pubkey,
+    pub creator: Pubkey,
+    pub operator: Pubkey,
+}
+
+impl SetOperator {
+    pub fn instruction(&self, farm_program_id: &Pubkey) -> Instruction {
+        ix_set_operator(&self.farm, &self.creator, &self.operator, farm_program_id)
+    }
+}
+
+/// RevokeOperator, signed by the creator or the operator itself
+#[derive(Clone, Debug, PartialEq)]
+pub struct RevokeOperator {
+    pub farm: Pubkey,
+    pub signer: Pubkey,
+}
+
+impl RevokeOperator {
+    pub fn instruction(&self, farm_program_id: &Pubkey) -> Instruction {
+        ix_revoke_operator(&self.farm, &self.signer, farm_program_id)
+    }
+}
+
+/// SetFarmPaused, signed by the creator or the operator
+#[derive(Clone, Debug, PartialEq)]
+pub struct SetFarmPaused {
+    pub farm: Pubkey,
+    pub signer: Pubkey,
+    pub paused: bool,
+}
+
+impl SetFarmPaused {
+    pub fn instruction(&self, farm_program_id: &Pubkey) -> Instruction {
+        ix_set_farm_paused(&self.farm, &self.signer, farm_program_id, self.paused)
+    }
+}
+
+/// `getProgramAccounts` filters matching farm accounts
+/// the config, fee schedule or any later account could share the farm size, the discriminator tells them apart
+pub fn farm_filters() -> Vec<RpcFilterType> {
+    vec![
+        RpcFilterType::DataSize(FarmAccount::LEN as u64),
+        RpcFilterType::Memcmp(Memcmp::new_base58_encoded(0, &FARM_DISCRIMINATOR)),
+    ]
+}
+
+/// `getProgramAccounts` filters matching farm metadata accounts, only the one of `farm` when given
+/// metadata accounts vary in size, the discriminator tells them apart from the other program accounts
+pub fn farm_metadata_filters(farm: Option<&Pubkey>) -> Vec<RpcFilterType> {
+    let mut filters = vec![RpcFilterType::Memcmp(Memcmp::new_base58_encoded(0, &FARM_METADATA_DISCRIMINATOR))];
+    if let Some(farm) = farm {
+        // after the discriminator and the bump
+        filters.push(RpcFilterType::Memcmp(Memcmp::new_base58_encoded(
+            FARM_METADATA_DISCRIMINATOR.len() + 1,
+            farm.as_ref(),
+        )));
+    }
+    filters
+}
+
+/// transaction history isn't served at processed commitment
+pub fn history_commitment(commitment: CommitmentConfig) -> CommitmentConfig {
+    if commitment.is_at_least_confirmed() {
+        commitment
+    } else {
+        CommitmentConfig::confirmed()
+    }
+}
+
+/// signatures of the transactions mentioning `address` newer than `until`, newest first
+/// getSignaturesForAddress is paged, a short page is the last one
+pub async fn get_signatures_since(
+    rpc: &RpcClient,
+    address: &Pubkey,
+    until: Option<Signature>,
+) -> Result<Vec<RpcConfirmedTransactionStatusWithSignature>, FarmClientError> {
+    let mut statuses = vec![];
+    let mut before = None;
+    loop {
+        let config = GetConfirmedSignaturesForAddress2Config {
+            before,
+            until,
+            limit: Some(SIGNATURES_PAGE),
+            commitment: Some(history_commitment(rpc.commitment())),
+        };
+        let page = rpc.get_signatures_for_address_with_config(address, config).await?;
+        let last_page = page.len() < SIGNATURES_PAGE;
+        before = match page.last() {
+            Some(status) => Some(
+                Signature::from_str(&status.signature)
+                    .map_err(|_| FarmClientError::InvalidSignature(status.signature.clone()))?,
+            ),
+            None => None,
+        };
+        statuses.extend(page);
+        if last_page {
+            return Ok(statuses);
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum FarmClientError {
+    #[error(transparent)]
+    Rpc(#[from] Box<ClientError>),
+
+    #[error(transparent)]
+    Banks(#[from] BanksClientError),
+
+    #[error("account {0} not found")]
+    AccountNotFound(Pubkey),
+
+    #[error("account {0} doesn't hold the expected farm program data")]
+    InvalidAccountData(Pubkey),
+
+    #[error("{0} is not supported by this connection")]
+    Unsupported(&'static str),
+
+    #[error("transaction rejected: {}", .0.error)]
+    Rejected(Box<RejectedTransaction>),
+
+    #[error("transaction {0} expired before it was processed")]
+    BlockhashExpired(Signature),
+
+    #[error("the node returned an invalid signature {0}")]
+    InvalidSignature(String),
+}
+
+/// a transaction the cluster refused in simulation or failed on chain
+/// `message` is the one that was sent, compute budget instructions included
+#[derive(Debug)]
+pub struct RejectedTransaction {
+    pub message: Message,
+    pub error: TransactionError,
+    /// program logs, empty when the node didn't return them
+    pub logs: Vec<String>,
+}
+
+impl FarmClientError {
+    fn rejected(transaction: &Transaction, error: TransactionError, logs: Vec<String>) -> Self {
+        FarmClientError::Rejected(Box::new(RejectedTransaction {
+            message: transaction.message.clone(),
+            error,
+            logs,
+        }))
+    }
+
+    /// a preflight failure of `transaction`, other errors are kept as they are
+    fn from_rpc(transaction: &Transaction, error: ClientError) -> Self {
+        match error.kind() {
+            ClientErrorKind::RpcError(RpcError::RpcResponseError {
+                data: RpcResponseErrorData::SendTransactionPreflightFailure(result),
+                ..
+            }) if result.err.is_some() => FarmClientError::rejected(
+                transaction,
+                result.err.clone().unwrap(),
+                result.logs.clone().unwrap_or_default(),
+            ),
+            ClientErrorKind::TransactionError(transaction_error) => {
+                FarmClientError::rejected(transaction, transaction_error.clone(), vec![])
+            }
+            _ => error.into(),
+        }
+    }
+
+    fn from_banks(transaction: &Transaction, error: BanksClientError) -> Self {
+        match error {
+            BanksClientError::TransactionError(error) => FarmClientError::rejected(transaction, error, vec![]),
+            BanksClientError::SimulationError { err, logs, .. } => FarmClientError::rejected(transaction, err, logs),
+            error => error.into(),
+        }
+    }
+}
+
+impl From<ClientError> for FarmClientError {
+    fn from(error: ClientError) -> Self {
+        FarmClientError::Rpc(Box::new(error))
+    }
+}
+
+impl FarmClientError {
+    /// error of a transaction the cluster rejected
+    pub fn transaction_error(&self) -> Option<TransactionError> {
+        match self {
+            FarmClientError::Rejected(rejected) => Some(rejected.error.clone()),
+            FarmClientError::Rpc(error) => error.get_transaction_error(),
+            FarmClientError::Banks(BanksClientError::TransactionError(error))
+            | FarmClientError::Banks(BanksClientError::SimulationError { err: error, .. }) => Some(error.clone()),
+            _ => None,
+        }
+    }
+
+    /// the blockhash ran out before the transaction landed, signing it again with a fresh one is safe
+    pub fn is_expired(&self) -> bool {
+        matches!(self, FarmClientError::BlockhashExpired(_))
+            || self.transaction_error() == Some(TransactionError::BlockhashNotFound)
+    }
+
+    /// the FarmError a rejected transaction carries
+    pub fn farm_error(&self) -> Option<FarmError> {
+        match self.transaction_error()? {
+            TransactionError::InstructionError(_, InstructionError::Custom(code)) => FarmError::from_u32(code),
+            _ => None,
+        }
+    }
+}
+
+/// what `FarmClient` needs from a connection to the cluster
+#[async_trait]
+pub trait FarmConnection {
+    async fn get_account(&self, address: &Pubkey) -> Result<Option<Account>, FarmClientError>;
+
+    /// accounts owned by `program_id` that match every filter
+    async fn get_program_accounts(
+        &self,
+        program_id: &Pubkey,
+        filters: Vec<RpcFilterType>,
+    ) -> Result<Vec<(Pubkey, Account)>, FarmClientError>;
+
+    async fn get_latest_blockhash(&self) -> Result<Hash, FarmClientError>;
+
+    /// compute units `transaction` consumes, when the node reports them
+    /// a transaction that fails in simulation comes back as `FarmClientError::Rejected`
+    async fn simulate_transaction(&self, transaction: &Transaction) -> Result<Option<u64>, FarmClientError>;
+
+    /// sends `transaction` and waits until it is processed
+    /// gives up with `FarmClientError::BlockhashExpired` once it can't land anymore
+    async fn send_transaction(&self, transaction: &Transaction) -> Result<Signature, FarmClientError>;
+}
+
+#[async_trait]
+impl<T: FarmConnection + Send + Sync + ?Sized> FarmConnection for Arc<T> {
+    async fn get_account(&self, address: &Pubkey) -> Result<Option<Account>, FarmClientError> {
+        (**self).get_account(address).await
+    }
+
+    async fn get_program_accounts(
+        &self,
+        program_id: &Pubkey,
+        filters: Vec<RpcFilterType>,
+    ) -> Result<Vec<(Pubkey, Account)>, FarmClientError> {
+        (**self).get_program_accounts(program_id, filters).await
+    }
+
+    async fn get_latest_blockhash(&self) -> Result<Hash, FarmClientError> {
+        (**self).get_latest_blockhash().await
+    }
+
+    async fn simulate_transaction(&self, transaction: &Transaction) -> Result<Option<u64>, FarmClientError> {
+        (**self).simulate_transaction(transaction).await
+    }
+
+    async fn send_transaction(&self, transaction: &Transaction) -> Result<Signature, FarmClientError> {
+        (**self).send_transaction(transaction).await
+    }
+}
+
+/// state of the durable nonce account at `address`
+fn nonce_data(address: &Pubkey, account: &Account) -> Result<NonceData, FarmClientError> {
+    data_from_account(account).map_err(|_| FarmClientError::InvalidAccountData(*address))
+}
+
+#[async_trait]
+impl FarmConnection for RpcClient {
+    async fn get_account(&self, address: &Pubkey) -> Result<Option<Account>, FarmClientError> {
+        Ok(self.get_account_with_commitment(address, self.commitment()).await?.value)
+    }
+
+    async fn get_program_accounts(
+        &self,
+        program_id: &Pubkey,
+        filters: Vec<RpcFilterType>,
+    ) -> Result<Vec<(Pubkey, Account)>, FarmClientError> {
+        let config = RpcProgramAccountsConfig {
+            filters: Some(filters),
+            account_config: RpcAccountInfoConfig {
+                commitment: Some(self.commitment()),
+                ..RpcAccountInfoConfig::default()
+            },
+            ..RpcProgramAccountsConfig::default()
+        };
+        Ok(self.get_program_accounts_with_config(program_id, config).await?)
+    }
+
+    async fn get_latest_blockhash(&self) -> Result<Hash, FarmClientError> {
+        Ok(RpcClient::get_latest_blockhash(self).await?)
+    }
+
+    async fn simulate_transaction(&self, transaction: &Transaction) -> Result<Option<u64>, FarmClientError> {
+        let result = RpcClient::simulate_transaction(self, transaction).await?.value;
+        match result.err {
+            Some(error) => Err(FarmClientError::rejected(transaction, error, result.logs.unwrap_or_default())),
+            None => Ok(result.units_consumed),
+        }
+    }
+
+    async fn send_transaction(&self, transaction: &Transaction) -> Result<Signature, FarmClientError> {
+        let signature = RpcClient::send_transaction(self, transaction)
+            .await
+            .map_err(|error| FarmClientError::from_rpc(transaction, error))?;
+        loop {
+            // checked before the status, a transaction can land right before its blockhash expires
+            let expired = match uses_durable_nonce(transaction) {
+                // a durable nonce stays valid until the nonce account advances
+                Some(advance_nonce) => {
+                    let address = transaction.message.account_keys[advance_nonce.accounts[0] as usize];
+                    let account = FarmConnection::get_account(self, &address)
+                        .await?
+                        .ok_or(FarmClientError::AccountNotFound(address))?;
+                    nonce_data(&address, &account)?.blockhash() != transaction.message.recent_blockhash
+                }
+                None => !self
+                    .is_blockhash_valid(&transaction.message.recent_blockhash, CommitmentConfig::processed())
+                    .await?,
+            };
+            if let Some(status) = self.get_signature_status_with_commitment(&signature, self.commitment()).await? {
+                return status
+                    .map(|()| signature)
+                    .map_err(|error| FarmClientError::rejected(transaction, error, vec![]));
+            }
+            // processed but not at our commitment yet, it can still get there
+            if expired
+                && self
+                    .get_signature_status_with_commitment(&signature, CommitmentConfig::processed())
+                    .await?
+                    .is_none()
+            {
+                return Err(FarmClientError::BlockhashExpired(signature));
+            }
+            tokio::time::sleep(CONFIRM_POLL_INTERVAL).await;
+        }
+    }
+}
+
+/// the banks server can't scan program accounts, `get_farms` and `get_farms_by_creator` need an `RpcClient`,
+/// and it can't send durable nonce transactions, it only simulates them
+#[async_trait]
+impl FarmConnection for BanksClient {
+    async fn get_account(&self, address: &Pubkey) -> Result<Option<Account>, FarmClientError> {
+        Ok(BanksClient::get_account(&mut self.clone(), *address).await?)
+    }
+
+    async fn get_program_accounts(
+        &self,
+        _program_id: &Pubkey,
+        _filters: Vec<RpcFilterType>,
+    ) -> Result<Vec<(Pubkey, Account)>, FarmClientError> {
+        Err(FarmClientError::Unsupported("getProgramAccounts"))
+    }
+
+    async fn get_latest_blockhash(&self) -> Result<Hash, FarmClientError> {
+        Ok(BanksClient::get_latest_blockhash(&mut self.clone()).await?)
+    }
+
+    async fn simulate_transaction(&self, transaction: &Transaction) -> Result<Option<u64>, FarmClientError> {
+        let simulation = BanksClient::simulate_transaction(&mut self.clone(), transaction.clone()).await?;
+        let (logs, units_consumed) = match simulation.simulation_details {
+            Some(details) => (details.logs, Some(details.units_consumed)),
+            None => (vec![], None),
+        };
+        match simulation.result {
+            Some(Err(error)) => Err(FarmClientError::rejected(transaction, error, logs)),
+            _ => Ok(units_consumed),
+        }
+    }
+
+    async fn send_transaction(&self, transaction: &Transaction) -> Result<Signature, FarmClientError> {
+        // the banks server looks the blockhash up in its recent blockhashes and panics on a nonce
+        if uses_durable_nonce(transaction).is_some() {
+            return Err(FarmClientError::Unsupported("sending durable nonce transactions"));
+        }
+        self.clone()
+            .process_transaction(transaction.clone())
+            .await
+            .map_err(|error| FarmClientError::from_banks(transaction, error))?;
+        Ok(transaction.signatures[0])
+    }
+}
+
+/// reads and writes the accounts of one deployment of the farm program
+pub struct FarmClient<C> {
+    program_id: Pubkey,
+    connection: C,
+    send_options: SendOptions,
+}
+
+impl<C: FarmConnection + Sync> FarmClient<C> {
+    pub fn new(program_id: Pubkey, connection: C) -> Self {
+        FarmClient {
+            program_id,
+            connection,
+            send_options: SendOptions::default(),
+        }
+    }
+
+    /// compute budget and retries of every transaction the client sends
+    pub fn send_options(mut self, send_options: SendOptions) -> Self {
+        self.send_options = send_options;
+        self
+    }
+
+    pub fn program_id(&self) -> &Pubkey {
+        &self.program_id
+    }
+
+    pub fn connection(&self) -> &C {
+        &self.connection
+    }
+
+    /// fetches `address` and decodes it as a `T` owned by the program
+    /// fixed size accounts like the fee schedule are padded, so trailing bytes are ignored
+    async fn get_program_account<T: BorshDeserialize>(&self, address: &Pubkey) -> Result<T, FarmClientError> {
+        let account = self
+            .connection
+            .get_account(address)
+            .await?
+            .ok_or(FarmClientError::AccountNotFound(*address))?;
+        self.decode(address, &account)
+    }
+
+    fn decode<T: BorshDeserialize>(&self, address: &Pubkey, account: &Account) -> Result<T, FarmClientError> {
+        if account.owner != self.program_id {
+            return Err(FarmClientError::InvalidAccountData(*address));
+        }
+        T::deserialize(&mut account.data.as_slice()).map_err(|_| FarmClientError::InvalidAccountData(*address))
+    }
+
+    pub async fn get_farm(&self, farm_id: &Pubkey) -> Result<Farm, FarmClientError> {
+        self.get_program_account(farm_id).await
+    }
+
+    /// a config created before the referral share reads with no referral share
+    pub async fn get_program_config(&self) -> Result<ProgramConfig, FarmClientError> {
+        let address = find_config_address(&self.program_id).0;
+        let account = self.connection.get_account(&address).await?.ok_or(FarmClientError::AccountNotFound(address))?;
+        if account.owner != self.program_id {
+            return Err(FarmClientError::InvalidAccountData(address));
+        }
+        ProgramConfig::unpack(&account.data).map_err(|_| FarmClientError::InvalidAccountData(address))
+    }
+
+    pub async fn get_fee_schedule(&self) -> Result<FeeSchedule, FarmClientError> {
+        self.get_program_account(&find_fee_schedule_address(&self.program_id).0).await
+    }
+
+    /// metadata of `farm_id`, `AccountNotFound` when the creator never set it
+    pub async fn get_farm_metadata(&self, farm_id: &Pubkey) -> Result<FarmMetadata, FarmClientError> {
+        self.get_program_account(&find_farm_metadata_address(farm_id, &self.program_id).0).await
+    }
+
+    /// metadata of every farm that has some
+    pub async fn get_all_farm_metadata(&self) -> Result<Vec<(Pubkey, FarmMetadata)>, FarmClientError> {
+        self.connection
+            .get_program_accounts(&self.program_id, farm_metadata_filters(None))
+            .await?
+            .iter()
+            .map(|(address, account)| Ok((*address, self.decode(address, account)?)))
+            .collect()
+    }
+
+    /// durable nonce account, its blockhash is the one offline signers sign with
+    pub async fn get_nonce_data(&self, nonce_account: &Pubkey) -> Result<NonceData, FarmClientError> {
+        let account = self
+            .connection
+            .get_account(nonce_account)
+            .await?
+            .ok_or(FarmClientError::AccountNotFound(*nonce_account))?;
+        nonce_data(nonce_account, &account)
+    }
+
+    /// every farm of the program
+    pub async fn get_farms(&self) -> Result<Vec<(Pubkey, Farm)>, FarmClientError> {
+        self.scan_farms(vec![]).await
+    }
+
+    /// every farm `creator` currently owns, pending transfers don't count
+    pub async fn get_farms_by_creator(&self, creator: &Pubkey) -> Result<Vec<(Pubkey, Farm)>, FarmClientError> {
+        self.scan_farms(vec![RpcFilterType::Memcmp(Memcmp::new_base58_encoded(
+            bytemuck::offset_of!(FarmAccount, creator),
+            creator.as_ref(),
+        ))])
+        .await
+    }
+
+    async fn scan_farms(&self, filters: Vec<RpcFilterType>) -> Result<Vec<(Pubkey, Farm)>, FarmClientError> {
+        let filters = farm_filters().into_iter().chain(filters).collect();
+        self.connection
+            .get_program_accounts(&self.program_id, filters)
+            .await?
+            .iter()
+            .map(|(farm_id, account)| Ok((*farm_id, self.decode(farm_id, account)?)))
+            .collect()
+    }
+
+    /// pays the fee of `farm_id` from `creator_token_account`
+    /// the fee vault and token program are read from the farm
+    pub async fn send_pay_fee(
+        &self,
+        farm_id: &Pubkey,
+        payer: &Keypair,
+        creator: &Keypair,
+        creator_token_account: &Pubkey,
+        referrer_token_account: Option<&Pubkey>,
+        amount: u64,
+    ) -> Result<Signature, FarmClientError> {
+        let farm = self.get_farm(farm_id).await?;
+        let mut pay = PayFarmFee::new(*farm_id, creator.pubkey(), *creator_token_account, farm.fee_vault, amount)
+            .token_program_id(farm.token_program_id);
+        if let Some(referrer_token_account) = referrer_token_account {
+            pay = pay.referrer(*referrer_token_account);
+        }
+        self.send(payer, &[pay.instruction(&self.program_id)], &[creator]).await
+    }
+}
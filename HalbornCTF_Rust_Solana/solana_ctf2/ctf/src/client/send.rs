@@ -0,0 +1,119 @@
+use {
+    super::{FarmClient, FarmClientError, FarmConnection},
+    crate::constant::MAX_BPS,
+    solana_sdk::{
+        compute_budget::ComputeBudgetInstruction,
+        hash::Hash,
+        instruction::Instruction,
+        signature::{Keypair, Signature, Signer},
+        transaction::Transaction,
+    },
+};
+
+/// the most compute units a transaction can request
+pub const MAX_COMPUTE_UNIT_LIMIT: u32 = 1_400_000;
+
+/// compute budget and retries of the transactions `FarmClient` sends
+///
+/// by default a transaction is simulated first, its compute unit limit is what the simulation
+/// consumed plus `compute_unit_margin_bps`, and it is signed again with a fresh blockhash
+/// up to `max_resends` times when it expires before landing
+#[derive(Clone, Debug, PartialEq)]
+pub struct SendOptions {
+    /// fixed compute unit limit, skips the simulation
+    pub compute_unit_limit: Option<u32>,
+    /// priority fee in micro-lamports per compute unit
+    pub compute_unit_price: Option<u64>,
+    /// simulate to estimate the compute unit limit when none is given
+    pub simulate: bool,
+    /// headroom on top of the simulated units, in basis points
+    pub compute_unit_margin_bps: u16,
+    pub max_resends: usize,
+}
+
+impl Default for SendOptions {
+    fn default() -> Self {
+        SendOptions {
+            compute_unit_limit: None,
+            compute_unit_price: None,
+            simulate: true,
+            compute_unit_margin_bps: 1_000,
+            max_resends: 3,
+        }
+    }
+}
+
+impl SendOptions {
+    /// limit for a transaction that consumed `units_consumed` in simulation
+    pub fn compute_unit_limit_for(&self, units_consumed: u64) -> u32 {
+        let units = units_consumed as u128 * (MAX_BPS as u128 + self.compute_unit_margin_bps as u128) / MAX_BPS as u128;
+        units.min(MAX_COMPUTE_UNIT_LIMIT as u128) as u32
+    }
+}
+
+/// compute budget instructions to put in front of a transaction, none when both are None
+pub fn compute_budget_instructions(compute_unit_limit: Option<u32>, compute_unit_price: Option<u64>) -> Vec<Instruction> {
+    let mut instructions = vec![];
+    if let Some(compute_unit_limit) = compute_unit_limit {
+        instructions.push(ComputeBudgetInstruction::set_compute_unit_limit(compute_unit_limit));
+    }
+    if let Some(compute_unit_price) = compute_unit_price {
+        instructions.push(ComputeBudgetInstruction::set_compute_unit_price(compute_unit_price));
+    }
+    instructions
+}
+
+fn signed_transaction(
+    payer: &Keypair,
+    instructions: &[Instruction],
+    signers: &[&Keypair],
+    recent_blockhash: Hash,
+) -> Transaction {
+    let mut transaction = Transaction::new_with_payer(instructions, Some(&payer.pubkey()));
+    transaction.sign(&[&[payer], signers].concat(), recent_blockhash);
+    transaction
+}
+
+impl<C: FarmConnection + Sync> FarmClient<C> {
+    /// signs `instructions` with the payer and `signers` and sends them in one transaction,
+    /// with the compute budget and retries of the client `SendOptions`
+    /// a transaction that fails in simulation is never sent, so it costs no fee
+    pub async fn send(
+        &self,
+        payer: &Keypair,
+        instructions: &[Instruction],
+        signers: &[&Keypair],
+    ) -> Result<Signature, FarmClientError> {
+        let options = &self.send_options;
+        let mut compute_unit_limit = options.compute_unit_limit;
+        if compute_unit_limit.is_none() && options.simulate {
+            // simulated with the max limit so the default per-instruction limit doesn't cap the estimate
+            let budget = compute_budget_instructions(Some(MAX_COMPUTE_UNIT_LIMIT), options.compute_unit_price);
+            let recent_blockhash = self.connection.get_latest_blockhash().await?;
+            let transaction = signed_transaction(payer, &[budget, instructions.to_vec()].concat(), signers, recent_blockhash);
+            compute_unit_limit = self
+                .connection
+                .simulate_transaction(&transaction)
+                .await?
+                .map(|units_consumed| options.compute_unit_limit_for(units_consumed));
+        }
+
+        let instructions =
+            [compute_budget_instructions(compute_unit_limit, options.compute_unit_price), instructions.to_vec()].concat();
+        let mut resends = 0;
+        loop {
+            let recent_blockhash = self.connection.get_latest_blockhash().await?;
+            let transaction = signed_transaction(payer, &instructions, signers, recent_blockhash);
+            match self.connection.send_transaction(&transaction).await {
+                Err(error) if error.is_expired() && resends < options.max_resends => resends += 1,
+                result => return result,
+            }
+        }
+    }
+
+    /// sends a transaction signed elsewhere, by offline signers for instance
+    /// it can't be signed again, so there is no simulation and no resend
+    pub async fn send_signed(&self, transaction: &Transaction) -> Result<Signature, FarmClientError> {
+        self.connection.send_transaction(transaction).await
+    }
+}
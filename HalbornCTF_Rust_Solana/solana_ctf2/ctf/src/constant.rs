@@ -1 +1,45 @@
-pub const FARM_FEE:u64 = 5000;
\ No newline at end of file
+/// fee of the original single-mint schedule, the usual base amount of a fee mint
+pub const FARM_FEE:u64 = 5000;
+
+/// seed of the program config PDA
+pub const CONFIG_SEED: &[u8] = b"config";
+
+/// basis points in 100%, upper bound of the referral share
+pub const MAX_BPS: u16 = 10_000;
+
+/// seed of the fee schedule PDA
+pub const FEE_SCHEDULE_SEED: &[u8] = b"fee_schedule";
+
+/// most duration tiers a fee schedule holds
+pub const MAX_FEE_TIERS: usize = 8;
+
+/// most payment mints a fee schedule accepts
+pub const MAX_FEE_MINTS: usize = 8;
+
+/// seed of the farm metadata PDA, followed by the farm address
+pub const FARM_METADATA_SEED: &[u8] = b"metadata";
+
+/// first bytes of every farm metadata account, a memcmp on them finds all of them
+/// no other account of the program starts with it
+pub const FARM_METADATA_DISCRIMINATOR: [u8; 8] = *b"farmmeta";
+
+/// first bytes of every farm account, a memcmp on them finds all of them
+pub const FARM_DISCRIMINATOR: [u8; 8] = *b"farmacct";
+
+/// layout version of the farm account, bumped whenever fields are added
+pub const FARM_VERSION: u8 = 1;
+
+/// max length in bytes of the farm metadata name
+pub const MAX_METADATA_NAME_LEN: usize = 32;
+
+/// max length in bytes of the farm metadata symbol
+pub const MAX_METADATA_SYMBOL_LEN: usize = 10;
+
+/// max length in bytes of the farm metadata uri
+pub const MAX_METADATA_URI_LEN: usize = 200;
+
+/// most tags a farm metadata holds
+pub const MAX_METADATA_TAGS: usize = 8;
+
+/// max length in bytes of one farm metadata tag
+pub const MAX_METADATA_TAG_LEN: usize = 16;
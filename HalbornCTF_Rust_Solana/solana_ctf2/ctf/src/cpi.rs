@@ -0,0 +1,126 @@
+//! typed cross-program invocations of the farm program for on-chain callers
+//! a caller links the crate with the no-entrypoint feature and passes the account infos it received,
+//! the `_signed` variants sign for caller PDAs, e.g. a creator owned by the calling program
+
+use {
+    crate::instruction::{ix_create_farm, ix_pay_create_fee, ix_pay_create_fee_with_referrer},
+    solana_program::{account_info::AccountInfo, entrypoint::ProgramResult, program::invoke_signed},
+};
+
+/// accounts of `FarmInstruction::Create`, in instruction order
+pub struct CreateFarm<'a> {
+    /// allocated with FarmAccount::LEN bytes and owned by the farm program
+    pub farm: AccountInfo<'a>,
+    pub authority: AccountInfo<'a>,
+    pub creator: AccountInfo<'a>,
+    pub config: AccountInfo<'a>,
+    pub fee_vault: AccountInfo<'a>,
+    pub token_program: AccountInfo<'a>,
+}
+
+/// accounts of `FarmInstruction::PayFarmFee`, in instruction order
+pub struct PayFarmFee<'a> {
+    pub farm: AccountInfo<'a>,
+    pub authority: AccountInfo<'a>,
+    /// signs the fee transfer out of `creator_token_account`
+    pub creator: AccountInfo<'a>,
+    pub creator_token_account: AccountInfo<'a>,
+    pub fee_vault: AccountInfo<'a>,
+    pub token_program: AccountInfo<'a>,
+    pub config: AccountInfo<'a>,
+    pub fee_schedule: AccountInfo<'a>,
+    pub referrer_token_account: Option<AccountInfo<'a>>,
+}
+
+/// creates a farm, the creator has to be a signer of the calling instruction
+pub fn create_farm<'a>(
+    farm_program: &AccountInfo<'a>,
+    accounts: &CreateFarm<'a>,
+    nonce: u8,
+    duration: u64,
+) -> ProgramResult {
+    create_farm_signed(farm_program, accounts, nonce, duration, &[])
+}
+
+/// creates a farm, `signer_seeds` sign for a creator PDA of the calling program
+pub fn create_farm_signed<'a>(
+    farm_program: &AccountInfo<'a>,
+    accounts: &CreateFarm<'a>,
+    nonce: u8,
+    duration: u64,
+    signer_seeds: &[&[&[u8]]],
+) -> ProgramResult {
+    let instruction = ix_create_farm(
+        accounts.farm.key,
+        accounts.authority.key,
+        accounts.creator.key,
+        accounts.fee_vault.key,
+        accounts.token_program.key,
+        farm_program.key,
+        nonce,
+        duration,
+    );
+    invoke_signed(
+        &instruction,
+        &[
+            accounts.farm.clone(),
+            accounts.authority.clone(),
+            accounts.creator.clone(),
+            accounts.config.clone(),
+            accounts.fee_vault.clone(),
+            accounts.token_program.clone(),
+            farm_program.clone(),
+        ],
+        signer_seeds,
+    )
+}
+
+/// pays the farm fee, the creator has to be a signer of the calling instruction
+pub fn pay_farm_fee<'a>(farm_program: &AccountInfo<'a>, accounts: &PayFarmFee<'a>, amount: u64) -> ProgramResult {
+    pay_farm_fee_signed(farm_program, accounts, amount, &[])
+}
+
+/// pays the farm fee, `signer_seeds` sign for a creator PDA of the calling program
+pub fn pay_farm_fee_signed<'a>(
+    farm_program: &AccountInfo<'a>,
+    accounts: &PayFarmFee<'a>,
+    amount: u64,
+    signer_seeds: &[&[&[u8]]],
+) -> ProgramResult {
+    let instruction = match &accounts.referrer_token_account {
+        Some(referrer_token_account) => ix_pay_create_fee_with_referrer(
+            accounts.farm.key,
+            accounts.authority.key,
+            accounts.creator.key,
+            accounts.creator_token_account.key,
+            accounts.fee_vault.key,
+            accounts.token_program.key,
+            farm_program.key,
+            referrer_token_account.key,
+            amount,
+        ),
+        None => ix_pay_create_fee(
+            accounts.farm.key,
+            accounts.authority.key,
+            accounts.creator.key,
+            accounts.creator_token_account.key,
+            accounts.fee_vault.key,
+            accounts.token_program.key,
+            farm_program.key,
+            amount,
+        ),
+    };
+    let mut account_infos = vec![
+        accounts.farm.clone(),
+        accounts.authority.clone(),
+        accounts.creator.clone(),
+        accounts.creator_token_account.clone(),
+        accounts.fee_vault.clone(),
+        accounts.token_program.clone(),
+        accounts.config.clone(),
+        accounts.fee_schedule.clone(),
+    ];
+    account_infos.extend(accounts.referrer_token_account.clone());
+    account_infos.push(farm_program.clone());
+    invoke_signed(&instruction, &account_infos, signer_seeds)
+}
@@ -0,0 +1,45 @@
+use {
+    borsh::{BorshDeserialize, BorshSchema, BorshSerialize},
+    solana_program::{log::sol_log_data, program_error::ProgramError, pubkey::Pubkey},
+};
+
+#[repr(C)]
+#[derive(Clone, Debug, PartialEq, BorshSerialize, BorshDeserialize, BorshSchema)]
+/// events logged by the program as `Program data: <base64 borsh>`
+/// off-chain tools decode them from the transaction logs
+pub enum FarmEvent {
+    /// the farm fee was paid and the farm enabled
+    FarmFeePaid {
+        #[allow(dead_code)]
+        /// farm
+        farm: Pubkey,
+
+        #[allow(dead_code)]
+        /// creator who paid the fee
+        creator: Pubkey,
+
+        #[allow(dead_code)]
+        /// fee vault
+        fee_vault: Pubkey,
+
+        #[allow(dead_code)]
+        /// part of the fee sent to the fee vault
+        fee_vault_amount: u64,
+
+        #[allow(dead_code)]
+        /// referrer token account, if the creator was referred
+        referrer: Option<Pubkey>,
+
+        #[allow(dead_code)]
+        /// part of the fee sent to the referrer
+        referrer_amount: u64,
+    },
+}
+
+impl FarmEvent {
+    /// writes the event to the program log
+    pub fn emit(&self) -> Result<(), ProgramError> {
+        sol_log_data(&[&self.try_to_vec()?]);
+        Ok(())
+    }
+}
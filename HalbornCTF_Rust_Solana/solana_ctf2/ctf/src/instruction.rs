@@ -1,10 +1,16 @@
 #![allow(clippy::too_many_arguments)]
 
 use {
+    crate::{
+        constant::{CONFIG_SEED, FARM_METADATA_SEED, FEE_SCHEDULE_SEED},
+        state::{FarmRole, FeeMint, FeeTier},
+    },
     borsh::{BorshDeserialize, BorshSchema, BorshSerialize},
     solana_program::{
+        bpf_loader_upgradeable,
         instruction::{AccountMeta, Instruction},
         pubkey::Pubkey,
+        system_program,
     },
 };
 
@@ -15,35 +21,304 @@ pub enum FarmInstruction {
     ///   These represent the parameters that will be included from client side
     ///   [w] - writable (account), [s] - signer (account), [] - readonly (account)
     /// 
-    ///   0. `[w]` farm account
+    ///   0. `[w]` farm account, allocated with FarmAccount::LEN bytes and owned by the program
     ///   1. `[]` farm authority
     ///   2. `[s]` farm creator
-    ///   3. nonce
+    ///   3. `[]` program config
+    ///   4. `[]` fee vault, token account owned by the farm authority
+    ///   5. `[]` token program id
+    ///   6. nonce
+    ///   7. farm duration in seconds, the window the fee pays for, nonzero
     Create {
         #[allow(dead_code)]
         /// nonce
         nonce: u8,
+
+        #[allow(dead_code)]
+        /// duration
+        duration: u64,
     },
     
-    ///   Creator has to pay a fee to unlock the farm
+    ///   Creator has to pay a fee to unlock the farm for its duration
+    ///   once the window ends the farm is paid for again
     /// 
     ///   0. `[w]` farm account
     ///   1. `[]` farm authority
     ///   2. `[s]` farm creator
-    ///   4. `[]` farm creator token account
-    ///   5. `[]` fee vault
-    ///   6. `[]` token program id
-    ///   7. `[]` farm program id
-    ///   8. `[]` amount
+    ///   3. `[]` farm creator token account
+    ///   4. `[]` fee vault
+    ///   5. `[]` token program id
+    ///   6. `[]` program config
+    ///   7. `[]` fee schedule, amount has to match the fee it gives for the farm and payment mint
+    ///   8. `[w]` (optional) referrer token account, receives the referral share of the fee
+    ///   9. `[]` amount
     PayFarmFee(
         // farm fee
         u64
     ),
+
+    ///   Creates the program config, the upgrade authority of the program appoints the admin
+    ///   has to be sent right after the program is deployed
+    ///
+    ///   0. `[w]` program config
+    ///   1. `[ws]` admin, pays for the program config account
+    ///   2. `[]` system program
+    ///   3. `[]` ProgramData account of the program
+    ///   4. `[s]` upgrade authority of the program
+    InitializeConfig,
+
+    ///   Admin pauses or resumes every user-facing instruction
+    ///   stays callable while the program is paused
+    ///
+    ///   0. `[w]` program config
+    ///   1. `[s]` admin
+    ///   2. `[]` paused
+    SetPaused(bool),
+
+    ///   Admin hands the program config over to a new admin
+    ///   stays callable while the program is paused
+    ///
+    ///   0. `[w]` program config
+    ///   1. `[s]` admin
+    ///   2. `[]` new admin
+    TransferAdmin(Pubkey),
+
+    ///   Creator proposes a new creator for the farm
+    ///   nothing changes until the new creator accepts, proposing the default pubkey cancels
+    ///
+    ///   0. `[w]` farm account
+    ///   1. `[s]` farm creator
+    ///   2. `[]` program config
+    ///   3. `[]` new creator
+    ProposeCreator(Pubkey),
+
+    ///   Pending creator takes over the farm
+    ///
+    ///   0. `[w]` farm account
+    ///   1. `[s]` pending creator
+    ///   2. `[]` program config
+    AcceptCreator,
+
+    ///   Admin sets the share of the farm fee paid to referrers
+    ///   stays callable while the program is paused
+    ///   grows a config created before the referral share to ProgramConfig::LEN
+    ///
+    ///   0. `[w]` program config
+    ///   1. `[ws]` admin, pays the rent of the grown config
+    ///   2. `[]` system program
+    ///   3. `[]` referral share in basis points, at most MAX_BPS
+    SetReferralShare(u16),
+
+    ///   Admin creates or replaces the farm fee schedule
+    ///   stays callable while the program is paused
+    ///
+    ///   0. `[w]` fee schedule
+    ///   1. `[]` program config
+    ///   2. `[ws]` admin, pays for the fee schedule account on first use
+    ///   3. `[]` system program
+    ///   4. `[]` tiers, sorted by min_duration, at most MAX_FEE_TIERS
+    ///   5. `[]` mints, distinct, at most MAX_FEE_MINTS
+    SetFeeSchedule {
+        #[allow(dead_code)]
+        /// tiers
+        tiers: Vec<FeeTier>,
+
+        #[allow(dead_code)]
+        /// mints
+        mints: Vec<FeeMint>,
+    },
+
+    ///   Creator creates the metadata of the farm
+    ///
+    ///   0. `[w]` farm metadata, the PDA of the farm
+    ///   1. `[]` farm account
+    ///   2. `[ws]` farm creator, pays for the farm metadata account
+    ///   3. `[]` program config
+    ///   4. `[]` system program
+    ///   5. `[]` name, at most MAX_METADATA_NAME_LEN bytes
+    ///   6. `[]` symbol, at most MAX_METADATA_SYMBOL_LEN bytes
+    ///   7. `[]` uri, at most MAX_METADATA_URI_LEN bytes
+    ///   8. `[]` tags, at most MAX_METADATA_TAGS of MAX_METADATA_TAG_LEN bytes
+    SetFarmMetadata {
+        #[allow(dead_code)]
+        /// name
+        name: String,
+
+        #[allow(dead_code)]
+        /// symbol
+        symbol: String,
+
+        #[allow(dead_code)]
+        /// uri
+        uri: String,
+
+        #[allow(dead_code)]
+        /// tags
+        tags: Vec<String>,
+    },
+
+    ///   Creator replaces the metadata of the farm
+    ///   the account grows when the new metadata doesn't fit, the creator pays the extra rent
+    ///
+    ///   0. `[w]` farm metadata
+    ///   1. `[]` farm account
+    ///   2. `[ws]` farm creator
+    ///   3. `[]` program config
+    ///   4. `[]` system program
+    ///   5. `[]` name, symbol, uri and tags, with the limits of SetFarmMetadata
+    UpdateFarmMetadata {
+        #[allow(dead_code)]
+        /// name
+        name: String,
+
+        #[allow(dead_code)]
+        /// symbol
+        symbol: String,
+
+        #[allow(dead_code)]
+        /// uri
+        uri: String,
+
+        #[allow(dead_code)]
+        /// tags
+        tags: Vec<String>,
+    },
+
+    ///   Creator delegates pausing the farm to an operator
+    ///   the operator can't pay the farm fee, change the creator or the metadata
+    ///   farms hold no rewards, so there is nothing for the operator to top up
+    ///   setting the default pubkey removes the operator
+    ///
+    ///   0. `[w]` farm account
+    ///   1. `[s]` farm creator
+    ///   2. `[]` program config
+    ///   3. `[]` operator
+    SetOperator(Pubkey),
+
+    ///   Creator or operator removes the operator of the farm
+    ///
+    ///   0. `[w]` farm account
+    ///   1. `[s]` farm creator or operator
+    ///   2. `[]` program config
+    RevokeOperator,
+
+    ///   Creator or operator pauses or resumes a single farm
+    ///   pausing stays callable while the program is paused, resuming doesn't
+    ///
+    ///   0. `[w]` farm account
+    ///   1. `[s]` farm creator or operator
+    ///   2. `[]` program config
+    ///   3. `[]` paused
+    SetFarmPaused(bool),
+}
+
+impl FarmInstruction {
+    /// position of the farm account, None for instructions that don't act on a farm
+    pub fn farm_account_index(&self) -> Option<usize> {
+        match self {
+            FarmInstruction::Create { .. }
+            | FarmInstruction::PayFarmFee(_)
+            | FarmInstruction::ProposeCreator(_)
+            | FarmInstruction::AcceptCreator
+            | FarmInstruction::SetOperator(_)
+            | FarmInstruction::RevokeOperator
+            | FarmInstruction::SetFarmPaused(_) => Some(0),
+            FarmInstruction::SetFarmMetadata { .. } | FarmInstruction::UpdateFarmMetadata { .. } => Some(1),
+            FarmInstruction::InitializeConfig
+            | FarmInstruction::SetPaused(_)
+            | FarmInstruction::TransferAdmin(_)
+            | FarmInstruction::SetReferralShare(_)
+            | FarmInstruction::SetFeeSchedule { .. } => None,
+        }
+    }
+
+    /// position of the signer whose role on the farm is checked
+    /// None for Create, the farm has no roles yet, and for admin instructions
+    pub fn signer_account_index(&self) -> Option<usize> {
+        match self {
+            FarmInstruction::PayFarmFee(_)
+            | FarmInstruction::SetFarmMetadata { .. }
+            | FarmInstruction::UpdateFarmMetadata { .. } => Some(2),
+            FarmInstruction::ProposeCreator(_)
+            | FarmInstruction::AcceptCreator
+            | FarmInstruction::SetOperator(_)
+            | FarmInstruction::RevokeOperator
+            | FarmInstruction::SetFarmPaused(_) => Some(1),
+            FarmInstruction::Create { .. }
+            | FarmInstruction::InitializeConfig
+            | FarmInstruction::SetPaused(_)
+            | FarmInstruction::TransferAdmin(_)
+            | FarmInstruction::SetReferralShare(_)
+            | FarmInstruction::SetFeeSchedule { .. } => None,
+        }
+    }
+
+    /// roles of the farm allowed to sign the instruction
+    /// the operator only pauses the farm, it can't move funds, take over the farm or touch fees
+    pub fn allowed_roles(&self) -> &'static [FarmRole] {
+        match self {
+            FarmInstruction::PayFarmFee(_)
+            | FarmInstruction::ProposeCreator(_)
+            | FarmInstruction::SetFarmMetadata { .. }
+            | FarmInstruction::UpdateFarmMetadata { .. }
+            | FarmInstruction::SetOperator(_) => &[FarmRole::Creator],
+            FarmInstruction::RevokeOperator | FarmInstruction::SetFarmPaused(_) => {
+                &[FarmRole::Creator, FarmRole::Operator]
+            }
+            FarmInstruction::AcceptCreator => &[FarmRole::PendingCreator],
+            FarmInstruction::Create { .. }
+            | FarmInstruction::InitializeConfig
+            | FarmInstruction::SetPaused(_)
+            | FarmInstruction::TransferAdmin(_)
+            | FarmInstruction::SetReferralShare(_)
+            | FarmInstruction::SetFeeSchedule { .. } => &[],
+        }
+    }
+
+    /// position of the program config account in user-facing instructions
+    /// admin instructions return None, they keep working while the program is paused
+    pub fn config_account_index(&self) -> Option<usize> {
+        match self {
+            FarmInstruction::Create { .. } => Some(3),
+            FarmInstruction::PayFarmFee(_) => Some(6),
+            FarmInstruction::ProposeCreator(_)
+            | FarmInstruction::AcceptCreator
+            | FarmInstruction::SetOperator(_)
+            | FarmInstruction::RevokeOperator
+            | FarmInstruction::SetFarmPaused(_) => Some(2),
+            FarmInstruction::SetFarmMetadata { .. } | FarmInstruction::UpdateFarmMetadata { .. } => Some(3),
+            FarmInstruction::InitializeConfig
+            | FarmInstruction::SetPaused(_)
+            | FarmInstruction::TransferAdmin(_)
+            | FarmInstruction::SetReferralShare(_)
+            | FarmInstruction::SetFeeSchedule { .. } => None,
+        }
+    }
+}
+
+/// address and bump of the program config PDA
+pub fn find_config_address(farm_program_id: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[CONFIG_SEED], farm_program_id)
+}
+
+/// address and bump of the fee schedule PDA
+pub fn find_fee_schedule_address(farm_program_id: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[FEE_SCHEDULE_SEED], farm_program_id)
+}
+
+/// address and bump of the metadata PDA of `farm_id`
+pub fn find_farm_metadata_address(farm_id: &Pubkey, farm_program_id: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[FARM_METADATA_SEED, farm_id.as_ref()], farm_program_id)
+}
+
+/// address and nonce of the authority PDA of `farm_id`, it owns the fee vault
+pub fn find_farm_authority(farm_id: &Pubkey, farm_program_id: &Pubkey) -> (Pubkey, u8) {
+    farm_core::pda::find_authority(farm_id, farm_program_id)
 }
 
 /// you can use this helper function to create the PayFarmFee instruction in your client
 /// see PayFarmFee enum variant above for account breakdown
-/// please note [amount] HAS TO match the farm fee, otherwise your transaction is going to fail
+/// please note [amount] HAS TO match the fee schedule, otherwise your transaction is going to fail
 pub fn ix_pay_create_fee(
     farm_id: &Pubkey,
     authority: &Pubkey,
@@ -61,6 +336,8 @@ pub fn ix_pay_create_fee(
         AccountMeta::new(*creator_token_account, false),
         AccountMeta::new(*fee_vault, false),
         AccountMeta::new_readonly(*token_program_id, false),
+        AccountMeta::new_readonly(find_config_address(farm_program_id).0, false),
+        AccountMeta::new_readonly(find_fee_schedule_address(farm_program_id).0, false),
     ];
     Instruction {
         program_id: *farm_program_id,
@@ -69,22 +346,285 @@ pub fn ix_pay_create_fee(
     }
 }
 
+/// same as `ix_pay_create_fee`, the referral share of the fee goes to [referrer_token_account]
+pub fn ix_pay_create_fee_with_referrer(
+    farm_id: &Pubkey,
+    authority: &Pubkey,
+    creator: &Pubkey,
+    creator_token_account: &Pubkey,
+    fee_vault: &Pubkey,
+    token_program_id: &Pubkey,
+    farm_program_id: &Pubkey,
+    referrer_token_account: &Pubkey,
+    amount: u64,
+) -> Instruction {
+    let mut instruction = ix_pay_create_fee(
+        farm_id,
+        authority,
+        creator,
+        creator_token_account,
+        fee_vault,
+        token_program_id,
+        farm_program_id,
+        amount,
+    );
+    instruction.accounts.push(AccountMeta::new(*referrer_token_account, false));
+    instruction
+}
+
 // Helper function to Create the Farm 
 pub fn ix_create_farm(
     farm_id: &Pubkey,
     authority: &Pubkey,
     creator: &Pubkey,
+    fee_vault: &Pubkey,
+    token_program_id: &Pubkey,
     farm_program_id: &Pubkey,
     nonce: u8,
+    duration: u64,
 ) -> Instruction {
     let accounts = vec![
         AccountMeta::new(*farm_id, false),
         AccountMeta::new_readonly(*authority, false),
-        AccountMeta::new(*creator, true)
+        AccountMeta::new(*creator, true),
+        AccountMeta::new_readonly(find_config_address(farm_program_id).0, false),
+        AccountMeta::new_readonly(*fee_vault, false),
+        AccountMeta::new_readonly(*token_program_id, false),
+    ];
+    Instruction {
+        program_id: *farm_program_id,
+        accounts,
+        data: FarmInstruction::Create{nonce, duration}.try_to_vec().unwrap(),
+    }
+}
+
+// Helper function to create the program config, [admin] pays for it and [upgrade_authority]
+// of the program signs off on it
+pub fn ix_initialize_config(
+    admin: &Pubkey,
+    upgrade_authority: &Pubkey,
+    farm_program_id: &Pubkey,
+) -> Instruction {
+    let (program_data, _) = Pubkey::find_program_address(&[farm_program_id.as_ref()], &bpf_loader_upgradeable::id());
+    let accounts = vec![
+        AccountMeta::new(find_config_address(farm_program_id).0, false),
+        AccountMeta::new(*admin, true),
+        AccountMeta::new_readonly(system_program::id(), false),
+        AccountMeta::new_readonly(program_data, false),
+        AccountMeta::new_readonly(*upgrade_authority, true),
+    ];
+    Instruction {
+        program_id: *farm_program_id,
+        accounts,
+        data: FarmInstruction::InitializeConfig.try_to_vec().unwrap(),
+    }
+}
+
+// Helper function to pause or resume the program
+pub fn ix_set_paused(
+    admin: &Pubkey,
+    farm_program_id: &Pubkey,
+    paused: bool,
+) -> Instruction {
+    let accounts = vec![
+        AccountMeta::new(find_config_address(farm_program_id).0, false),
+        AccountMeta::new_readonly(*admin, true),
     ];
     Instruction {
         program_id: *farm_program_id,
         accounts,
-        data: FarmInstruction::Create{nonce:(nonce)}.try_to_vec().unwrap(),
+        data: FarmInstruction::SetPaused(paused).try_to_vec().unwrap(),
     }
-}
\ No newline at end of file
+}
+
+// Helper function to hand the program config over to [new_admin]
+pub fn ix_transfer_admin(
+    admin: &Pubkey,
+    new_admin: &Pubkey,
+    farm_program_id: &Pubkey,
+) -> Instruction {
+    let accounts = vec![
+        AccountMeta::new(find_config_address(farm_program_id).0, false),
+        AccountMeta::new_readonly(*admin, true),
+    ];
+    Instruction {
+        program_id: *farm_program_id,
+        accounts,
+        data: FarmInstruction::TransferAdmin(*new_admin).try_to_vec().unwrap(),
+    }
+}
+
+// Helper function to propose [new_creator] as the creator of the farm
+pub fn ix_propose_creator(
+    farm_id: &Pubkey,
+    creator: &Pubkey,
+    new_creator: &Pubkey,
+    farm_program_id: &Pubkey,
+) -> Instruction {
+    let accounts = vec![
+        AccountMeta::new(*farm_id, false),
+        AccountMeta::new_readonly(*creator, true),
+        AccountMeta::new_readonly(find_config_address(farm_program_id).0, false),
+    ];
+    Instruction {
+        program_id: *farm_program_id,
+        accounts,
+        data: FarmInstruction::ProposeCreator(*new_creator).try_to_vec().unwrap(),
+    }
+}
+
+// Helper function for the pending creator to accept the farm
+pub fn ix_accept_creator(
+    farm_id: &Pubkey,
+    pending_creator: &Pubkey,
+    farm_program_id: &Pubkey,
+) -> Instruction {
+    let accounts = vec![
+        AccountMeta::new(*farm_id, false),
+        AccountMeta::new_readonly(*pending_creator, true),
+        AccountMeta::new_readonly(find_config_address(farm_program_id).0, false),
+    ];
+    Instruction {
+        program_id: *farm_program_id,
+        accounts,
+        data: FarmInstruction::AcceptCreator.try_to_vec().unwrap(),
+    }
+}
+
+// Helper function for the creator to make [operator] the operator of the farm
+pub fn ix_set_operator(
+    farm_id: &Pubkey,
+    creator: &Pubkey,
+    operator: &Pubkey,
+    farm_program_id: &Pubkey,
+) -> Instruction {
+    let accounts = vec![
+        AccountMeta::new(*farm_id, false),
+        AccountMeta::new_readonly(*creator, true),
+        AccountMeta::new_readonly(find_config_address(farm_program_id).0, false),
+    ];
+    Instruction {
+        program_id: *farm_program_id,
+        accounts,
+        data: FarmInstruction::SetOperator(*operator).try_to_vec().unwrap(),
+    }
+}
+
+// Helper function for the creator or the operator to remove the operator of the farm
+pub fn ix_revoke_operator(
+    farm_id: &Pubkey,
+    signer: &Pubkey,
+    farm_program_id: &Pubkey,
+) -> Instruction {
+    let accounts = vec![
+        AccountMeta::new(*farm_id, false),
+        AccountMeta::new_readonly(*signer, true),
+        AccountMeta::new_readonly(find_config_address(farm_program_id).0, false),
+    ];
+    Instruction {
+        program_id: *farm_program_id,
+        accounts,
+        data: FarmInstruction::RevokeOperator.try_to_vec().unwrap(),
+    }
+}
+
+// Helper function for the creator or the operator to pause or resume the farm
+pub fn ix_set_farm_paused(
+    farm_id: &Pubkey,
+    signer: &Pubkey,
+    farm_program_id: &Pubkey,
+    paused: bool,
+) -> Instruction {
+    let accounts = vec![
+        AccountMeta::new(*farm_id, false),
+        AccountMeta::new_readonly(*signer, true),
+        AccountMeta::new_readonly(find_config_address(farm_program_id).0, false),
+    ];
+    Instruction {
+        program_id: *farm_program_id,
+        accounts,
+        data: FarmInstruction::SetFarmPaused(paused).try_to_vec().unwrap(),
+    }
+}
+
+// Helper function to set the referral share of the farm fee
+pub fn ix_set_referral_share(
+    admin: &Pubkey,
+    farm_program_id: &Pubkey,
+    referral_bps: u16,
+) -> Instruction {
+    let accounts = vec![
+        AccountMeta::new(find_config_address(farm_program_id).0, false),
+        AccountMeta::new(*admin, true),
+        AccountMeta::new_readonly(system_program::id(), false),
+    ];
+    Instruction {
+        program_id: *farm_program_id,
+        accounts,
+        data: FarmInstruction::SetReferralShare(referral_bps).try_to_vec().unwrap(),
+    }
+}
+
+// Helper function to create or replace the fee schedule
+pub fn ix_set_fee_schedule(
+    admin: &Pubkey,
+    farm_program_id: &Pubkey,
+    tiers: Vec<FeeTier>,
+    mints: Vec<FeeMint>,
+) -> Instruction {
+    let accounts = vec![
+        AccountMeta::new(find_fee_schedule_address(farm_program_id).0, false),
+        AccountMeta::new_readonly(find_config_address(farm_program_id).0, false),
+        AccountMeta::new(*admin, true),
+        AccountMeta::new_readonly(system_program::id(), false),
+    ];
+    Instruction {
+        program_id: *farm_program_id,
+        accounts,
+        data: FarmInstruction::SetFeeSchedule { tiers, mints }.try_to_vec().unwrap(),
+    }
+}
+
+// Helper function for the creator to create the metadata of the farm
+pub fn ix_set_farm_metadata(
+    farm_id: &Pubkey,
+    creator: &Pubkey,
+    farm_program_id: &Pubkey,
+    name: String,
+    symbol: String,
+    uri: String,
+    tags: Vec<String>,
+) -> Instruction {
+    Instruction {
+        program_id: *farm_program_id,
+        accounts: farm_metadata_accounts(farm_id, creator, farm_program_id),
+        data: FarmInstruction::SetFarmMetadata { name, symbol, uri, tags }.try_to_vec().unwrap(),
+    }
+}
+
+// Helper function for the creator to replace the metadata of the farm
+pub fn ix_update_farm_metadata(
+    farm_id: &Pubkey,
+    creator: &Pubkey,
+    farm_program_id: &Pubkey,
+    name: String,
+    symbol: String,
+    uri: String,
+    tags: Vec<String>,
+) -> Instruction {
+    Instruction {
+        program_id: *farm_program_id,
+        accounts: farm_metadata_accounts(farm_id, creator, farm_program_id),
+        data: FarmInstruction::UpdateFarmMetadata { name, symbol, uri, tags }.try_to_vec().unwrap(),
+    }
+}
+
+fn farm_metadata_accounts(farm_id: &Pubkey, creator: &Pubkey, farm_program_id: &Pubkey) -> Vec<AccountMeta> {
+    vec![
+        AccountMeta::new(find_farm_metadata_address(farm_id, farm_program_id).0, false),
+        AccountMeta::new_readonly(*farm_id, false),
+        AccountMeta::new(*creator, true),
+        AccountMeta::new_readonly(find_config_address(farm_program_id).0, false),
+        AccountMeta::new_readonly(system_program::id(), false),
+    ]
+}
@@ -1,19 +1,29 @@
 use solana_program::{
     account_info::{ AccountInfo},
-    entrypoint,
     entrypoint::ProgramResult,
-    program_error::PrintProgramError,
     pubkey::Pubkey,
 };
 
 pub mod error;
+pub mod event;
 pub mod instruction;
 pub mod processor;
 pub mod state;
 pub mod constant;
-
-// this registers the program entrypoint
-entrypoint!(process_instruction);
+#[cfg(feature = "cu-bench")]
+pub mod bench;
+#[cfg(feature = "client")]
+pub mod client;
+#[cfg(feature = "no-entrypoint")]
+pub mod cpi;
+
+// this registers the program entrypoint, crates linking the program leave it out with no-entrypoint
+#[cfg(not(any(feature = "cu-bench", feature = "no-entrypoint")))]
+solana_program::entrypoint!(process_instruction);
+#[cfg(all(feature = "cu-bench", not(feature = "no-entrypoint")))]
+use bench::process_instruction as process_bench_instruction;
+#[cfg(all(feature = "cu-bench", not(feature = "no-entrypoint")))]
+solana_program::entrypoint!(process_bench_instruction);
 
 /// this is the program entrypoint
 /// this function ALWAYS takes three parameters:
@@ -23,15 +33,8 @@ pub fn process_instruction(
     accounts: &[AccountInfo],
     _instruction_data: &[u8],
 ) -> ProgramResult {
-    // process the instruction
-    if let Err(error) = processor::Processor::process(program_id, accounts, _instruction_data) {
-        // revert the transaction and print the relevant error to validator log if processing fails
-        error.print::<error::FarmError>();
-        Err(error)
-    } else {
-        // otherwise return OK
-        Ok(())
-    }
+    // process the instruction, a failure reverts the transaction and prints the error to the validator log
+    farm_core::entrypoint::process_instruction(processor::Processor::process, program_id, accounts, _instruction_data)
 }
 
 
@@ -1,18 +1,16 @@
 use {
     crate::{
-        constant::FARM_FEE, error::FarmError, instruction::FarmInstruction, state::Farm
-    }, borsh::{BorshDeserialize, BorshSerialize}, num_traits::FromPrimitive, solana_program::{
+        constant::{CONFIG_SEED, FARM_DISCRIMINATOR, FARM_METADATA_DISCRIMINATOR, FARM_METADATA_SEED, FARM_VERSION, FEE_SCHEDULE_SEED, MAX_BPS}, error::FarmError, event::FarmEvent, instruction::FarmInstruction, state::{FarmAccount, FarmMetadata, FarmRole, FeeMint, FeeSchedule, FeeTier, ProgramConfig}
+    }, borsh::{BorshDeserialize, BorshSerialize}, farm_core::{
+        token::token_transfer,
+        validation::{check_authority, check_program_account, check_signer, check_token_program, check_upgrade_authority},
+    }, solana_program::{
         account_info::{
             next_account_info,
             AccountInfo,
-        }, borsh::try_from_slice_unchecked, decode_error::DecodeError, entrypoint::ProgramResult, instruction::{
-            AccountMeta,
-            Instruction
-        }, msg, program::invoke_signed, program_error::{PrintProgramError, ProgramError}, program_pack::Pack, pubkey::Pubkey
-    }, spl_token::{
-        instruction::TokenInstruction,
-        state::Account as TokenAccount
-    }
+        }, entrypoint::ProgramResult, msg, program::{invoke, invoke_signed}, program_error::ProgramError, program_pack::Pack, pubkey::Pubkey,
+        clock::Clock, rent::Rent, system_instruction, sysvar::Sysvar
+    }, spl_token::state::Account as TokenAccount, std::convert::TryFrom
 };
 
 pub struct Processor {}
@@ -20,153 +18,692 @@ impl Processor {
     /// this is the instruction data router
     pub fn process(program_id: &Pubkey, accounts: &[AccountInfo], input: &[u8]) -> ProgramResult {
         let instruction = FarmInstruction::try_from_slice(input)?;
+        // user-facing instructions are rejected while the program is paused,
+        // except pausing a farm, which only takes it further out of use
+        if let Some(config_index) = instruction.config_account_index() {
+            let config_info = accounts.get(config_index).ok_or(ProgramError::NotEnoughAccountKeys)?;
+            if Self::program_config(program_id, config_info)?.paused == 1
+                && !matches!(instruction, FarmInstruction::SetFarmPaused(true))
+            {
+                return Err(FarmError::ProgramPaused.into());
+            }
+        }
+        Self::check_farm_role(program_id, accounts, &instruction)?;
         // here we route the data based on instruction type
         match instruction {
+            // create a farm, it stays disabled until the fee is paid
+            FarmInstruction::Create { nonce, duration } => {
+                Self::process_create_farm(program_id, accounts, nonce, duration)
+            },
+
             // pay the farm fee
             FarmInstruction::PayFarmFee(amount) => {
                 Self::process_pay_farm_fee(program_id, accounts, amount)
             },
 
-            // otherwise return an error
-            _ => Err(FarmError::NotAllowed.into())
+            // admin instructions
+            FarmInstruction::InitializeConfig => {
+                Self::process_initialize_config(program_id, accounts)
+            },
+            FarmInstruction::SetPaused(paused) => {
+                Self::process_set_paused(program_id, accounts, paused)
+            },
+            FarmInstruction::TransferAdmin(new_admin) => {
+                Self::process_transfer_admin(program_id, accounts, new_admin)
+            },
+            FarmInstruction::SetReferralShare(referral_bps) => {
+                Self::process_set_referral_share(program_id, accounts, referral_bps)
+            },
+            FarmInstruction::SetFeeSchedule { tiers, mints } => {
+                Self::process_set_fee_schedule(program_id, accounts, tiers, mints)
+            },
+
+            // two-step creator transfer
+            FarmInstruction::ProposeCreator(new_creator) => {
+                Self::process_propose_creator(program_id, accounts, new_creator)
+            },
+            FarmInstruction::AcceptCreator => {
+                Self::process_accept_creator(program_id, accounts)
+            },
+
+            // farm metadata, creator only
+            FarmInstruction::SetFarmMetadata { name, symbol, uri, tags } => {
+                Self::process_set_farm_metadata(program_id, accounts, name, symbol, uri, tags)
+            },
+            FarmInstruction::UpdateFarmMetadata { name, symbol, uri, tags } => {
+                Self::process_update_farm_metadata(program_id, accounts, name, symbol, uri, tags)
+            },
+
+            // farm operator
+            FarmInstruction::SetOperator(operator) => {
+                Self::process_set_operator(program_id, accounts, operator)
+            },
+            FarmInstruction::RevokeOperator => {
+                Self::process_revoke_operator(program_id, accounts)
+            },
+            FarmInstruction::SetFarmPaused(paused) => {
+                Self::process_set_farm_paused(program_id, accounts, paused)
+            },
         }
     } 
 
+    /// this function applies the permission matrix of `FarmInstruction::allowed_roles`
+    /// a signer without a role the instruction allows is rejected here, so is a program account
+    /// that doesn't load as a farm; missing signatures and accounts of other programs go on to
+    /// the checks of the instruction itself
+    fn check_farm_role(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        instruction: &FarmInstruction,
+    ) -> ProgramResult {
+        let (farm_index, signer_index) = match (instruction.farm_account_index(), instruction.signer_account_index()) {
+            (Some(farm_index), Some(signer_index)) => (farm_index, signer_index),
+            _ => return Ok(()),
+        };
+        let (farm_id_info, signer_info) = match (accounts.get(farm_index), accounts.get(signer_index)) {
+            (Some(farm_id_info), Some(signer_info)) => (farm_id_info, signer_info),
+            _ => return Err(ProgramError::NotEnoughAccountKeys),
+        };
+        if !signer_info.is_signer || farm_id_info.owner != program_id {
+            return Ok(());
+        }
+        let farm_data = FarmAccount::load(farm_id_info)?;
+        // without a pending transfer there is no role to accept, AcceptCreator reports NoPendingCreator
+        if matches!(instruction, FarmInstruction::AcceptCreator) && farm_data.pending_creator == Pubkey::default() {
+            return Ok(());
+        }
+        if !instruction.allowed_roles().iter().any(|role| farm_data.has_role(signer_info.key, *role)) {
+            return Err(FarmError::Unauthorized.into());
+        }
+        Ok(())
+    }
+
+    /// this function initializes a farm in an account the client allocated for the program
+    /// the farm starts disabled, the creator enables it by paying the fee
+    pub fn process_create_farm(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        nonce: u8,
+        duration: u64,
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let farm_id_info = next_account_info(account_info_iter)?;
+        let authority_info = next_account_info(account_info_iter)?;
+        let creator_info = next_account_info(account_info_iter)?;
+        let _config_info = next_account_info(account_info_iter)?;
+        let fee_vault_info = next_account_info(account_info_iter)?;
+        let token_program_info = next_account_info(account_info_iter)?;
+
+        check_program_account(farm_id_info, program_id)?;
+        check_signer(creator_info)?;
+        let mut farm_data = FarmAccount::load_uninitialized_mut(farm_id_info)?;
+        check_authority(program_id, farm_id_info.key, nonce, authority_info)?;
+        check_token_program(token_program_info)?;
+        if *fee_vault_info.owner != spl_token::id() {
+            return Err(ProgramError::IncorrectProgramId);
+        }
+        if TokenAccount::unpack(&fee_vault_info.try_borrow_data()?)?.owner != *authority_info.key {
+            return Err(FarmError::InvalidFeeAccount.into());
+        }
+        // the duration is the window the fee pays for, a farm without one could never be active
+        if duration == 0 {
+            return Err(FarmError::InvalidFarmWindow.into());
+        }
+
+        farm_data.discriminator = FARM_DISCRIMINATOR;
+        farm_data.version = FARM_VERSION;
+        farm_data.enabled = 0;
+        farm_data.nonce = nonce;
+        farm_data.token_program_id = *token_program_info.key;
+        farm_data.creator = *creator_info.key;
+        farm_data.fee_vault = *fee_vault_info.key;
+        farm_data.pending_creator = Pubkey::default();
+        farm_data.set_duration(duration);
+        farm_data.operator = Pubkey::default();
+        farm_data.paused = 0;
+        farm_data.set_ends_at(0);
+        msg!("Farm created: {}", farm_id_info.key);
+        Ok(())
+    }
+
+    /// this function creates the program config PDA
+    /// the upgrade authority appoints the admin, the program starts unpaused
+    pub fn process_initialize_config(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let config_info = next_account_info(account_info_iter)?;
+        let admin_info = next_account_info(account_info_iter)?;
+        let system_program_info = next_account_info(account_info_iter)?;
+        let program_data_info = next_account_info(account_info_iter)?;
+        let upgrade_authority_info = next_account_info(account_info_iter)?;
+
+        check_signer(admin_info)?;
+        // whoever sends it first would become the admin otherwise
+        check_upgrade_authority(program_id, program_data_info, upgrade_authority_info)?;
+        let (config_key, bump) = Pubkey::find_program_address(&[CONFIG_SEED], program_id);
+        if *config_info.key != config_key {
+            return Err(FarmError::InvalidProgramConfig.into());
+        }
+        if !config_info.data_is_empty() {
+            return Err(FarmError::AlreadyInUse.into());
+        }
+
+        invoke_signed(
+            &system_instruction::create_account(
+                admin_info.key,
+                config_info.key,
+                Rent::get()?.minimum_balance(ProgramConfig::LEN),
+                ProgramConfig::LEN as u64,
+                program_id,
+            ),
+            &[admin_info.clone(), config_info.clone(), system_program_info.clone()],
+            &[&[CONFIG_SEED, &[bump]]],
+        )?;
+
+        let mut config = ProgramConfig::load_mut(config_info)?;
+        config.is_initialized = 1;
+        config.bump = bump;
+        config.admin = *admin_info.key;
+        Ok(())
+    }
+
+    /// this function lets the admin pause or resume the program
+    pub fn process_set_paused(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        paused: bool,
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let config_info = next_account_info(account_info_iter)?;
+        let admin_info = next_account_info(account_info_iter)?;
+
+        let mut config = Self::check_admin(program_id, config_info, admin_info)?;
+        config.paused = paused as u8;
+        config.pack_into(&mut config_info.try_borrow_mut_data()?);
+        msg!("Program paused: {}", paused);
+        Ok(())
+    }
+
+    /// this function hands the program config over to a new admin
+    pub fn process_transfer_admin(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        new_admin: Pubkey,
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let config_info = next_account_info(account_info_iter)?;
+        let admin_info = next_account_info(account_info_iter)?;
+
+        let mut config = Self::check_admin(program_id, config_info, admin_info)?;
+        config.admin = new_admin;
+        config.pack_into(&mut config_info.try_borrow_mut_data()?);
+        msg!("New admin: {}", new_admin);
+        Ok(())
+    }
+
+    /// this function sets the share of the farm fee paid to referrers
+    pub fn process_set_referral_share(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        referral_bps: u16,
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let config_info = next_account_info(account_info_iter)?;
+        let admin_info = next_account_info(account_info_iter)?;
+        let system_program_info = next_account_info(account_info_iter)?;
+
+        let mut config = Self::check_admin(program_id, config_info, admin_info)?;
+        if referral_bps > MAX_BPS {
+            return Err(FarmError::InvalidReferralShare.into());
+        }
+        // configs created before the referral share have no room for it
+        if config_info.data_len() < ProgramConfig::LEN {
+            let lamports = Rent::get()?.minimum_balance(ProgramConfig::LEN).saturating_sub(config_info.lamports());
+            if lamports > 0 {
+                invoke(
+                    &system_instruction::transfer(admin_info.key, config_info.key, lamports),
+                    &[admin_info.clone(), config_info.clone(), system_program_info.clone()],
+                )?;
+            }
+            config_info.realloc(ProgramConfig::LEN, false)?;
+        }
+        config.set_referral_bps(referral_bps);
+        config.pack_into(&mut config_info.try_borrow_mut_data()?);
+        msg!("Referral share: {} bps", referral_bps);
+        Ok(())
+    }
+
+    /// this function creates the fee schedule PDA on first use and replaces its tiers and mints
+    pub fn process_set_fee_schedule(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        tiers: Vec<FeeTier>,
+        mints: Vec<FeeMint>,
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let fee_schedule_info = next_account_info(account_info_iter)?;
+        let config_info = next_account_info(account_info_iter)?;
+        let admin_info = next_account_info(account_info_iter)?;
+        let system_program_info = next_account_info(account_info_iter)?;
+
+        Self::check_admin(program_id, config_info, admin_info)?;
+        let (fee_schedule_key, bump) = Pubkey::find_program_address(&[FEE_SCHEDULE_SEED], program_id);
+        if *fee_schedule_info.key != fee_schedule_key {
+            return Err(FarmError::InvalidFeeSchedule.into());
+        }
+        let fee_schedule = FeeSchedule { is_initialized: true, bump, tiers, mints };
+        if !fee_schedule.is_valid() {
+            return Err(FarmError::InvalidFeeSchedule.into());
+        }
+
+        if fee_schedule_info.data_is_empty() {
+            invoke_signed(
+                &system_instruction::create_account(
+                    admin_info.key,
+                    fee_schedule_info.key,
+                    Rent::get()?.minimum_balance(FeeSchedule::LEN),
+                    FeeSchedule::LEN as u64,
+                    program_id,
+                ),
+                &[admin_info.clone(), fee_schedule_info.clone(), system_program_info.clone()],
+                &[&[FEE_SCHEDULE_SEED, &[bump]]],
+            )?;
+        } else if fee_schedule_info.owner != program_id {
+            return Err(FarmError::InvalidFeeSchedule.into());
+        }
+        fee_schedule.serialize(&mut &mut fee_schedule_info.data.borrow_mut()[..])?;
+        msg!("Fee schedule: {} tiers, {} mints", fee_schedule.tiers.len(), fee_schedule.mints.len());
+        Ok(())
+    }
+
+    /// this function loads the fee schedule and makes sure it is the genuine fee schedule PDA
+    pub fn fee_schedule(
+        program_id: &Pubkey,
+        fee_schedule_info: &AccountInfo,
+    ) -> Result<FeeSchedule, ProgramError> {
+        if fee_schedule_info.owner != program_id {
+            return Err(FarmError::InvalidFeeSchedule.into());
+        }
+        let fee_schedule = FeeSchedule::deserialize(&mut &fee_schedule_info.data.borrow()[..])?;
+        if !fee_schedule.is_initialized {
+            return Err(FarmError::InvalidFeeSchedule.into());
+        }
+        let fee_schedule_key = Pubkey::create_program_address(&[FEE_SCHEDULE_SEED, &[fee_schedule.bump]], program_id)
+            .or(Err(FarmError::InvalidFeeSchedule))?;
+        if *fee_schedule_info.key != fee_schedule_key {
+            return Err(FarmError::InvalidFeeSchedule.into());
+        }
+        Ok(fee_schedule)
+    }
+
+    /// this function loads the program config and makes sure it is the genuine config PDA
+    pub fn program_config(
+        program_id: &Pubkey,
+        config_info: &AccountInfo,
+    ) -> Result<ProgramConfig, ProgramError> {
+        if config_info.owner != program_id {
+            return Err(FarmError::InvalidProgramConfig.into());
+        }
+        let config = ProgramConfig::unpack(&config_info.try_borrow_data()?)?;
+        if config.is_initialized != 1 {
+            return Err(FarmError::InvalidProgramConfig.into());
+        }
+        let config_key = Pubkey::create_program_address(&[CONFIG_SEED, &[config.bump]], program_id)
+            .or(Err(FarmError::InvalidProgramConfig))?;
+        if *config_info.key != config_key {
+            return Err(FarmError::InvalidProgramConfig.into());
+        }
+        Ok(config)
+    }
+
+    /// this function validates the admin signature against the program config and returns the config
+    fn check_admin(
+        program_id: &Pubkey,
+        config_info: &AccountInfo,
+        admin_info: &AccountInfo,
+    ) -> Result<ProgramConfig, ProgramError> {
+        let config = Self::program_config(program_id, config_info)?;
+        check_signer(admin_info)?;
+        if *admin_info.key != config.admin {
+            return Err(FarmError::WrongAdmin.into());
+        }
+        Ok(config)
+    }
+
     /// this function handles farm fee payment
     /// by default, farms are not allowed (inactive)
-    /// farm creator has to pay 5000 tokens to enable the farm
+    /// farm creator has to pay the fee given by the fee schedule to enable the farm
+    /// the farm stays active for its duration, then it is paid for again
     pub fn process_pay_farm_fee(
         program_id: &Pubkey,
         accounts: &[AccountInfo],
         amount: u64,
     ) -> ProgramResult {
         let account_info_iter = &mut accounts.iter();
-        msg!("Entra");
         let farm_id_info = next_account_info(account_info_iter)?;
         let authority_info = next_account_info(account_info_iter)?;
         let creator_info = next_account_info(account_info_iter)?;
         let creator_token_account_info = next_account_info(account_info_iter)?;
         let fee_vault_info = next_account_info(account_info_iter)?;
         let token_program_info = next_account_info(account_info_iter)?;
-        msg!("Entra1");
-        /* msg!("Farm id info Key {:?}", &farm_id_info.key);
-        msg!("Authority info Key {:?}", &authority_info.key);
-        msg!("Creator info Key {:?}", &creator_info.key);
-        msg!("Creator token info Key {:?}", &creator_token_account_info.key);
-        msg!("Fee vault info Key {:?}", &fee_vault_info.key);
-        msg!("Token program info Key {:?}", &token_program_info.key); */
-        //msg!("FARM ID INFO DATA : {:?}", farm_id_info.data);
-        let mut farm_data = try_from_slice_unchecked::<Farm>(&farm_id_info.data.borrow())?;
-        msg!("Entra2");
-        if farm_data.enabled == 1 {
+        let config_info = next_account_info(account_info_iter)?;
+        let fee_schedule_info = next_account_info(account_info_iter)?;
+        // the referrer is optional, older clients don't send it
+        let referrer_token_account_info = next_account_info(account_info_iter).ok();
+        // copy the farm out of the account so no borrow is held across the token transfer
+        let farm_data = *FarmAccount::load(farm_id_info)?;
+        // a farm whose window ended is paid for again, from now on
+        let now = Clock::get()?.unix_timestamp;
+        if farm_data.is_active(now) {
             return Err(FarmError::AlreadyInUse.into());
         }
-        msg!("Entra3");
-        if !creator_info.is_signer {
-            return Err(FarmError::SignatureMissing.into())
-        }
-        msg!("Entra4");
+        let ends_at = i64::try_from(farm_data.duration())
+            .ok()
+            .and_then(|duration| now.checked_add(duration))
+            .filter(|ends_at| *ends_at > now)
+            .ok_or(FarmError::InvalidFarmWindow)?;
+        check_signer(creator_info)?;
         if *creator_info.key != farm_data.creator {
-            return Err(FarmError::WrongCreator.into());
+            return Err(FarmError::Unauthorized.into());
         }
-        msg!("Entra5");
-        if *authority_info.key != Self::authority_id(program_id, farm_id_info.key, farm_data.nonce)? {
-            return Err(FarmError::InvalidProgramAddress.into());
+        if farm_data.paused == 1 {
+            return Err(FarmError::FarmPaused.into());
         }
-        msg!("Entra6");
-        if amount != FARM_FEE {
+        check_authority(program_id, farm_id_info.key, farm_data.nonce, authority_info)?;
+        check_token_program(token_program_info)?;
+        // the fee depends on the farm duration and the mint it is paid in
+        let payment_mint = TokenAccount::unpack_from_slice(&creator_token_account_info.try_borrow_data()?)?.mint;
+        let expected_fee = Self::fee_schedule(program_id, fee_schedule_info)?
+            .expected_fee(&payment_mint, farm_data.duration())
+            .ok_or(FarmError::UnsupportedFeeMint)?;
+        if amount != expected_fee {
             return Err(FarmError::InvalidFarmFee.into());
         }
-        msg!("Entra7");
-        //msg!("{:?}", &fee_vault_info.try_borrow_data()?);
-        let fee_vault_owner = TokenAccount::unpack_from_slice(&fee_vault_info.try_borrow_data()?)?.owner;
-
-        msg!("Entra8");
-        if fee_vault_owner != *authority_info.key {
+        let fee_vault = TokenAccount::unpack_from_slice(&fee_vault_info.try_borrow_data()?)?;
+        if fee_vault.owner != *authority_info.key || fee_vault.mint != payment_mint {
             return Err(FarmError::InvalidFeeAccount.into())
         }
-        msg!("Entra9");
-        Self::token_transfer(
+        let referrer_amount = match referrer_token_account_info {
+            Some(referrer_info) => {
+                if referrer_info.key == creator_token_account_info.key || referrer_info.key == fee_vault_info.key {
+                    return Err(FarmError::InvalidReferrer.into());
+                }
+                let referral_bps = Self::program_config(program_id, config_info)?.referral_bps();
+                // bounded by amount, referral_bps <= MAX_BPS
+                (amount as u128 * referral_bps as u128 / MAX_BPS as u128) as u64
+            }
+            None => 0,
+        };
+        let fee_vault_amount = amount - referrer_amount;
+        token_transfer(
             farm_id_info.key,
             token_program_info.clone(), 
             creator_token_account_info.clone(), 
             fee_vault_info.clone(), 
             creator_info.clone(), 
             farm_data.nonce, 
-            amount
+            fee_vault_amount
         )?;
-        msg!("Entra10");
+        if let Some(referrer_info) = referrer_token_account_info.filter(|_| referrer_amount > 0) {
+            token_transfer(
+                farm_id_info.key,
+                token_program_info.clone(),
+                creator_token_account_info.clone(),
+                referrer_info.clone(),
+                creator_info.clone(),
+                farm_data.nonce,
+                referrer_amount
+            )?;
+        }
+        FarmEvent::FarmFeePaid {
+            farm: *farm_id_info.key,
+            creator: *creator_info.key,
+            fee_vault: *fee_vault_info.key,
+            fee_vault_amount,
+            referrer: referrer_token_account_info.map(|referrer_info| *referrer_info.key),
+            referrer_amount,
+        }
+        .emit()?;
+        let mut farm_data = FarmAccount::load_mut(farm_id_info)?;
         farm_data.enabled = 1;
-        msg!("Entra11");
-        farm_data
-            .serialize(&mut *farm_id_info.data.borrow_mut())
-            .map_err(|e| e.into())
+        farm_data.set_ends_at(ends_at);
+        Ok(())
     }
 
-    /// this function validates the farm authority address
-    pub fn authority_id(
+    /// this function records a pending creator for the farm
+    /// the current creator keeps control until the pending creator accepts
+    pub fn process_propose_creator(
         program_id: &Pubkey,
-        my_info: &Pubkey,
-        nonce: u8,
-    ) -> Result<Pubkey, FarmError> {
-        Pubkey::create_program_address(&[&my_info.to_bytes()[..32], &[nonce]], program_id)
-            .or(Err(FarmError::InvalidProgramAddress))
-    }
-
-    /// this function facilitates token transfer
-    pub fn token_transfer<'a>(
-        pool: &Pubkey,
-        token_program: AccountInfo<'a>,
-        source: AccountInfo<'a>,
-        destination: AccountInfo<'a>,
-        authority: AccountInfo<'a>,
-        nonce: u8,
-        amount: u64,
-    ) -> Result<(), ProgramError> {
-        msg!("Token transfer 1");
-        let pool_bytes = pool.to_bytes();
-        let authority_signature_seeds = [&pool_bytes[..32], &[nonce]];
-        let signers = &[&authority_signature_seeds[..]];
-        msg!("Token transfer 2");
-        let data = TokenInstruction::Transfer{amount}.pack();
-        msg!("Token transfer 3");
-        let mut accounts = Vec::with_capacity(4);
-        accounts.push(AccountMeta::new(*source.key, false));
-        accounts.push(AccountMeta::new(*destination.key, false));
-        accounts.push(AccountMeta::new_readonly(*authority.key, true));
-        msg!("Token transfer 4");
-        let ix = Instruction {
-            program_id: *token_program.key,
-            accounts,
-            data,
-        };
-        msg!("Token transfer 5");
-        /* msg!("Source: {:?}", source);
-        msg!("Destination: {:?}", destination);
-        msg!("Authority: {:?}", authority);
-        msg!("Token program: {:?}", token_program); */
+        accounts: &[AccountInfo],
+        new_creator: Pubkey,
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let farm_id_info = next_account_info(account_info_iter)?;
+        let creator_info = next_account_info(account_info_iter)?;
+
+        check_program_account(farm_id_info, program_id)?;
+        check_signer(creator_info)?;
+        let mut farm_data = FarmAccount::load_mut(farm_id_info)?;
+        if *creator_info.key != farm_data.creator {
+            return Err(FarmError::Unauthorized.into());
+        }
+        farm_data.pending_creator = new_creator;
+        msg!("Pending creator: {}", new_creator);
+        Ok(())
+    }
+
+    /// this function hands the farm over to the pending creator
+    /// the pending creator has to sign, so the farm can't end up with a key nobody controls
+    pub fn process_accept_creator(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let farm_id_info = next_account_info(account_info_iter)?;
+        let pending_creator_info = next_account_info(account_info_iter)?;
+
+        check_program_account(farm_id_info, program_id)?;
+        check_signer(pending_creator_info)?;
+        let mut farm_data = FarmAccount::load_mut(farm_id_info)?;
+        if farm_data.pending_creator == Pubkey::default() {
+            return Err(FarmError::NoPendingCreator.into());
+        }
+        if *pending_creator_info.key != farm_data.pending_creator {
+            return Err(FarmError::Unauthorized.into());
+        }
+        farm_data.creator = farm_data.pending_creator;
+        farm_data.pending_creator = Pubkey::default();
+        msg!("New creator: {}", farm_data.creator);
+        Ok(())
+    }
+
+    /// this function creates the metadata PDA of a farm, sized for the metadata it holds
+    pub fn process_set_farm_metadata(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        name: String,
+        symbol: String,
+        uri: String,
+        tags: Vec<String>,
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let metadata_info = next_account_info(account_info_iter)?;
+        let farm_id_info = next_account_info(account_info_iter)?;
+        let creator_info = next_account_info(account_info_iter)?;
+        let _config_info = next_account_info(account_info_iter)?;
+        let system_program_info = next_account_info(account_info_iter)?;
+
+        Self::check_farm_creator(program_id, farm_id_info, creator_info)?;
+        let (metadata_key, bump) =
+            Pubkey::find_program_address(&[FARM_METADATA_SEED, farm_id_info.key.as_ref()], program_id);
+        if *metadata_info.key != metadata_key {
+            return Err(FarmError::InvalidFarmMetadata.into());
+        }
+        if !metadata_info.data_is_empty() {
+            return Err(FarmError::AlreadyInUse.into());
+        }
+        let metadata = FarmMetadata::new(*farm_id_info.key, bump, name, symbol, uri, tags);
+        if !metadata.is_valid() {
+            return Err(FarmError::FarmMetadataTooLong.into());
+        }
+
+        let data = metadata.try_to_vec()?;
         invoke_signed(
-            &ix,
-            &[source, destination, authority, token_program],
-            signers,
-        )
-    }   
-}
+            &system_instruction::create_account(
+                creator_info.key,
+                metadata_info.key,
+                Rent::get()?.minimum_balance(data.len()),
+                data.len() as u64,
+                program_id,
+            ),
+            &[creator_info.clone(), metadata_info.clone(), system_program_info.clone()],
+            &[&[FARM_METADATA_SEED, farm_id_info.key.as_ref(), &[bump]]],
+        )?;
+        metadata_info.data.borrow_mut().copy_from_slice(&data);
+        msg!("Farm metadata: {}", metadata.name);
+        Ok(())
+    }
 
-impl PrintProgramError for FarmError {
-    fn print<E>(&self)
-    where
-        E: 'static + std::error::Error + DecodeError<E> + PrintProgramError + FromPrimitive,
-    {
-        match self {
-            FarmError::AlreadyInUse => msg!("Error: account already in use"),
-            FarmError::InvalidProgramAddress => msg!("Error: the program address provided doesn't match the value generated by the program"),
-            FarmError::SignatureMissing => msg!("Error: signature missing"),
-            FarmError::InvalidFeeAccount => msg!("Error: fee vault mismatch"),
-            FarmError::WrongPoolMint => msg!("Error: pool mint incorrect"),
-            FarmError::NotAllowed => msg!("Error: farm not allowed"),
-            FarmError::InvalidFarmFee => msg!("Error: farm fee incorrect. should be {}",FARM_FEE),
-            FarmError::WrongCreator => msg!("Error: creator mismatch"),
-        }
-    }
-} 
+    /// this function replaces the metadata of a farm
+    /// the account only grows, shorter metadata leaves zeroes behind it
+    pub fn process_update_farm_metadata(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        name: String,
+        symbol: String,
+        uri: String,
+        tags: Vec<String>,
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let metadata_info = next_account_info(account_info_iter)?;
+        let farm_id_info = next_account_info(account_info_iter)?;
+        let creator_info = next_account_info(account_info_iter)?;
+        let _config_info = next_account_info(account_info_iter)?;
+        let system_program_info = next_account_info(account_info_iter)?;
+
+        Self::check_farm_creator(program_id, farm_id_info, creator_info)?;
+        let bump = Self::farm_metadata(program_id, farm_id_info.key, metadata_info)?.bump;
+        let metadata = FarmMetadata::new(*farm_id_info.key, bump, name, symbol, uri, tags);
+        if !metadata.is_valid() {
+            return Err(FarmError::FarmMetadataTooLong.into());
+        }
+
+        let data = metadata.try_to_vec()?;
+        if data.len() > metadata_info.data_len() {
+            let lamports = Rent::get()?.minimum_balance(data.len()).saturating_sub(metadata_info.lamports());
+            if lamports > 0 {
+                invoke(
+                    &system_instruction::transfer(creator_info.key, metadata_info.key, lamports),
+                    &[creator_info.clone(), metadata_info.clone(), system_program_info.clone()],
+                )?;
+            }
+            metadata_info.realloc(data.len(), false)?;
+        }
+        let mut account_data = metadata_info.data.borrow_mut();
+        account_data[..data.len()].copy_from_slice(&data);
+        account_data[data.len()..].fill(0);
+        msg!("Farm metadata: {}", metadata.name);
+        Ok(())
+    }
+
+    /// this function loads the metadata of `farm` and makes sure it is the genuine metadata PDA
+    pub fn farm_metadata(
+        program_id: &Pubkey,
+        farm: &Pubkey,
+        metadata_info: &AccountInfo,
+    ) -> Result<FarmMetadata, ProgramError> {
+        if metadata_info.owner != program_id {
+            return Err(FarmError::InvalidFarmMetadata.into());
+        }
+        let metadata = FarmMetadata::deserialize(&mut &metadata_info.data.borrow()[..])
+            .or(Err(FarmError::InvalidFarmMetadata))?;
+        if metadata.discriminator != FARM_METADATA_DISCRIMINATOR || metadata.farm != *farm {
+            return Err(FarmError::InvalidFarmMetadata.into());
+        }
+        let metadata_key = Pubkey::create_program_address(&[FARM_METADATA_SEED, farm.as_ref(), &[metadata.bump]], program_id)
+            .or(Err(FarmError::InvalidFarmMetadata))?;
+        if *metadata_info.key != metadata_key {
+            return Err(FarmError::InvalidFarmMetadata.into());
+        }
+        Ok(metadata)
+    }
+
+    /// this function makes `operator` the operator of the farm, the default pubkey removes it
+    pub fn process_set_operator(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        operator: Pubkey,
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let farm_id_info = next_account_info(account_info_iter)?;
+        let creator_info = next_account_info(account_info_iter)?;
+
+        Self::check_farm_creator(program_id, farm_id_info, creator_info)?;
+        FarmAccount::load_mut(farm_id_info)?.operator = operator;
+        msg!("Operator: {}", operator);
+        Ok(())
+    }
+
+    /// this function removes the operator of the farm
+    /// the operator can step down by itself, the creator can remove it at any time
+    pub fn process_revoke_operator(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let farm_id_info = next_account_info(account_info_iter)?;
+        let signer_info = next_account_info(account_info_iter)?;
+
+        Self::check_farm_creator_or_operator(program_id, farm_id_info, signer_info)?;
+        FarmAccount::load_mut(farm_id_info)?.operator = Pubkey::default();
+        msg!("Operator revoked");
+        Ok(())
+    }
+
+    /// this function pauses or resumes a single farm, a paused farm can't be paid for
+    pub fn process_set_farm_paused(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        paused: bool,
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let farm_id_info = next_account_info(account_info_iter)?;
+        let signer_info = next_account_info(account_info_iter)?;
+
+        Self::check_farm_creator_or_operator(program_id, farm_id_info, signer_info)?;
+        FarmAccount::load_mut(farm_id_info)?.paused = paused as u8;
+        msg!("Farm paused: {}", paused);
+        Ok(())
+    }
+
+    /// this function validates the creator signature against the farm
+    fn check_farm_creator(
+        program_id: &Pubkey,
+        farm_id_info: &AccountInfo,
+        creator_info: &AccountInfo,
+    ) -> ProgramResult {
+        check_program_account(farm_id_info, program_id)?;
+        check_signer(creator_info)?;
+        if FarmAccount::load(farm_id_info)?.creator != *creator_info.key {
+            return Err(FarmError::Unauthorized.into());
+        }
+        Ok(())
+    }
+
+    /// this function validates the signature of the creator or the operator against the farm
+    fn check_farm_creator_or_operator(
+        program_id: &Pubkey,
+        farm_id_info: &AccountInfo,
+        signer_info: &AccountInfo,
+    ) -> ProgramResult {
+        check_program_account(farm_id_info, program_id)?;
+        check_signer(signer_info)?;
+        let farm_data = FarmAccount::load(farm_id_info)?;
+        if !farm_data.has_role(signer_info.key, FarmRole::Creator) && !farm_data.has_role(signer_info.key, FarmRole::Operator) {
+            return Err(FarmError::Unauthorized.into());
+        }
+        Ok(())
+    }
+}
@@ -1,9 +1,23 @@
 #![allow(clippy::too_many_arguments)]
 use {
+    crate::{
+        constant::{
+            FARM_DISCRIMINATOR, FARM_METADATA_DISCRIMINATOR, FARM_VERSION, MAX_BPS, MAX_FEE_MINTS, MAX_FEE_TIERS, MAX_METADATA_NAME_LEN,
+            MAX_METADATA_SYMBOL_LEN, MAX_METADATA_TAGS, MAX_METADATA_TAG_LEN, MAX_METADATA_URI_LEN,
+        },
+        error::FarmError,
+    },
     borsh::{BorshDeserialize, BorshSchema, BorshSerialize},
+    bytemuck::{Pod, Zeroable},
     solana_program::{
+        account_info::AccountInfo,
+        program_error::ProgramError,
         pubkey::{Pubkey},
     },
+    std::{
+        cell::{Ref, RefMut},
+        convert::TryFrom,
+    },
 };
 
 #[repr(C)]
@@ -11,9 +25,326 @@ use {
 /// this structs describes a Farm
 /// all farms are disabled by default
 pub struct Farm {
+    /// FARM_DISCRIMINATOR
+    pub discriminator: [u8; 8],
+    /// FARM_VERSION of the layout the farm was written with
+    pub version: u8,
     pub enabled: u8,
     pub nonce: u8,
     pub token_program_id: Pubkey,
     pub creator: Pubkey,
     pub fee_vault: Pubkey,
-}
\ No newline at end of file
+    /// creator proposed by `ProposeCreator`, default when no transfer is pending
+    pub pending_creator: Pubkey,
+    /// farm duration in seconds, picks the fee tier
+    pub duration: u64,
+    /// delegate set by `SetOperator`, default when the farm has none
+    pub operator: Pubkey,
+    /// set by the creator or the operator, a paused farm can't be paid for
+    pub paused: u8,
+    /// unix timestamp the paid window ends at, `duration` seconds after the fee was paid
+    /// zero until the first payment, once it passes the farm has to be paid for again
+    pub ends_at: i64,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Pod, Zeroable)]
+/// zero-copy view of a Farm account
+/// the layout matches the borsh encoding of `Farm` byte for byte,
+/// so the program can read and write fields in place while clients keep decoding with borsh
+pub struct FarmAccount {
+    pub discriminator: [u8; 8],
+    pub version: u8,
+    pub enabled: u8,
+    pub nonce: u8,
+    pub token_program_id: Pubkey,
+    pub creator: Pubkey,
+    pub fee_vault: Pubkey,
+    pub pending_creator: Pubkey,
+    /// little endian u64, a plain u64 would need padding after the pubkeys
+    pub duration: [u8; 8],
+    pub operator: Pubkey,
+    pub paused: u8,
+    /// little endian i64
+    pub ends_at: [u8; 8],
+}
+
+impl FarmAccount {
+    /// size of the farm account data
+    pub const LEN: usize = std::mem::size_of::<FarmAccount>();
+
+    /// borrows the farm stored in `account` without copying it
+    pub fn load<'a>(account: &'a AccountInfo) -> Result<Ref<'a, Self>, ProgramError> {
+        check_farm_data(&account.try_borrow_data()?)?;
+        load_pod(account)
+    }
+
+    /// mutably borrows the farm stored in `account`, writes land directly in the account data
+    pub fn load_mut<'a>(account: &'a AccountInfo) -> Result<RefMut<'a, Self>, ProgramError> {
+        check_farm_data(&account.try_borrow_data()?)?;
+        load_pod_mut(account)
+    }
+
+    /// mutably borrows a freshly allocated farm account, Create writes the header
+    pub fn load_uninitialized_mut<'a>(account: &'a AccountInfo) -> Result<RefMut<'a, Self>, ProgramError> {
+        let farm = load_pod_mut::<Self>(account)?;
+        if farm.discriminator != [0; 8] || farm.creator != Pubkey::default() {
+            return Err(FarmError::AlreadyInUse.into());
+        }
+        Ok(farm)
+    }
+
+    pub fn duration(&self) -> u64 {
+        u64::from_le_bytes(self.duration)
+    }
+
+    pub fn set_duration(&mut self, duration: u64) {
+        self.duration = duration.to_le_bytes();
+    }
+
+    pub fn ends_at(&self) -> i64 {
+        i64::from_le_bytes(self.ends_at)
+    }
+
+    pub fn set_ends_at(&mut self, ends_at: i64) {
+        self.ends_at = ends_at.to_le_bytes();
+    }
+
+    /// whether the fee paid for the farm still covers `now`
+    pub fn is_active(&self, now: i64) -> bool {
+        self.enabled == 1 && now < self.ends_at()
+    }
+
+    /// whether `key` holds `role` on the farm, an unset operator or pending creator is nobody's role
+    pub fn has_role(&self, key: &Pubkey, role: FarmRole) -> bool {
+        let holder = match role {
+            FarmRole::Creator => self.creator,
+            FarmRole::Operator => self.operator,
+            FarmRole::PendingCreator => self.pending_creator,
+        };
+        holder != Pubkey::default() && holder == *key
+    }
+}
+
+/// what a signer is on a farm, `FarmInstruction::allowed_roles` gives the roles each instruction accepts
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FarmRole {
+    Creator,
+    Operator,
+    PendingCreator,
+}
+
+/// checks the header of the farm in `data`
+pub fn check_farm_data(data: &[u8]) -> Result<(), ProgramError> {
+    if data.len() < FarmAccount::LEN {
+        return Err(ProgramError::AccountDataTooSmall);
+    }
+    if data[..8] != FARM_DISCRIMINATOR || data[8] != FARM_VERSION {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    Ok(())
+}
+
+impl From<&FarmAccount> for Farm {
+    fn from(farm: &FarmAccount) -> Self {
+        Farm {
+            discriminator: farm.discriminator,
+            version: farm.version,
+            enabled: farm.enabled,
+            nonce: farm.nonce,
+            token_program_id: farm.token_program_id,
+            creator: farm.creator,
+            fee_vault: farm.fee_vault,
+            pending_creator: farm.pending_creator,
+            duration: farm.duration(),
+            operator: farm.operator,
+            paused: farm.paused,
+            ends_at: farm.ends_at(),
+        }
+    }
+}
+
+impl From<&Farm> for FarmAccount {
+    fn from(farm: &Farm) -> Self {
+        FarmAccount {
+            discriminator: farm.discriminator,
+            version: farm.version,
+            enabled: farm.enabled,
+            nonce: farm.nonce,
+            token_program_id: farm.token_program_id,
+            creator: farm.creator,
+            fee_vault: farm.fee_vault,
+            pending_creator: farm.pending_creator,
+            duration: farm.duration.to_le_bytes(),
+            operator: farm.operator,
+            paused: farm.paused,
+            ends_at: farm.ends_at.to_le_bytes(),
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Pod, Zeroable, BorshDeserialize, BorshSerialize, BorshSchema)]
+/// program wide settings, stored in the PDA derived from `CONFIG_SEED`
+/// while `paused` is set every user-facing instruction is rejected
+pub struct ProgramConfig {
+    pub is_initialized: u8,
+    pub paused: u8,
+    pub bump: u8,
+    pub admin: Pubkey,
+    /// share of the farm fee paid to referrers, in basis points (little endian u16)
+    pub referral_bps: [u8; 2],
+}
+
+impl ProgramConfig {
+    /// size of the program config account data
+    pub const LEN: usize = std::mem::size_of::<ProgramConfig>();
+
+    /// size of the configs created before `referral_bps` was added
+    pub const V0_LEN: usize = Self::LEN - 2;
+
+    /// copies the config out of `data`, a V0_LEN config reads with no referral share
+    pub fn unpack(data: &[u8]) -> Result<Self, ProgramError> {
+        if data.len() != Self::V0_LEN && data.len() < Self::LEN {
+            return Err(ProgramError::AccountDataTooSmall);
+        }
+        let len = data.len().min(Self::LEN);
+        let mut config = Self::default();
+        bytemuck::bytes_of_mut(&mut config)[..len].copy_from_slice(&data[..len]);
+        Ok(config)
+    }
+
+    /// writes the config back into `data`, a V0_LEN config only gets the fields it has room for
+    pub fn pack_into(&self, data: &mut [u8]) {
+        let len = data.len().min(Self::LEN);
+        data[..len].copy_from_slice(&bytemuck::bytes_of(self)[..len]);
+    }
+
+    /// borrows the config stored in `account` without copying it
+    pub fn load<'a>(account: &'a AccountInfo) -> Result<Ref<'a, Self>, ProgramError> {
+        load_pod(account)
+    }
+
+    /// mutably borrows the config stored in `account`
+    pub fn load_mut<'a>(account: &'a AccountInfo) -> Result<RefMut<'a, Self>, ProgramError> {
+        load_pod_mut(account)
+    }
+
+    pub fn referral_bps(&self) -> u16 {
+        u16::from_le_bytes(self.referral_bps)
+    }
+
+    pub fn set_referral_bps(&mut self, referral_bps: u16) {
+        self.referral_bps = referral_bps.to_le_bytes();
+    }
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, BorshDeserialize, BorshSerialize, BorshSchema)]
+/// fee multiplier for farms lasting at least `min_duration` seconds
+pub struct FeeTier {
+    pub min_duration: u64,
+    /// applied to the base amount of the payment mint, MAX_BPS keeps it unchanged
+    pub multiplier_bps: u32,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, BorshDeserialize, BorshSerialize, BorshSchema)]
+/// a mint the farm fee can be paid in, with its base amount
+pub struct FeeMint {
+    pub mint: Pubkey,
+    pub amount: u64,
+}
+
+#[repr(C)]
+#[derive(Clone, Debug, Default, PartialEq, BorshDeserialize, BorshSerialize, BorshSchema)]
+/// farm fee schedule, stored in the PDA derived from `FEE_SCHEDULE_SEED`
+/// tiers are sorted by `min_duration`, the account is sized for the max tiers and mints
+pub struct FeeSchedule {
+    pub is_initialized: bool,
+    pub bump: u8,
+    pub tiers: Vec<FeeTier>,
+    pub mints: Vec<FeeMint>,
+}
+
+impl FeeSchedule {
+    /// size of the fee schedule account data
+    pub const LEN: usize = 1 + 1 + 4 + MAX_FEE_TIERS * (8 + 4) + 4 + MAX_FEE_MINTS * (32 + 8);
+
+    /// fee a farm lasting `duration` seconds pays in `mint`, None when the mint isn't accepted
+    /// farms shorter than every tier pay the base amount
+    pub fn expected_fee(&self, mint: &Pubkey, duration: u64) -> Option<u64> {
+        let amount = self.mints.iter().find(|fee_mint| fee_mint.mint == *mint)?.amount;
+        let multiplier_bps = self
+            .tiers
+            .iter()
+            .rev()
+            .find(|tier| tier.min_duration <= duration)
+            .map_or(MAX_BPS as u32, |tier| tier.multiplier_bps);
+        u64::try_from(amount as u128 * multiplier_bps as u128 / MAX_BPS as u128).ok()
+    }
+
+    /// checks the limits and ordering the processor relies on
+    pub fn is_valid(&self) -> bool {
+        self.tiers.len() <= MAX_FEE_TIERS
+            && self.mints.len() <= MAX_FEE_MINTS
+            && self.tiers.windows(2).all(|pair| pair[0].min_duration < pair[1].min_duration)
+            && self.tiers.iter().all(|tier| tier.multiplier_bps > 0)
+            && self.mints.iter().all(|fee_mint| fee_mint.amount > 0)
+            && self
+                .mints
+                .iter()
+                .enumerate()
+                .all(|(i, fee_mint)| self.mints[..i].iter().all(|other| other.mint != fee_mint.mint))
+    }
+}
+
+#[repr(C)]
+#[derive(Clone, Debug, Default, PartialEq, BorshDeserialize, BorshSerialize, BorshSchema)]
+/// human readable info of a farm, stored in the PDA derived from `FARM_METADATA_SEED` and the farm
+/// the account grows with the metadata, an update to shorter values leaves zeroes at the end
+pub struct FarmMetadata {
+    /// FARM_METADATA_DISCRIMINATOR
+    pub discriminator: [u8; 8],
+    pub bump: u8,
+    pub farm: Pubkey,
+    pub name: String,
+    pub symbol: String,
+    pub uri: String,
+    pub tags: Vec<String>,
+}
+
+impl FarmMetadata {
+    pub fn new(farm: Pubkey, bump: u8, name: String, symbol: String, uri: String, tags: Vec<String>) -> Self {
+        FarmMetadata { discriminator: FARM_METADATA_DISCRIMINATOR, bump, farm, name, symbol, uri, tags }
+    }
+
+    /// checks the max lengths, in bytes of utf-8
+    pub fn is_valid(&self) -> bool {
+        self.name.len() <= MAX_METADATA_NAME_LEN
+            && self.symbol.len() <= MAX_METADATA_SYMBOL_LEN
+            && self.uri.len() <= MAX_METADATA_URI_LEN
+            && self.tags.len() <= MAX_METADATA_TAGS
+            && self.tags.iter().all(|tag| tag.len() <= MAX_METADATA_TAG_LEN)
+    }
+}
+
+/// maps the start of the account data onto `T`
+fn load_pod<'a, T: Pod>(account: &'a AccountInfo) -> Result<Ref<'a, T>, ProgramError> {
+    let len = std::mem::size_of::<T>();
+    let data = account.try_borrow_data()?;
+    if data.len() < len {
+        return Err(ProgramError::AccountDataTooSmall);
+    }
+    Ok(Ref::map(data, |data| bytemuck::from_bytes(&data[..len])))
+}
+
+/// mutably maps the start of the account data onto `T`
+fn load_pod_mut<'a, T: Pod>(account: &'a AccountInfo) -> Result<RefMut<'a, T>, ProgramError> {
+    let len = std::mem::size_of::<T>();
+    let data = account.try_borrow_mut_data()?;
+    if data.len() < len {
+        return Err(ProgramError::AccountDataTooSmall);
+    }
+    Ok(RefMut::map(data, |data| bytemuck::from_bytes_mut(&mut data[..len])))
+}
@@ -0,0 +1,340 @@
+// run with `cargo test --features client`
+#![cfg(feature = "client")]
+
+mod common;
+
+use {
+    common::FarmFixture,
+    ctf_solana_farm::{
+        client::{
+            farm_filters, farm_metadata_filters, find_farm_authority, find_farm_metadata_address, CreateFarm, FarmClient,
+            FarmClientError, FarmConnection, PayFarmFee, SendOptions, SetFarmMetadata, MAX_COMPUTE_UNIT_LIMIT,
+        },
+        constant::{FARM_DISCRIMINATOR, FARM_FEE},
+        error::FarmError,
+    },
+    solana_client::{nonblocking::rpc_client::RpcClient, rpc_filter::RpcFilterType},
+    solana_program_test::*,
+    solana_sdk::{
+        account::{Account, AccountSharedData},
+        commitment_config::CommitmentConfig,
+        compute_budget,
+        message::Message,
+        nonce,
+        pubkey::Pubkey,
+        rent::Rent,
+        signature::{Keypair, Signer},
+        system_instruction,
+        transaction::Transaction,
+    },
+    solana_validator::test_validator::TestValidatorGenesis,
+    std::str::FromStr,
+};
+
+async fn builders_match_instruction_helpers<const BPF: bool>() {
+    let ctx = FarmFixture::new().uncreated().start::<BPF>().await;
+    let farm = ctx.farm.pubkey();
+    assert_eq!(find_farm_authority(&farm, &ctx.program_id), (ctx.authority, ctx.nonce));
+
+    let create = CreateFarm::new(farm, ctx.creator.pubkey(), ctx.fee_vault, 86_400);
+    assert_eq!(
+        create.instruction(&ctx.program_id),
+        ctx.create_farm(&ctx.creator.pubkey(), &ctx.fee_vault, 86_400)
+    );
+
+    let pay = PayFarmFee::new(farm, ctx.creator.pubkey(), ctx.creator_token_account, ctx.fee_vault, FARM_FEE);
+    assert_eq!(pay.instruction(&ctx.program_id), ctx.pay_fee(&ctx.creator_token_account, None, FARM_FEE));
+    let pay = pay.referrer(ctx.referrer_token_account);
+    assert_eq!(
+        pay.instruction(&ctx.program_id),
+        ctx.pay_fee(&ctx.creator_token_account, Some(&ctx.referrer_token_account), FARM_FEE)
+    );
+}
+
+async fn get_farm<const BPF: bool>() {
+    let mut ctx = FarmFixture::new().duration(86_400).start::<BPF>().await;
+    let client = FarmClient::new(ctx.program_id, ctx.banks_client.clone());
+
+    assert_eq!(client.get_farm(&ctx.farm.pubkey()).await.unwrap(), ctx.get_farm().await);
+    assert_eq!(client.get_program_config().await.unwrap().admin, ctx.admin.pubkey());
+    assert_eq!(client.get_fee_schedule().await.unwrap().mints[0].mint, ctx.mint);
+
+    let missing = Pubkey::new_unique();
+    assert!(matches!(
+        client.get_farm(&missing).await,
+        Err(FarmClientError::AccountNotFound(address)) if address == missing
+    ));
+    // token accounts belong to the token program
+    assert!(matches!(
+        client.get_farm(&ctx.fee_vault).await,
+        Err(FarmClientError::InvalidAccountData(address)) if address == ctx.fee_vault
+    ));
+}
+
+async fn send_pay_fee<const BPF: bool>() {
+    let mut ctx = FarmFixture::new().start::<BPF>().await;
+    let client = FarmClient::new(ctx.program_id, ctx.banks_client.clone());
+    let farm = ctx.farm.pubkey();
+
+    let error = client
+        .send_pay_fee(&farm, &ctx.payer, &ctx.creator, &ctx.creator_token_account, None, FARM_FEE - 1)
+        .await
+        .unwrap_err();
+    assert_eq!(error.farm_error(), Some(FarmError::InvalidFarmFee));
+
+    client
+        .send_pay_fee(&farm, &ctx.payer, &ctx.creator, &ctx.creator_token_account, None, FARM_FEE)
+        .await
+        .unwrap();
+    assert_eq!(ctx.get_farm().await.enabled, 1);
+    assert_eq!(ctx.token_balance(ctx.fee_vault).await, FARM_FEE);
+}
+
+async fn farm_metadata<const BPF: bool>() {
+    let mut ctx = FarmFixture::new().start::<BPF>().await;
+    let client = FarmClient::new(ctx.program_id, ctx.banks_client.clone());
+    let farm = ctx.farm.pubkey();
+
+    assert!(matches!(client.get_farm_metadata(&farm).await, Err(FarmClientError::AccountNotFound(_))));
+    let set = SetFarmMetadata {
+        farm,
+        creator: ctx.creator.pubkey(),
+        name: "farm".to_string(),
+        symbol: "FARM".to_string(),
+        uri: "https://farm".to_string(),
+        tags: vec!["defi".to_string()],
+    };
+    let creator = Keypair::from_bytes(&ctx.creator.to_bytes()).unwrap();
+    ctx.send(&[set.instruction(&ctx.program_id)], &[&creator]).await.unwrap();
+    let metadata = client.get_farm_metadata(&farm).await.unwrap();
+    assert_eq!((metadata.farm, metadata.name, metadata.tags), (farm, set.name, set.tags));
+
+    // the filters pick the metadata account of the farm and nothing else
+    let metadata_address = find_farm_metadata_address(&farm, &ctx.program_id).0;
+    let connection = client.connection();
+    let metadata_account = AccountSharedData::from(connection.get_account(&metadata_address).await.unwrap().unwrap());
+    let farm_account = AccountSharedData::from(connection.get_account(&farm).await.unwrap().unwrap());
+    let allows = |filters: Vec<RpcFilterType>, account: &AccountSharedData| {
+        filters.iter().all(|filter| filter.allows(account))
+    };
+    assert!(allows(farm_metadata_filters(None), &metadata_account));
+    assert!(allows(farm_metadata_filters(Some(&farm)), &metadata_account));
+    assert!(!allows(farm_metadata_filters(Some(&Pubkey::new_unique())), &metadata_account));
+    assert!(!allows(farm_metadata_filters(None), &farm_account));
+}
+
+async fn farm_filters_match_farms<const BPF: bool>() {
+    let ctx = FarmFixture::new().start::<BPF>().await;
+    let farm_account = ctx.banks_client.get_account(&ctx.farm.pubkey()).await.unwrap().unwrap();
+    let allows = |account: &Account| farm_filters().iter().all(|filter| filter.allows(&AccountSharedData::from(account.clone())));
+    assert!(allows(&farm_account));
+
+    // another program account of the farm size is not a farm
+    let mut other_account = farm_account.clone();
+    other_account.data[..FARM_DISCRIMINATOR.len()].fill(0);
+    assert!(!allows(&other_account));
+    let mut truncated_account = farm_account;
+    truncated_account.data.pop();
+    assert!(!allows(&truncated_account));
+}
+
+async fn get_farms_by_creator_needs_rpc<const BPF: bool>() {
+    let ctx = FarmFixture::new().start::<BPF>().await;
+    let client = FarmClient::new(ctx.program_id, ctx.banks_client.clone());
+    assert!(matches!(
+        client.get_farms_by_creator(&ctx.creator.pubkey()).await,
+        Err(FarmClientError::Unsupported(_))
+    ));
+}
+
+#[test]
+fn test_get_farms_by_creator() {
+    let program_id = Pubkey::from_str("8gBxX2ZXm9E5eiyHfTrewq7JGpuhMxsedHzh5fpJMMRw").unwrap();
+    let fixture = FarmFixture::new().program_id(program_id);
+    let other_fixture = FarmFixture::new().program_id(program_id);
+    // the program accounts of the first fixture, plus the farm of another creator
+    let mut accounts = fixture.accounts(&fixture.addresses());
+    accounts.extend(
+        other_fixture
+            .accounts(&other_fixture.addresses())
+            .into_iter()
+            .filter(|(address, _)| *address == other_fixture.farm().pubkey()),
+    );
+
+    let mut genesis = TestValidatorGenesis::default();
+    genesis.add_program("target/deploy/ctf_solana_farm", program_id);
+    genesis.add_accounts(accounts.into_iter().map(|(address, account)| (address, AccountSharedData::from(account))));
+    let (test_validator, _) = genesis.start();
+
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    runtime.block_on(async {
+        let client = FarmClient::new(
+            program_id,
+            RpcClient::new_with_commitment(test_validator.rpc_url(), CommitmentConfig::confirmed()),
+        );
+        for fixture in [&fixture, &other_fixture] {
+            let farms = client.get_farms_by_creator(&fixture.creator().pubkey()).await.unwrap();
+            assert_eq!(farms.len(), 1);
+            assert_eq!(farms[0].0, fixture.farm().pubkey());
+            assert_eq!(farms[0].1.creator, fixture.creator().pubkey());
+        }
+        assert!(client.get_farms_by_creator(&Pubkey::new_unique()).await.unwrap().is_empty());
+    });
+}
+
+#[test]
+fn test_compute_unit_limit_for() {
+    let options = SendOptions::default();
+    assert_eq!(options.compute_unit_limit_for(10_000), 11_000);
+    assert_eq!(options.compute_unit_limit_for(u64::MAX), MAX_COMPUTE_UNIT_LIMIT);
+}
+
+async fn send_with_priority_fee<const BPF: bool>() {
+    let mut ctx = FarmFixture::new().start::<BPF>().await;
+    let options = SendOptions {
+        // one lamport per compute unit
+        compute_unit_price: Some(1_000_000),
+        ..SendOptions::default()
+    };
+    let client = FarmClient::new(ctx.program_id, ctx.banks_client.clone()).send_options(options);
+    let farm = ctx.farm.pubkey();
+
+    let balance = ctx.banks_client.get_balance(ctx.payer.pubkey()).await.unwrap();
+    client
+        .send_pay_fee(&farm, &ctx.payer, &ctx.creator, &ctx.creator_token_account, None, FARM_FEE)
+        .await
+        .unwrap();
+    assert_eq!(ctx.get_farm().await.enabled, 1);
+    // more than the 5000 lamports of each of the two signatures
+    assert!(balance - ctx.banks_client.get_balance(ctx.payer.pubkey()).await.unwrap() > 10_000);
+}
+
+async fn failed_simulation_is_not_sent<const BPF: bool>() {
+    let mut ctx = FarmFixture::new().start::<BPF>().await;
+    let client = FarmClient::new(ctx.program_id, ctx.banks_client.clone());
+    let farm = ctx.farm.pubkey();
+
+    let balance = ctx.banks_client.get_balance(ctx.payer.pubkey()).await.unwrap();
+    let error = client
+        .send_pay_fee(&farm, &ctx.payer, &ctx.creator, &ctx.creator_token_account, None, FARM_FEE - 1)
+        .await
+        .unwrap_err();
+    assert_eq!(error.farm_error(), Some(FarmError::InvalidFarmFee));
+    // the rejected message starts with the compute unit limit of the simulation
+    match error {
+        FarmClientError::Rejected(rejected) => {
+            let instruction = &rejected.message.instructions[0];
+            assert_eq!(rejected.message.account_keys[instruction.program_id_index as usize], compute_budget::id());
+        }
+        error => panic!("unexpected error {}", error),
+    }
+    assert_eq!(ctx.banks_client.get_balance(ctx.payer.pubkey()).await.unwrap(), balance);
+}
+
+async fn sign_pay_fee_with_durable_nonce<const BPF: bool>() {
+    let mut ctx = FarmFixture::new().start::<BPF>().await;
+    let client = FarmClient::new(ctx.program_id, ctx.banks_client.clone());
+    let nonce_account = Keypair::new();
+    let instructions = system_instruction::create_nonce_account(
+        &ctx.payer.pubkey(),
+        &nonce_account.pubkey(),
+        &ctx.creator.pubkey(),
+        Rent::default().minimum_balance(nonce::State::size()),
+    );
+    client.send(&ctx.payer, &instructions, &[&nonce_account]).await.unwrap();
+    let nonce = client.get_nonce_data(&nonce_account.pubkey()).await.unwrap();
+    assert_eq!(nonce.authority, ctx.creator.pubkey());
+
+    // the creator and the payer sign separately, the way offline signers would
+    let pay = PayFarmFee::new(ctx.farm.pubkey(), ctx.creator.pubkey(), ctx.creator_token_account, ctx.fee_vault, FARM_FEE);
+    let mut message = Message::new_with_nonce(
+        vec![pay.instruction(&ctx.program_id)],
+        Some(&ctx.payer.pubkey()),
+        &nonce_account.pubkey(),
+        &ctx.creator.pubkey(),
+    );
+    message.recent_blockhash = nonce.blockhash();
+    let mut transaction = Transaction::new_unsigned(message);
+    transaction.partial_sign(&[&ctx.creator], nonce.blockhash());
+    transaction.partial_sign(&[&ctx.payer], nonce.blockhash());
+    assert!(transaction.is_signed());
+
+    // the banks server only simulates durable nonce transactions, a validator runs them
+    assert!(client.connection().simulate_transaction(&transaction).await.unwrap().is_some());
+    assert!(matches!(client.send_signed(&transaction).await, Err(FarmClientError::Unsupported(_))));
+    assert_eq!(ctx.get_farm().await.enabled, 0);
+}
+
+// every scenario runs natively, and against the BPF build as well when `common::test_bpf` is on
+
+#[tokio::test]
+async fn test_builders_match_instruction_helpers() {
+    builders_match_instruction_helpers::<false>().await;
+    if common::test_bpf() {
+        builders_match_instruction_helpers::<true>().await;
+    }
+}
+
+#[tokio::test]
+async fn test_get_farm() {
+    get_farm::<false>().await;
+    if common::test_bpf() {
+        get_farm::<true>().await;
+    }
+}
+
+#[tokio::test]
+async fn test_send_pay_fee() {
+    send_pay_fee::<false>().await;
+    if common::test_bpf() {
+        send_pay_fee::<true>().await;
+    }
+}
+
+#[tokio::test]
+async fn test_farm_metadata() {
+    farm_metadata::<false>().await;
+    if common::test_bpf() {
+        farm_metadata::<true>().await;
+    }
+}
+
+#[tokio::test]
+async fn test_farm_filters() {
+    farm_filters_match_farms::<false>().await;
+    if common::test_bpf() {
+        farm_filters_match_farms::<true>().await;
+    }
+}
+
+#[tokio::test]
+async fn test_get_farms_by_creator_needs_rpc() {
+    get_farms_by_creator_needs_rpc::<false>().await;
+    if common::test_bpf() {
+        get_farms_by_creator_needs_rpc::<true>().await;
+    }
+}
+
+#[tokio::test]
+async fn test_send_with_priority_fee() {
+    send_with_priority_fee::<false>().await;
+    if common::test_bpf() {
+        send_with_priority_fee::<true>().await;
+    }
+}
+
+#[tokio::test]
+async fn test_failed_simulation_is_not_sent() {
+    failed_simulation_is_not_sent::<false>().await;
+    if common::test_bpf() {
+        failed_simulation_is_not_sent::<true>().await;
+    }
+}
+
+#[tokio::test]
+async fn test_sign_pay_fee_with_durable_nonce() {
+    sign_pay_fee_with_durable_nonce::<false>().await;
+    if common::test_bpf() {
+        sign_pay_fee_with_durable_nonce::<true>().await;
+    }
+}
@@ -0,0 +1,394 @@
+use {
+    super::{mint_account, program_test, token_account, token_balance},
+    borsh::{BorshDeserialize, BorshSerialize},
+    ctf_solana_farm::{
+        constant::{FARM_DISCRIMINATOR, FARM_FEE, FARM_VERSION},
+        instruction::{
+            find_config_address, find_fee_schedule_address, ix_create_farm, ix_pay_create_fee,
+            ix_pay_create_fee_with_referrer, ix_set_fee_schedule, ix_set_referral_share,
+        },
+        state::{Farm, FarmAccount, FeeMint, FeeSchedule, FeeTier, ProgramConfig},
+    },
+    solana_program::{clock::Clock, instruction::Instruction, rent::Rent, system_instruction},
+    solana_program_test::*,
+    solana_sdk::{
+        account::Account,
+        native_token::LAMPORTS_PER_SOL,
+        pubkey::Pubkey,
+        signature::{Keypair, Signer},
+        transaction::Transaction,
+    },
+};
+
+/// tokens the creator starts with in each mint
+pub const CREATOR_BALANCE: u64 = 1_000_000;
+
+/// builds the accounts of a farm test: program config, fee schedule, a fee mint,
+/// a farm at its authority PDA with the fee vault, and a funded creator
+/// every account is a real one, so the SPL Token program accepts the transfers
+///
+/// the defaults give a created, disabled farm accepting FARM_FEE in `mint`,
+/// builder methods state what a test needs differently
+pub struct FarmFixture {
+    program_id: Pubkey,
+    admin: Keypair,
+    creator: Keypair,
+    farm: Keypair,
+    created: bool,
+    enabled: bool,
+    paused: bool,
+    duration: u64,
+    referral_bps: u16,
+    v0_config: bool,
+    fee_tiers: Vec<FeeTier>,
+    fee_amount: u64,
+    creator_balance: u64,
+}
+
+/// handles to everything a `FarmFixture` set up
+/// the creator also holds `other_mint`, which the fee schedule doesn't accept
+pub struct FarmContext {
+    context: ProgramTestContext,
+    pub banks_client: BanksClient,
+    pub payer: Keypair,
+    pub program_id: Pubkey,
+    pub admin: Keypair,
+    pub creator: Keypair,
+    pub farm: Keypair,
+    pub authority: Pubkey,
+    pub nonce: u8,
+    pub mint: Pubkey,
+    pub fee_vault: Pubkey,
+    pub creator_token_account: Pubkey,
+    pub referrer_token_account: Pubkey,
+    pub other_mint: Pubkey,
+    pub other_creator_token_account: Pubkey,
+}
+
+impl Default for FarmFixture {
+    fn default() -> Self {
+        FarmFixture {
+            program_id: Pubkey::new_unique(),
+            admin: Keypair::new(),
+            creator: Keypair::new(),
+            farm: Keypair::new(),
+            created: true,
+            enabled: false,
+            paused: false,
+            duration: 86_400,
+            referral_bps: 0,
+            v0_config: false,
+            fee_tiers: vec![],
+            fee_amount: FARM_FEE,
+            creator_balance: CREATOR_BALANCE,
+        }
+    }
+}
+
+impl FarmFixture {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn program_id(mut self, program_id: Pubkey) -> Self {
+        self.program_id = program_id;
+        self
+    }
+
+    /// leaves the farm account unallocated, for tests that create the farm themselves
+    pub fn uncreated(mut self) -> Self {
+        self.created = false;
+        self
+    }
+
+    /// the fee is paid and its window doesn't end
+    pub fn enabled(mut self) -> Self {
+        self.enabled = true;
+        self
+    }
+
+    pub fn paused(mut self) -> Self {
+        self.paused = true;
+        self
+    }
+
+    pub fn duration(mut self, duration: u64) -> Self {
+        self.duration = duration;
+        self
+    }
+
+    pub fn referral_bps(mut self, referral_bps: u16) -> Self {
+        self.referral_bps = referral_bps;
+        self
+    }
+
+    /// stores the program config in its ProgramConfig::V0_LEN layout, from before the referral share
+    pub fn v0_config(mut self) -> Self {
+        self.v0_config = true;
+        self
+    }
+
+    pub fn fee_tiers(mut self, fee_tiers: Vec<FeeTier>) -> Self {
+        self.fee_tiers = fee_tiers;
+        self
+    }
+
+    /// base fee of `mint` in the fee schedule
+    pub fn fee_amount(mut self, fee_amount: u64) -> Self {
+        self.fee_amount = fee_amount;
+        self
+    }
+
+    pub fn creator_balance(mut self, creator_balance: u64) -> Self {
+        self.creator_balance = creator_balance;
+        self
+    }
+
+    pub fn creator(&self) -> &Keypair {
+        &self.creator
+    }
+
+    pub fn farm(&self) -> &Keypair {
+        &self.farm
+    }
+
+    /// every account of the fixture, usable with ProgramTest or a test validator genesis
+    /// the program config and fee schedule are written directly, no admin transaction needed
+    pub fn accounts(&self, addresses: &FixtureAddresses) -> Vec<(Pubkey, Account)> {
+        let (config_address, config_bump) = find_config_address(&self.program_id);
+        let (fee_schedule_address, fee_schedule_bump) = find_fee_schedule_address(&self.program_id);
+
+        let mut config = ProgramConfig {
+            is_initialized: 1,
+            paused: self.paused as u8,
+            bump: config_bump,
+            admin: self.admin.pubkey(),
+            ..ProgramConfig::default()
+        };
+        config.set_referral_bps(self.referral_bps);
+        let mut config_data = config.try_to_vec().unwrap();
+        if self.v0_config {
+            config_data.truncate(ProgramConfig::V0_LEN);
+        }
+        let mut fee_schedule_data = FeeSchedule {
+            is_initialized: true,
+            bump: fee_schedule_bump,
+            tiers: self.fee_tiers.clone(),
+            mints: vec![FeeMint { mint: addresses.mint, amount: self.fee_amount }],
+        }
+        .try_to_vec()
+        .unwrap();
+        fee_schedule_data.resize(FeeSchedule::LEN, 0);
+
+        let mut accounts = vec![
+            (config_address, program_account(config_data, self.program_id)),
+            (fee_schedule_address, program_account(fee_schedule_data, self.program_id)),
+            (addresses.mint, mint_account(6)),
+            (addresses.other_mint, mint_account(6)),
+            (addresses.fee_vault, token_account(addresses.mint, addresses.authority, 0)),
+            (
+                addresses.creator_token_account,
+                token_account(addresses.mint, self.creator.pubkey(), self.creator_balance),
+            ),
+            (
+                addresses.referrer_token_account,
+                token_account(addresses.mint, Pubkey::new_unique(), 0),
+            ),
+            (
+                addresses.other_creator_token_account,
+                token_account(addresses.other_mint, self.creator.pubkey(), self.creator_balance),
+            ),
+            (self.admin.pubkey(), system_account(LAMPORTS_PER_SOL)),
+            (self.creator.pubkey(), system_account(LAMPORTS_PER_SOL)),
+        ];
+        if self.created {
+            let farm = Farm {
+                discriminator: FARM_DISCRIMINATOR,
+                version: FARM_VERSION,
+                enabled: self.enabled as u8,
+                nonce: addresses.nonce,
+                token_program_id: spl_token::id(),
+                creator: self.creator.pubkey(),
+                fee_vault: addresses.fee_vault,
+                pending_creator: Pubkey::default(),
+                duration: self.duration,
+                operator: Pubkey::default(),
+                paused: 0,
+                ends_at: if self.enabled { i64::MAX } else { 0 },
+            };
+            accounts.push((self.farm.pubkey(), program_account(farm.try_to_vec().unwrap(), self.program_id)));
+        }
+        accounts
+    }
+
+    /// addresses of the fixture accounts that aren't keypairs
+    pub fn addresses(&self) -> FixtureAddresses {
+        let (authority, nonce) = Pubkey::find_program_address(&[&self.farm.pubkey().to_bytes()[..32]], &self.program_id);
+        FixtureAddresses {
+            authority,
+            nonce,
+            mint: Pubkey::new_unique(),
+            fee_vault: Pubkey::new_unique(),
+            creator_token_account: Pubkey::new_unique(),
+            referrer_token_account: Pubkey::new_unique(),
+            other_mint: Pubkey::new_unique(),
+            other_creator_token_account: Pubkey::new_unique(),
+        }
+    }
+
+    /// starts the program with the fixture accounts, natively or the BPF build when `BPF` is set
+    pub async fn start<const BPF: bool>(self) -> FarmContext {
+        let addresses = self.addresses();
+        let mut program_test = program_test::<BPF>(self.program_id);
+        for (address, account) in self.accounts(&addresses) {
+            program_test.add_account(address, account);
+        }
+        let context = program_test.start_with_context().await;
+        FarmContext {
+            banks_client: context.banks_client.clone(),
+            payer: context.payer.insecure_clone(),
+            context,
+            program_id: self.program_id,
+            admin: self.admin,
+            creator: self.creator,
+            farm: self.farm,
+            authority: addresses.authority,
+            nonce: addresses.nonce,
+            mint: addresses.mint,
+            fee_vault: addresses.fee_vault,
+            creator_token_account: addresses.creator_token_account,
+            referrer_token_account: addresses.referrer_token_account,
+            other_mint: addresses.other_mint,
+            other_creator_token_account: addresses.other_creator_token_account,
+        }
+    }
+}
+
+/// addresses picked by `FarmFixture::addresses`
+pub struct FixtureAddresses {
+    pub authority: Pubkey,
+    pub nonce: u8,
+    pub mint: Pubkey,
+    pub fee_vault: Pubkey,
+    pub creator_token_account: Pubkey,
+    pub referrer_token_account: Pubkey,
+    pub other_mint: Pubkey,
+    pub other_creator_token_account: Pubkey,
+}
+
+fn program_account(data: Vec<u8>, owner: Pubkey) -> Account {
+    Account {
+        lamports: Rent::default().minimum_balance(data.len()),
+        data,
+        owner,
+        ..Account::default()
+    }
+}
+
+fn system_account(lamports: u64) -> Account {
+    Account {
+        lamports,
+        ..Account::default()
+    }
+}
+
+impl FarmContext {
+    /// signs `instructions` with the payer and `signers` and processes them in one transaction,
+    /// each time with a fresh blockhash so a retried instruction isn't taken for the earlier transaction
+    pub async fn send(&mut self, instructions: &[Instruction], signers: &[&Keypair]) -> Result<(), BanksClientError> {
+        let recent_blockhash = self.context.get_new_latest_blockhash().await.unwrap();
+        let mut transaction = Transaction::new_with_payer(instructions, Some(&self.payer.pubkey()));
+        transaction.sign(&[&[&self.payer], signers].concat(), recent_blockhash);
+        self.banks_client.process_transaction(transaction).await
+    }
+
+    /// unix timestamp of the clock sysvar
+    pub async fn now(&mut self) -> i64 {
+        self.banks_client.get_sysvar::<Clock>().await.unwrap().unix_timestamp
+    }
+
+    /// moves the clock to `unix_timestamp`
+    pub async fn warp_to(&mut self, unix_timestamp: i64) {
+        let clock = Clock { unix_timestamp, ..self.banks_client.get_sysvar::<Clock>().await.unwrap() };
+        self.context.set_sysvar(&clock);
+    }
+
+    /// PayFarmFee paid by the creator from `creator_token_account`
+    pub fn pay_fee(&self, creator_token_account: &Pubkey, referrer: Option<&Pubkey>, amount: u64) -> Instruction {
+        match referrer {
+            Some(referrer) => ix_pay_create_fee_with_referrer(
+                &self.farm.pubkey(),
+                &self.authority,
+                &self.creator.pubkey(),
+                creator_token_account,
+                &self.fee_vault,
+                &spl_token::id(),
+                &self.program_id,
+                referrer,
+                amount,
+            ),
+            None => ix_pay_create_fee(
+                &self.farm.pubkey(),
+                &self.authority,
+                &self.creator.pubkey(),
+                creator_token_account,
+                &self.fee_vault,
+                &spl_token::id(),
+                &self.program_id,
+                amount,
+            ),
+        }
+    }
+
+    /// pays `amount` from the creator token account
+    pub async fn pay(&mut self, referrer: Option<&Pubkey>, amount: u64) -> Result<(), BanksClientError> {
+        let instruction = self.pay_fee(&self.creator_token_account, referrer, amount);
+        let creator = self.creator.insecure_clone();
+        self.send(&[instruction], &[&creator]).await
+    }
+
+    /// allocates the farm account for the program, to go before `create_farm`
+    pub fn allocate_farm(&self) -> Instruction {
+        system_instruction::create_account(
+            &self.payer.pubkey(),
+            &self.farm.pubkey(),
+            Rent::default().minimum_balance(FarmAccount::LEN),
+            FarmAccount::LEN as u64,
+            &self.program_id,
+        )
+    }
+
+    pub fn create_farm(&self, creator: &Pubkey, fee_vault: &Pubkey, duration: u64) -> Instruction {
+        ix_create_farm(
+            &self.farm.pubkey(),
+            &self.authority,
+            creator,
+            fee_vault,
+            &spl_token::id(),
+            &self.program_id,
+            self.nonce,
+            duration,
+        )
+    }
+
+    pub async fn set_referral_share(&mut self, referral_bps: u16) -> Result<(), BanksClientError> {
+        let instruction = ix_set_referral_share(&self.admin.pubkey(), &self.program_id, referral_bps);
+        let admin = self.admin.insecure_clone();
+        self.send(&[instruction], &[&admin]).await
+    }
+
+    pub async fn set_fee_schedule(&mut self, tiers: Vec<FeeTier>, mints: Vec<FeeMint>) -> Result<(), BanksClientError> {
+        let instruction = ix_set_fee_schedule(&self.admin.pubkey(), &self.program_id, tiers, mints);
+        let admin = self.admin.insecure_clone();
+        self.send(&[instruction], &[&admin]).await
+    }
+
+    pub async fn get_farm(&mut self) -> Farm {
+        let account = self.banks_client.get_account(self.farm.pubkey()).await.unwrap().unwrap();
+        Farm::try_from_slice(&account.data).unwrap()
+    }
+
+    pub async fn token_balance(&mut self, address: Pubkey) -> u64 {
+        token_balance(&mut self.banks_client, address).await
+    }
+}
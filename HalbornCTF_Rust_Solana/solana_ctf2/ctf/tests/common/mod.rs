@@ -0,0 +1,174 @@
+// helpers shared by the ProgramTest suites, not every suite uses all of them
+// `cargo build-sbf && cargo test --features test-bpf` runs the suites against the BPF build as well as natively
+#![allow(dead_code)]
+
+mod fixture;
+
+#[allow(unused_imports)]
+pub use fixture::{FarmContext, FarmFixture, CREATOR_BALANCE};
+
+use {
+    ctf_solana_farm::{error::FarmError, instruction::ix_initialize_config, process_instruction},
+    farm_core::conformance::{program_data_account, program_data_address, upgrade_authority},
+    solana_program::{
+        instruction::{Instruction, InstructionError},
+        program_option::COption,
+        program_pack::Pack,
+        rent::Rent,
+    },
+    solana_program_test::*,
+    solana_sdk::{
+        account::Account,
+        bpf_loader,
+        pubkey::Pubkey,
+        signature::{Keypair, Signer},
+        transaction::{Transaction, TransactionError},
+    },
+    std::{
+        env, fs,
+        path::{Path, PathBuf},
+    },
+};
+
+/// whether the suites run the `cargo build-sbf` artifact instead of the native processor,
+/// switched on by the test-bpf feature or by `cargo test-sbf`, which sets SBF_OUT_DIR
+pub fn test_bpf() -> bool {
+    cfg!(feature = "test-bpf") || env::var_os("SBF_OUT_DIR").is_some() || env::var_os("BPF_OUT_DIR").is_some()
+}
+
+/// program test running the farm program natively, or the BPF build when `BPF` is set
+/// the suites run each scenario with `BPF` off, then on as well when `test_bpf` is on
+pub fn program_test<const BPF: bool>(program_id: Pubkey) -> ProgramTest {
+    if BPF {
+        bpf_program_test(program_id)
+    } else {
+        native_program_test(program_id)
+    }
+}
+
+/// program test running the farm processor natively
+pub fn native_program_test(program_id: Pubkey) -> ProgramTest {
+    let mut program_test = native_programs(program_id);
+    program_test.add_program("ctf_solana_farm", program_id, processor!(process_instruction));
+    program_test
+}
+
+/// program test running the farm program built by `cargo build-sbf`, other programs of the
+/// test stay native
+pub fn bpf_program_test(program_id: Pubkey) -> ProgramTest {
+    let mut program_test = native_programs(program_id);
+    program_test.add_account(program_id, bpf_program());
+    program_test
+}
+
+/// program test adding the programs of `add_program` natively, even under `cargo test-sbf`,
+/// with a ProgramData account for `program_id` naming `upgrade_authority` as its authority
+fn native_programs(program_id: Pubkey) -> ProgramTest {
+    let mut program_test = ProgramTest::default();
+    program_test.prefer_bpf(false);
+    program_test.add_account(program_data_address(&program_id), program_data_account(&upgrade_authority().pubkey()));
+    program_test
+}
+
+/// the shared object in SBF_OUT_DIR, BPF_OUT_DIR or target/deploy, deployed with the BPF loader
+fn bpf_program() -> Account {
+    let out_dir = env::var_os("SBF_OUT_DIR")
+        .or_else(|| env::var_os("BPF_OUT_DIR"))
+        .map(PathBuf::from)
+        .unwrap_or_else(|| Path::new(env!("CARGO_MANIFEST_DIR")).join("target/deploy"));
+    let path = out_dir.join("ctf_solana_farm.so");
+    let data = fs::read(&path)
+        .unwrap_or_else(|err| panic!("{}: {}, build the program with `cargo build-sbf` first", path.display(), err));
+    Account {
+        lamports: Rent::default().minimum_balance(data.len()),
+        data,
+        owner: bpf_loader::id(),
+        executable: true,
+        ..Account::default()
+    }
+}
+
+/// an initialized spl mint
+pub fn mint_account(decimals: u8) -> Account {
+    let mut data = vec![0; spl_token::state::Mint::LEN];
+    spl_token::state::Mint {
+        mint_authority: COption::None,
+        supply: u64::MAX,
+        decimals,
+        is_initialized: true,
+        freeze_authority: COption::None,
+    }
+    .pack_into_slice(&mut data);
+    token_program_account(data)
+}
+
+/// an initialized spl token account holding `amount` of `mint`
+pub fn token_account(mint: Pubkey, owner: Pubkey, amount: u64) -> Account {
+    let mut data = vec![0; spl_token::state::Account::LEN];
+    spl_token::state::Account {
+        mint,
+        owner,
+        amount,
+        state: spl_token::state::AccountState::Initialized,
+        ..spl_token::state::Account::default()
+    }
+    .pack_into_slice(&mut data);
+    token_program_account(data)
+}
+
+fn token_program_account(data: Vec<u8>) -> Account {
+    Account {
+        lamports: 1_000_000_000,
+        data,
+        owner: spl_token::id(),
+        ..Account::default()
+    }
+}
+
+/// reads the balance of a token account
+pub async fn token_balance(banks_client: &mut BanksClient, address: Pubkey) -> u64 {
+    let account = banks_client.get_account(address).await.unwrap().unwrap();
+    spl_token::state::Account::unpack(&account.data).unwrap().amount
+}
+
+/// creates the program config with `payer` as admin
+pub async fn initialize_config(banks_client: &mut BanksClient, payer: &Keypair, program_id: &Pubkey) {
+    let upgrade_authority = upgrade_authority();
+    let instruction = ix_initialize_config(&payer.pubkey(), &upgrade_authority.pubkey(), program_id);
+    send(banks_client, payer, instruction, &[&upgrade_authority])
+        .await
+        .unwrap();
+}
+
+/// signs `instruction` with the payer and `signers` and processes it
+pub async fn send(
+    banks_client: &mut BanksClient,
+    payer: &Keypair,
+    instruction: Instruction,
+    signers: &[&Keypair],
+) -> Result<(), BanksClientError> {
+    send_all(banks_client, payer, &[instruction], signers).await
+}
+
+/// same as `send` with several instructions in one transaction
+pub async fn send_all(
+    banks_client: &mut BanksClient,
+    payer: &Keypair,
+    instructions: &[Instruction],
+    signers: &[&Keypair],
+) -> Result<(), BanksClientError> {
+    let recent_blockhash = banks_client.get_latest_blockhash().await.unwrap();
+    let mut transaction = Transaction::new_with_payer(instructions, Some(&payer.pubkey()));
+    transaction.sign(&[&[payer], signers].concat(), recent_blockhash);
+    banks_client.process_transaction(transaction).await
+}
+
+/// decodes the FarmError returned by the failing instruction of a transaction
+pub fn farm_error(result: Result<(), BanksClientError>) -> Option<FarmError> {
+    match result.unwrap_err().unwrap() {
+        TransactionError::InstructionError(_, InstructionError::Custom(code)) => {
+            num_traits::FromPrimitive::from_u32(code)
+        }
+        _ => None,
+    }
+}
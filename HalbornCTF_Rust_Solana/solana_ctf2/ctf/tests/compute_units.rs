@@ -0,0 +1,84 @@
+#![cfg(all(feature = "test-bpf", feature = "cu-bench"))]
+// build the benchmark program first:
+//   cargo build-sbf --features cu-bench
+//   cargo test-sbf --features test-bpf,cu-bench --test compute_units -- --nocapture
+
+use {
+    borsh::BorshSerialize,
+    ctf_solana_farm::{
+        bench::BenchInstruction,
+        constant::{FARM_DISCRIMINATOR, FARM_VERSION},
+        state::Farm,
+    },
+    solana_program::instruction::{AccountMeta, Instruction},
+    solana_program_test::*,
+    solana_sdk::{
+        account::Account,
+        pubkey::Pubkey,
+        signature::{Keypair, Signer},
+        transaction::Transaction,
+    },
+};
+
+async fn units_consumed(
+    banks_client: &mut BanksClient,
+    payer: &Keypair,
+    program_id: &Pubkey,
+    farm_id: &Pubkey,
+    bench: BenchInstruction,
+) -> u64 {
+    let instruction = Instruction {
+        program_id: *program_id,
+        accounts: vec![AccountMeta::new(*farm_id, false)],
+        data: bench.try_to_vec().unwrap(),
+    };
+    let recent_blockhash = banks_client.get_latest_blockhash().await.unwrap();
+    let transaction = Transaction::new_signed_with_payer(
+        &[instruction],
+        Some(&payer.pubkey()),
+        &[payer],
+        recent_blockhash,
+    );
+    let simulation = banks_client.simulate_transaction(transaction).await.unwrap();
+    assert_eq!(simulation.result, Some(Ok(())));
+    simulation.simulation_details.unwrap().units_consumed
+}
+
+#[tokio::test]
+async fn test_farm_access_compute_units() {
+    let program_id = Pubkey::new_unique();
+    let farm_id = Pubkey::new_unique();
+    let farm_data = Farm {
+        discriminator: FARM_DISCRIMINATOR,
+        version: FARM_VERSION,
+        enabled: 0,
+        nonce: 42,
+        token_program_id: spl_token::id(),
+        creator: Pubkey::new_unique(),
+        fee_vault: Pubkey::new_unique(),
+        pending_creator: Pubkey::default(),
+        duration: 86_400,
+        operator: Pubkey::default(),
+        paused: 0,
+        ends_at: 0,
+    };
+
+    // no processor, ProgramTest loads target/deploy/ctf_solana_farm.so
+    let mut program_test = ProgramTest::new("ctf_solana_farm", program_id, None);
+    program_test.add_account(
+        farm_id,
+        Account {
+            lamports: 1_000_000_000,
+            data: farm_data.try_to_vec().unwrap(),
+            owner: program_id,
+            ..Account::default()
+        },
+    );
+    let (mut banks_client, payer, _) = program_test.start().await;
+
+    let borsh_units = units_consumed(&mut banks_client, &payer, &program_id, &farm_id, BenchInstruction::Borsh).await;
+    let zero_copy_units = units_consumed(&mut banks_client, &payer, &program_id, &farm_id, BenchInstruction::ZeroCopy).await;
+
+    println!("farm account update, borsh: {} CU, zero-copy: {} CU", borsh_units, zero_copy_units);
+    assert!(zero_copy_units < borsh_units);
+}
@@ -0,0 +1,139 @@
+// the farm-core conformance scenarios, the first generation program runs the same ones
+// every scenario runs natively, and against the BPF build as well when `common::test_bpf` is on
+mod common;
+
+use {
+    borsh::BorshSerialize,
+    ctf_solana_farm::{
+        constant::{FARM_DISCRIMINATOR, FARM_FEE, FARM_VERSION},
+        error::FarmError,
+        instruction::{ix_initialize_config, ix_pay_create_fee, ix_set_fee_schedule},
+        process_instruction,
+        state::{Farm, FarmAccount, FeeMint},
+    },
+    farm_core::conformance::{self, FarmProgram, PayFee},
+    solana_program::{account_info::AccountInfo, entrypoint::ProgramResult, instruction::Instruction, pubkey::Pubkey},
+    solana_program_test::*,
+};
+
+/// the program as the scenarios see it, fees are paid in the one mint of a flat fee schedule
+struct ConformingFarm<const BPF: bool>;
+
+type NativeFarm = ConformingFarm<false>;
+type BpfFarm = ConformingFarm<true>;
+
+impl<const BPF: bool> FarmProgram for ConformingFarm<BPF> {
+    const FEE: u64 = FARM_FEE;
+
+    fn process_instruction(program_id: &Pubkey, accounts: &[AccountInfo], instruction_data: &[u8]) -> ProgramResult {
+        process_instruction(program_id, accounts, instruction_data)
+    }
+
+    fn unpaid_farm(creator: &Pubkey, nonce: u8, fee_vault: &Pubkey) -> Vec<u8> {
+        let farm = Farm {
+            discriminator: FARM_DISCRIMINATOR,
+            version: FARM_VERSION,
+            enabled: 0,
+            nonce,
+            token_program_id: spl_token::id(),
+            creator: *creator,
+            fee_vault: *fee_vault,
+            pending_creator: Pubkey::default(),
+            duration: 86_400,
+            operator: Pubkey::default(),
+            paused: 0,
+            ends_at: 0,
+        };
+        farm.try_to_vec().unwrap()
+    }
+
+    fn is_enabled(data: &[u8]) -> bool {
+        bytemuck::from_bytes::<FarmAccount>(&data[..FarmAccount::LEN]).enabled == 1
+    }
+
+    fn setup(program_id: &Pubkey, admin: &Pubkey, upgrade_authority: &Pubkey, mint: &Pubkey) -> Vec<Instruction> {
+        let mints = vec![FeeMint { mint: *mint, amount: FARM_FEE }];
+        vec![ix_initialize_config(admin, upgrade_authority, program_id), ix_set_fee_schedule(admin, program_id, vec![], mints)]
+    }
+
+    fn other_creator_error() -> FarmError {
+        FarmError::Unauthorized
+    }
+
+    fn pay_fee(program_id: &Pubkey, accounts: &PayFee) -> Instruction {
+        ix_pay_create_fee(
+            &accounts.farm_id,
+            &accounts.authority,
+            &accounts.creator,
+            &accounts.creator_token_account,
+            &accounts.fee_vault,
+            &accounts.token_program_id,
+            program_id,
+            accounts.amount,
+        )
+    }
+
+    fn program_test(program_id: Pubkey) -> ProgramTest {
+        if BPF {
+            common::bpf_program_test(program_id)
+        } else {
+            common::native_program_test(program_id)
+        }
+    }
+}
+
+#[tokio::test]
+async fn test_pays_fee() {
+    conformance::pays_fee::<NativeFarm>().await;
+    if common::test_bpf() {
+        conformance::pays_fee::<BpfFarm>().await;
+    }
+}
+
+#[tokio::test]
+async fn test_rejects_second_payment() {
+    conformance::rejects_second_payment::<NativeFarm>().await;
+    if common::test_bpf() {
+        conformance::rejects_second_payment::<BpfFarm>().await;
+    }
+}
+
+#[tokio::test]
+async fn test_rejects_missing_creator_signature() {
+    conformance::rejects_missing_creator_signature::<NativeFarm>().await;
+    if common::test_bpf() {
+        conformance::rejects_missing_creator_signature::<BpfFarm>().await;
+    }
+}
+
+#[tokio::test]
+async fn test_rejects_other_creator() {
+    conformance::rejects_other_creator::<NativeFarm>().await;
+    if common::test_bpf() {
+        conformance::rejects_other_creator::<BpfFarm>().await;
+    }
+}
+
+#[tokio::test]
+async fn test_rejects_wrong_authority() {
+    conformance::rejects_wrong_authority::<NativeFarm>().await;
+    if common::test_bpf() {
+        conformance::rejects_wrong_authority::<BpfFarm>().await;
+    }
+}
+
+#[tokio::test]
+async fn test_rejects_foreign_token_program() {
+    conformance::rejects_foreign_token_program::<NativeFarm>().await;
+    if common::test_bpf() {
+        conformance::rejects_foreign_token_program::<BpfFarm>().await;
+    }
+}
+
+#[tokio::test]
+async fn test_rejects_wrong_fee() {
+    conformance::rejects_wrong_fee::<NativeFarm>().await;
+    if common::test_bpf() {
+        conformance::rejects_wrong_fee::<BpfFarm>().await;
+    }
+}
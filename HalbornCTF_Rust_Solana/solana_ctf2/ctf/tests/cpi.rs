@@ -0,0 +1,242 @@
+// a mock launchpad program creating and paying farms through the cpi wrappers
+// run with `cargo test --features no-entrypoint`
+#![cfg(feature = "no-entrypoint")]
+
+mod common;
+
+use {
+    borsh::{BorshDeserialize, BorshSerialize},
+    common::{farm_error, program_test, send, token_account, token_balance, FarmFixture, CREATOR_BALANCE},
+    ctf_solana_farm::{
+        constant::FARM_FEE,
+        cpi::{self, CreateFarm, PayFarmFee},
+        error::FarmError,
+        instruction::{find_config_address, find_fee_schedule_address},
+        state::{Farm, FarmAccount},
+    },
+    solana_program::{
+        account_info::{next_account_info, AccountInfo},
+        entrypoint::ProgramResult,
+        instruction::{AccountMeta, Instruction},
+        program_error::ProgramError,
+        pubkey::Pubkey,
+        rent::Rent,
+    },
+    solana_program_test::*,
+    solana_sdk::{
+        account::Account,
+        signature::{Keypair, Signer},
+    },
+};
+
+const LAUNCHPAD_SEED: &[u8] = b"launchpad";
+
+#[derive(BorshSerialize, BorshDeserialize)]
+enum LaunchpadInstruction {
+    /// creates and pays a farm owned by the launchpad PDA
+    Launch { nonce: u8, duration: u64, amount: u64 },
+    /// same without signer seeds, the creator has to sign the transaction
+    /// (a PDA creator here is a privilege escalation, which panics natively instead of failing)
+    LaunchAsSigner { nonce: u8, duration: u64, amount: u64 },
+}
+
+/// accounts: farm program, farm, authority, creator, config, fee schedule, fee vault,
+/// creator token account, token program
+fn process_launchpad(program_id: &Pubkey, accounts: &[AccountInfo], instruction_data: &[u8]) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let farm_program = next_account_info(account_info_iter)?;
+    let farm = next_account_info(account_info_iter)?;
+    let authority = next_account_info(account_info_iter)?;
+    let creator = next_account_info(account_info_iter)?;
+    let config = next_account_info(account_info_iter)?;
+    let fee_schedule = next_account_info(account_info_iter)?;
+    let fee_vault = next_account_info(account_info_iter)?;
+    let creator_token_account = next_account_info(account_info_iter)?;
+    let token_program = next_account_info(account_info_iter)?;
+
+    let create_accounts = CreateFarm {
+        farm: farm.clone(),
+        authority: authority.clone(),
+        creator: creator.clone(),
+        config: config.clone(),
+        fee_vault: fee_vault.clone(),
+        token_program: token_program.clone(),
+    };
+    let pay_accounts = PayFarmFee {
+        farm: farm.clone(),
+        authority: authority.clone(),
+        creator: creator.clone(),
+        creator_token_account: creator_token_account.clone(),
+        fee_vault: fee_vault.clone(),
+        token_program: token_program.clone(),
+        config: config.clone(),
+        fee_schedule: fee_schedule.clone(),
+        referrer_token_account: None,
+    };
+    match LaunchpadInstruction::try_from_slice(instruction_data).map_err(|_| ProgramError::InvalidInstructionData)? {
+        LaunchpadInstruction::Launch { nonce, duration, amount } => {
+            let (_, bump) = Pubkey::find_program_address(&[LAUNCHPAD_SEED], program_id);
+            let signer_seeds: &[&[&[u8]]] = &[&[LAUNCHPAD_SEED, &[bump]]];
+            cpi::create_farm_signed(farm_program, &create_accounts, nonce, duration, signer_seeds)?;
+            cpi::pay_farm_fee_signed(farm_program, &pay_accounts, amount, signer_seeds)
+        }
+        LaunchpadInstruction::LaunchAsSigner { nonce, duration, amount } => {
+            cpi::create_farm(farm_program, &create_accounts, nonce, duration)?;
+            cpi::pay_farm_fee(farm_program, &pay_accounts, amount)
+        }
+    }
+}
+
+/// the farm fixture next to the launchpad, with an unpaid farm account allocated for the farm program
+struct Launchpad {
+    banks_client: BanksClient,
+    payer: Keypair,
+    fixture: FarmFixture,
+    program_id: Pubkey,
+    farm_program_id: Pubkey,
+    pda: Pubkey,
+    authority: Pubkey,
+    nonce: u8,
+    fee_vault: Pubkey,
+    /// held by the launchpad PDA
+    pda_token_account: Pubkey,
+    /// held by the fixture creator
+    creator_token_account: Pubkey,
+}
+
+impl Launchpad {
+    async fn start<const BPF: bool>() -> Self {
+        let farm_program_id = Pubkey::new_unique();
+        let program_id = Pubkey::new_unique();
+        let (pda, _) = Pubkey::find_program_address(&[LAUNCHPAD_SEED], &program_id);
+        let fixture = FarmFixture::new().program_id(farm_program_id).uncreated();
+        let addresses = fixture.addresses();
+        let pda_token_account = Pubkey::new_unique();
+
+        let mut program_test = program_test::<BPF>(farm_program_id);
+        program_test.add_program("mock_launchpad", program_id, processor!(process_launchpad));
+        for (address, account) in fixture.accounts(&addresses) {
+            program_test.add_account(address, account);
+        }
+        program_test.add_account(
+            fixture.farm().pubkey(),
+            Account {
+                lamports: Rent::default().minimum_balance(FarmAccount::LEN),
+                data: vec![0; FarmAccount::LEN],
+                owner: farm_program_id,
+                ..Account::default()
+            },
+        );
+        program_test.add_account(pda_token_account, token_account(addresses.mint, pda, CREATOR_BALANCE));
+        let (banks_client, payer, _) = program_test.start().await;
+        Launchpad {
+            banks_client,
+            payer,
+            fixture,
+            program_id,
+            farm_program_id,
+            pda,
+            authority: addresses.authority,
+            nonce: addresses.nonce,
+            fee_vault: addresses.fee_vault,
+            pda_token_account,
+            creator_token_account: addresses.creator_token_account,
+        }
+    }
+
+    fn instruction(&self, creator: &Pubkey, creator_token_account: &Pubkey, data: LaunchpadInstruction) -> Instruction {
+        Instruction {
+            program_id: self.program_id,
+            accounts: vec![
+                AccountMeta::new_readonly(self.farm_program_id, false),
+                AccountMeta::new(self.fixture.farm().pubkey(), false),
+                AccountMeta::new_readonly(self.authority, false),
+                AccountMeta::new(*creator, *creator != self.pda),
+                AccountMeta::new_readonly(find_config_address(&self.farm_program_id).0, false),
+                AccountMeta::new_readonly(find_fee_schedule_address(&self.farm_program_id).0, false),
+                AccountMeta::new(self.fee_vault, false),
+                AccountMeta::new(*creator_token_account, false),
+                AccountMeta::new_readonly(spl_token::id(), false),
+            ],
+            data: data.try_to_vec().unwrap(),
+        }
+    }
+
+    /// launches a farm created by the launchpad PDA
+    fn launch(&self, amount: u64) -> Instruction {
+        let data = LaunchpadInstruction::Launch { nonce: self.nonce, duration: 86_400, amount };
+        self.instruction(&self.pda, &self.pda_token_account, data)
+    }
+
+    async fn get_farm(&mut self) -> Farm {
+        let account = self.banks_client.get_account(self.fixture.farm().pubkey()).await.unwrap().unwrap();
+        Farm::try_from_slice(&account.data).unwrap()
+    }
+}
+
+async fn cpi_launch_with_pda_creator<const BPF: bool>() {
+    let mut launchpad = Launchpad::start::<BPF>().await;
+
+    let instruction = launchpad.launch(FARM_FEE);
+    send(&mut launchpad.banks_client, &launchpad.payer, instruction, &[]).await.unwrap();
+    let farm = launchpad.get_farm().await;
+    assert_eq!(farm.creator, launchpad.pda);
+    assert_eq!(farm.enabled, 1);
+    assert_eq!(token_balance(&mut launchpad.banks_client, launchpad.fee_vault).await, FARM_FEE);
+    assert_eq!(
+        token_balance(&mut launchpad.banks_client, launchpad.pda_token_account).await,
+        CREATOR_BALANCE - FARM_FEE
+    );
+}
+
+async fn cpi_launch_with_signing_creator<const BPF: bool>() {
+    let mut launchpad = Launchpad::start::<BPF>().await;
+    let creator = launchpad.fixture.creator().pubkey();
+
+    let data = LaunchpadInstruction::LaunchAsSigner { nonce: launchpad.nonce, duration: 86_400, amount: FARM_FEE };
+    let instruction = launchpad.instruction(&creator, &launchpad.creator_token_account, data);
+    send(&mut launchpad.banks_client, &launchpad.payer, instruction, &[launchpad.fixture.creator()])
+        .await
+        .unwrap();
+    let farm = launchpad.get_farm().await;
+    assert_eq!(farm.creator, creator);
+    assert_eq!(farm.enabled, 1);
+    assert_eq!(token_balance(&mut launchpad.banks_client, launchpad.fee_vault).await, FARM_FEE);
+}
+
+async fn cpi_wrong_fee_reverts_create<const BPF: bool>() {
+    let mut launchpad = Launchpad::start::<BPF>().await;
+
+    let instruction = launchpad.launch(FARM_FEE - 1);
+    let result = send(&mut launchpad.banks_client, &launchpad.payer, instruction, &[]).await;
+    assert_eq!(farm_error(result), Some(FarmError::InvalidFarmFee));
+    // the farm created in the same instruction is rolled back
+    assert_eq!(launchpad.get_farm().await.creator, Pubkey::default());
+    assert_eq!(token_balance(&mut launchpad.banks_client, launchpad.pda_token_account).await, CREATOR_BALANCE);
+}
+
+// every scenario runs natively, and against the BPF build as well when `common::test_bpf` is on
+
+#[tokio::test]
+async fn test_cpi_launch_with_pda_creator() {
+    cpi_launch_with_pda_creator::<false>().await;
+    if common::test_bpf() {
+        cpi_launch_with_pda_creator::<true>().await;
+    }
+}
+
+#[tokio::test]
+async fn test_cpi_launch_with_signing_creator() {
+    cpi_launch_with_signing_creator::<false>().await;
+    if common::test_bpf() {
+        cpi_launch_with_signing_creator::<true>().await;
+    }
+}
+
+#[tokio::test]
+async fn test_cpi_wrong_fee_reverts_create() {
+    cpi_wrong_fee_reverts_create::<false>().await;
+    if common::test_bpf() {
+        cpi_wrong_fee_reverts_create::<true>().await;
+    }
+}
@@ -0,0 +1,115 @@
+mod common;
+
+use {
+    common::{farm_error, FarmFixture},
+    ctf_solana_farm::{
+        constant::{FARM_DISCRIMINATOR, FARM_VERSION},
+        error::FarmError,
+        state::Farm,
+    },
+    solana_program_test::*,
+    solana_sdk::{
+        pubkey::Pubkey,
+        signature::{Keypair, Signer},
+    },
+};
+
+async fn create_farm<const BPF: bool>() {
+    let mut ctx = FarmFixture::new().uncreated().start::<BPF>().await;
+    let creator = Keypair::new();
+
+    let instructions = [ctx.allocate_farm(), ctx.create_farm(&creator.pubkey(), &ctx.fee_vault, 86_400)];
+    let farm = Keypair::from_bytes(&ctx.farm.to_bytes()).unwrap();
+    ctx.send(&instructions, &[&farm, &creator]).await.unwrap();
+    assert_eq!(
+        ctx.get_farm().await,
+        Farm {
+            discriminator: FARM_DISCRIMINATOR,
+            version: FARM_VERSION,
+            enabled: 0,
+            nonce: ctx.nonce,
+            token_program_id: spl_token::id(),
+            creator: creator.pubkey(),
+            fee_vault: ctx.fee_vault,
+            pending_creator: Pubkey::default(),
+            duration: 86_400,
+            operator: Pubkey::default(),
+            paused: 0,
+            ends_at: 0,
+        }
+    );
+
+    // a created farm can't be taken over by creating it again
+    let intruder = Keypair::new();
+    let instructions = [ctx.create_farm(&intruder.pubkey(), &ctx.fee_vault, 0)];
+    let result = ctx.send(&instructions, &[&intruder]).await;
+    assert_eq!(farm_error(result), Some(FarmError::AlreadyInUse));
+    assert_eq!(ctx.get_farm().await.creator, creator.pubkey());
+}
+
+async fn create_farm_requires_authority_fee_vault<const BPF: bool>() {
+    let mut ctx = FarmFixture::new().uncreated().start::<BPF>().await;
+    let creator = Keypair::new();
+
+    // the referrer token account belongs to someone else
+    let instructions = [ctx.allocate_farm(), ctx.create_farm(&creator.pubkey(), &ctx.referrer_token_account, 86_400)];
+    let farm = Keypair::from_bytes(&ctx.farm.to_bytes()).unwrap();
+    let result = ctx.send(&instructions, &[&farm, &creator]).await;
+    assert_eq!(farm_error(result), Some(FarmError::InvalidFeeAccount));
+}
+
+async fn create_farm_checks_authority_nonce<const BPF: bool>() {
+    let mut ctx = FarmFixture::new().uncreated().start::<BPF>().await;
+    let creator = Keypair::new();
+    ctx.authority = Pubkey::new_unique();
+
+    let instructions = [ctx.allocate_farm(), ctx.create_farm(&creator.pubkey(), &ctx.fee_vault, 86_400)];
+    let farm = Keypair::from_bytes(&ctx.farm.to_bytes()).unwrap();
+    let result = ctx.send(&instructions, &[&farm, &creator]).await;
+    assert_eq!(farm_error(result), Some(FarmError::InvalidProgramAddress));
+}
+
+async fn create_farm_requires_duration<const BPF: bool>() {
+    let mut ctx = FarmFixture::new().uncreated().start::<BPF>().await;
+    let creator = Keypair::new();
+
+    // the fee would pay for a window that is over before it starts
+    let instructions = [ctx.allocate_farm(), ctx.create_farm(&creator.pubkey(), &ctx.fee_vault, 0)];
+    let farm = Keypair::from_bytes(&ctx.farm.to_bytes()).unwrap();
+    let result = ctx.send(&instructions, &[&farm, &creator]).await;
+    assert_eq!(farm_error(result), Some(FarmError::InvalidFarmWindow));
+}
+
+// every scenario runs natively, and against the BPF build as well when `common::test_bpf` is on
+
+#[tokio::test]
+async fn test_create_farm() {
+    create_farm::<false>().await;
+    if common::test_bpf() {
+        create_farm::<true>().await;
+    }
+}
+
+#[tokio::test]
+async fn test_create_farm_requires_authority_fee_vault() {
+    create_farm_requires_authority_fee_vault::<false>().await;
+    if common::test_bpf() {
+        create_farm_requires_authority_fee_vault::<true>().await;
+    }
+}
+
+#[tokio::test]
+async fn test_create_farm_checks_authority_nonce() {
+    create_farm_checks_authority_nonce::<false>().await;
+    if common::test_bpf() {
+        create_farm_checks_authority_nonce::<true>().await;
+    }
+}
+
+#[tokio::test]
+async fn test_create_farm_requires_duration() {
+    create_farm_requires_duration::<false>().await;
+    if common::test_bpf() {
+        create_farm_requires_duration::<true>().await;
+    }
+}
@@ -0,0 +1,119 @@
+mod common;
+
+use {
+    common::{farm_error, FarmFixture},
+    ctf_solana_farm::{
+        error::FarmError,
+        instruction::{find_config_address, ix_accept_creator, ix_propose_creator},
+    },
+    solana_program_test::*,
+    solana_sdk::{
+        instruction::InstructionError,
+        pubkey::Pubkey,
+        signature::{Keypair, Signer},
+        transaction::TransactionError,
+    },
+};
+
+async fn propose_and_accept_creator<const BPF: bool>() {
+    let mut ctx = FarmFixture::new().start::<BPF>().await;
+    let creator = Keypair::from_bytes(&ctx.creator.to_bytes()).unwrap();
+    let new_creator = Keypair::new();
+    let farm_id = ctx.farm.pubkey();
+
+    ctx.send(&[ix_propose_creator(&farm_id, &creator.pubkey(), &new_creator.pubkey(), &ctx.program_id)], &[&creator])
+        .await
+        .unwrap();
+    let farm = ctx.get_farm().await;
+    assert_eq!(farm.creator, creator.pubkey());
+    assert_eq!(farm.pending_creator, new_creator.pubkey());
+
+    ctx.send(&[ix_accept_creator(&farm_id, &new_creator.pubkey(), &ctx.program_id)], &[&new_creator])
+        .await
+        .unwrap();
+    let farm = ctx.get_farm().await;
+    assert_eq!(farm.creator, new_creator.pubkey());
+    assert_eq!(farm.pending_creator, Pubkey::default());
+
+    // the previous creator lost control of the farm
+    let result = ctx.send(&[ix_propose_creator(&farm_id, &creator.pubkey(), &creator.pubkey(), &ctx.program_id)], &[&creator]).await;
+    assert_eq!(farm_error(result), Some(FarmError::Unauthorized));
+}
+
+async fn only_creator_can_propose<const BPF: bool>() {
+    let mut ctx = FarmFixture::new().start::<BPF>().await;
+    let intruder = Keypair::new();
+    let farm_id = ctx.farm.pubkey();
+
+    let result = ctx.send(&[ix_propose_creator(&farm_id, &intruder.pubkey(), &intruder.pubkey(), &ctx.program_id)], &[&intruder]).await;
+    assert_eq!(farm_error(result), Some(FarmError::Unauthorized));
+}
+
+async fn program_account_that_isnt_a_farm_is_rejected<const BPF: bool>() {
+    let mut ctx = FarmFixture::new().start::<BPF>().await;
+    let creator = Keypair::from_bytes(&ctx.creator.to_bytes()).unwrap();
+    // the program config is owned by the program but doesn't load as a farm
+    let config = find_config_address(&ctx.program_id).0;
+
+    let result = ctx.send(&[ix_propose_creator(&config, &creator.pubkey(), &creator.pubkey(), &ctx.program_id)], &[&creator]).await;
+    assert_eq!(result.unwrap_err().unwrap(), TransactionError::InstructionError(0, InstructionError::AccountDataTooSmall));
+}
+
+async fn accept_requires_pending_creator<const BPF: bool>() {
+    let mut ctx = FarmFixture::new().start::<BPF>().await;
+    let creator = Keypair::from_bytes(&ctx.creator.to_bytes()).unwrap();
+    let new_creator = Keypair::new();
+    let intruder = Keypair::new();
+    let farm_id = ctx.farm.pubkey();
+
+    let result = ctx.send(&[ix_accept_creator(&farm_id, &new_creator.pubkey(), &ctx.program_id)], &[&new_creator]).await;
+    assert_eq!(farm_error(result), Some(FarmError::NoPendingCreator));
+
+    ctx.send(&[ix_propose_creator(&farm_id, &creator.pubkey(), &new_creator.pubkey(), &ctx.program_id)], &[&creator])
+        .await
+        .unwrap();
+    let result = ctx.send(&[ix_accept_creator(&farm_id, &intruder.pubkey(), &ctx.program_id)], &[&intruder]).await;
+    assert_eq!(farm_error(result), Some(FarmError::Unauthorized));
+
+    // proposing the default pubkey cancels the transfer
+    ctx.send(&[ix_propose_creator(&farm_id, &creator.pubkey(), &Pubkey::default(), &ctx.program_id)], &[&creator])
+        .await
+        .unwrap();
+    let result = ctx.send(&[ix_accept_creator(&farm_id, &new_creator.pubkey(), &ctx.program_id)], &[&new_creator]).await;
+    assert_eq!(farm_error(result), Some(FarmError::NoPendingCreator));
+    assert_eq!(ctx.get_farm().await.creator, creator.pubkey());
+}
+
+// every scenario runs natively, and against the BPF build as well when `common::test_bpf` is on
+
+#[tokio::test]
+async fn test_propose_and_accept_creator() {
+    propose_and_accept_creator::<false>().await;
+    if common::test_bpf() {
+        propose_and_accept_creator::<true>().await;
+    }
+}
+
+#[tokio::test]
+async fn test_only_creator_can_propose() {
+    only_creator_can_propose::<false>().await;
+    if common::test_bpf() {
+        only_creator_can_propose::<true>().await;
+    }
+}
+
+#[tokio::test]
+async fn test_accept_requires_pending_creator() {
+    accept_requires_pending_creator::<false>().await;
+    if common::test_bpf() {
+        accept_requires_pending_creator::<true>().await;
+    }
+}
+
+#[tokio::test]
+async fn test_program_account_that_isnt_a_farm_is_rejected() {
+    program_account_that_isnt_a_farm_is_rejected::<false>().await;
+    if common::test_bpf() {
+        program_account_that_isnt_a_farm_is_rejected::<true>().await;
+    }
+}
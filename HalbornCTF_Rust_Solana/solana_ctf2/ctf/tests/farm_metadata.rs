@@ -0,0 +1,152 @@
+mod common;
+
+use {
+    borsh::BorshDeserialize,
+    common::{farm_error, FarmContext, FarmFixture},
+    ctf_solana_farm::{
+        constant::{FARM_METADATA_DISCRIMINATOR, MAX_METADATA_NAME_LEN, MAX_METADATA_TAGS},
+        error::FarmError,
+        instruction::{find_farm_metadata_address, ix_set_farm_metadata, ix_update_farm_metadata},
+        state::FarmMetadata,
+    },
+    solana_program_test::*,
+    solana_sdk::{
+        account::Account,
+        instruction::Instruction,
+        rent::Rent,
+        signature::{Keypair, Signer},
+    },
+};
+
+fn set_metadata(ctx: &FarmContext, creator: &Keypair, name: &str, uri: &str, tags: &[&str]) -> Instruction {
+    ix_set_farm_metadata(
+        &ctx.farm.pubkey(),
+        &creator.pubkey(),
+        &ctx.program_id,
+        name.to_string(),
+        "FARM".to_string(),
+        uri.to_string(),
+        tags.iter().map(|tag| tag.to_string()).collect(),
+    )
+}
+
+fn update_metadata(ctx: &FarmContext, creator: &Keypair, name: &str, uri: &str, tags: &[&str]) -> Instruction {
+    ix_update_farm_metadata(
+        &ctx.farm.pubkey(),
+        &creator.pubkey(),
+        &ctx.program_id,
+        name.to_string(),
+        "FARM".to_string(),
+        uri.to_string(),
+        tags.iter().map(|tag| tag.to_string()).collect(),
+    )
+}
+
+async fn metadata_account(ctx: &mut FarmContext) -> Account {
+    let address = find_farm_metadata_address(&ctx.farm.pubkey(), &ctx.program_id).0;
+    ctx.banks_client.get_account(address).await.unwrap().unwrap()
+}
+
+async fn set_farm_metadata<const BPF: bool>() {
+    let mut ctx = FarmFixture::new().start::<BPF>().await;
+    let creator = Keypair::from_bytes(&ctx.creator.to_bytes()).unwrap();
+    let intruder = Keypair::new();
+
+    let result = ctx.send(&[set_metadata(&ctx, &intruder, "farm", "https://farm", &[])], &[&intruder]).await;
+    assert_eq!(farm_error(result), Some(FarmError::Unauthorized));
+
+    ctx.send(&[set_metadata(&ctx, &creator, "farm", "https://farm", &["defi"])], &[&creator])
+        .await
+        .unwrap();
+    let account = metadata_account(&mut ctx).await;
+    assert_eq!(account.owner, ctx.program_id);
+    assert_eq!(account.data[..8], FARM_METADATA_DISCRIMINATOR);
+    let metadata = FarmMetadata::try_from_slice(&account.data).unwrap();
+    assert_eq!(metadata.farm, ctx.farm.pubkey());
+    assert_eq!(metadata.name, "farm");
+    assert_eq!(metadata.uri, "https://farm");
+    assert_eq!(metadata.tags, vec!["defi".to_string()]);
+
+    let result = ctx.send(&[set_metadata(&ctx, &creator, "other", "", &[])], &[&creator]).await;
+    assert_eq!(farm_error(result), Some(FarmError::AlreadyInUse));
+}
+
+async fn farm_metadata_max_lengths<const BPF: bool>() {
+    let mut ctx = FarmFixture::new().start::<BPF>().await;
+    let creator = Keypair::from_bytes(&ctx.creator.to_bytes()).unwrap();
+
+    let name = "n".repeat(MAX_METADATA_NAME_LEN + 1);
+    let result = ctx.send(&[set_metadata(&ctx, &creator, &name, "", &[])], &[&creator]).await;
+    assert_eq!(farm_error(result), Some(FarmError::FarmMetadataTooLong));
+    let tags = vec!["tag"; MAX_METADATA_TAGS + 1];
+    let result = ctx.send(&[set_metadata(&ctx, &creator, "farm", "", &tags)], &[&creator]).await;
+    assert_eq!(farm_error(result), Some(FarmError::FarmMetadataTooLong));
+
+    ctx.send(&[set_metadata(&ctx, &creator, "farm", "", &[])], &[&creator]).await.unwrap();
+    let result = ctx.send(&[update_metadata(&ctx, &creator, &name, "", &[])], &[&creator]).await;
+    assert_eq!(farm_error(result), Some(FarmError::FarmMetadataTooLong));
+}
+
+async fn update_farm_metadata_reallocs<const BPF: bool>() {
+    let mut ctx = FarmFixture::new().start::<BPF>().await;
+    let creator = Keypair::from_bytes(&ctx.creator.to_bytes()).unwrap();
+    let intruder = Keypair::new();
+
+    let result = ctx.send(&[update_metadata(&ctx, &creator, "farm", "", &[])], &[&creator]).await;
+    assert_eq!(farm_error(result), Some(FarmError::InvalidFarmMetadata));
+
+    ctx.send(&[set_metadata(&ctx, &creator, "farm", "", &[])], &[&creator]).await.unwrap();
+    let small_len = metadata_account(&mut ctx).await.data.len();
+
+    let result = ctx.send(&[update_metadata(&ctx, &intruder, "farm", "", &[])], &[&intruder]).await;
+    assert_eq!(farm_error(result), Some(FarmError::Unauthorized));
+
+    // grows and stays rent exempt, the creator pays the difference
+    let uri = format!("https://farm/{}", "u".repeat(100));
+    ctx.send(&[update_metadata(&ctx, &creator, "grown farm", &uri, &["defi", "staking"])], &[&creator])
+        .await
+        .unwrap();
+    let account = metadata_account(&mut ctx).await;
+    assert!(account.data.len() > small_len);
+    assert!(Rent::default().is_exempt(account.lamports, account.data.len()));
+    let metadata = FarmMetadata::try_from_slice(&account.data).unwrap();
+    assert_eq!(metadata.name, "grown farm");
+    assert_eq!(metadata.uri, uri);
+    assert_eq!(metadata.tags.len(), 2);
+
+    // shrinking keeps the size, the end is zeroed
+    let grown_len = account.data.len();
+    ctx.send(&[update_metadata(&ctx, &creator, "farm", "", &[])], &[&creator]).await.unwrap();
+    let account = metadata_account(&mut ctx).await;
+    assert_eq!(account.data.len(), grown_len);
+    assert!(account.data[small_len..].iter().all(|byte| *byte == 0));
+    let metadata = FarmMetadata::deserialize(&mut account.data.as_slice()).unwrap();
+    assert_eq!(metadata.name, "farm");
+    assert!(metadata.tags.is_empty());
+}
+
+// every scenario runs natively, and against the BPF build as well when `common::test_bpf` is on
+
+#[tokio::test]
+async fn test_set_farm_metadata() {
+    set_farm_metadata::<false>().await;
+    if common::test_bpf() {
+        set_farm_metadata::<true>().await;
+    }
+}
+
+#[tokio::test]
+async fn test_farm_metadata_max_lengths() {
+    farm_metadata_max_lengths::<false>().await;
+    if common::test_bpf() {
+        farm_metadata_max_lengths::<true>().await;
+    }
+}
+
+#[tokio::test]
+async fn test_update_farm_metadata_reallocs() {
+    update_farm_metadata_reallocs::<false>().await;
+    if common::test_bpf() {
+        update_farm_metadata_reallocs::<true>().await;
+    }
+}
@@ -0,0 +1,163 @@
+mod common;
+
+use {
+    borsh::BorshDeserialize,
+    common::{farm_error, send, FarmFixture, CREATOR_BALANCE},
+    ctf_solana_farm::{
+        constant::{FARM_FEE, MAX_FEE_MINTS},
+        error::FarmError,
+        instruction::{find_fee_schedule_address, ix_set_fee_schedule},
+        state::{FeeMint, FeeSchedule, FeeTier},
+    },
+    solana_program_test::*,
+    solana_sdk::{
+        pubkey::Pubkey,
+        signature::{Keypair, Signer},
+    },
+};
+
+const DAY: u64 = 86_400;
+
+/// a week long farm pays the base fee, a month long farm twice of it
+fn tiers() -> Vec<FeeTier> {
+    vec![
+        FeeTier { min_duration: 7 * DAY, multiplier_bps: 10_000 },
+        FeeTier { min_duration: 30 * DAY, multiplier_bps: 20_000 },
+    ]
+}
+
+async fn fee_follows_duration_tier<const BPF: bool>() {
+    let mut ctx = FarmFixture::new().duration(45 * DAY).fee_tiers(tiers()).start::<BPF>().await;
+
+    assert_eq!(farm_error(ctx.pay(None, FARM_FEE).await), Some(FarmError::InvalidFarmFee));
+    ctx.pay(None, 2 * FARM_FEE).await.unwrap();
+    assert_eq!(ctx.token_balance(ctx.fee_vault).await, 2 * FARM_FEE);
+    assert_eq!(ctx.token_balance(ctx.creator_token_account).await, CREATOR_BALANCE - 2 * FARM_FEE);
+}
+
+async fn short_farm_pays_base_fee<const BPF: bool>() {
+    let mut ctx = FarmFixture::new().duration(DAY).fee_tiers(tiers()).start::<BPF>().await;
+
+    ctx.pay(None, FARM_FEE).await.unwrap();
+    assert_eq!(ctx.token_balance(ctx.fee_vault).await, FARM_FEE);
+}
+
+async fn short_farm_pays_again_once_its_window_ends<const BPF: bool>() {
+    let mut ctx = FarmFixture::new().duration(DAY).fee_tiers(tiers()).start::<BPF>().await;
+
+    // the base fee of a one day farm pays for one day
+    let paid_at = ctx.now().await;
+    ctx.pay(None, FARM_FEE).await.unwrap();
+    let ends_at = ctx.get_farm().await.ends_at;
+    assert!(ends_at >= paid_at + DAY as i64 && ends_at <= ctx.now().await + DAY as i64);
+    assert_eq!(farm_error(ctx.pay(None, FARM_FEE).await), Some(FarmError::AlreadyInUse));
+
+    // it doesn't keep the farm running past that day, the next day is paid for again
+    ctx.warp_to(ends_at).await;
+    assert_eq!(farm_error(ctx.pay(None, 0).await), Some(FarmError::InvalidFarmFee));
+    ctx.pay(None, FARM_FEE).await.unwrap();
+    assert!(ctx.get_farm().await.ends_at >= ends_at + DAY as i64);
+    assert_eq!(ctx.token_balance(ctx.fee_vault).await, 2 * FARM_FEE);
+}
+
+async fn fee_mint_must_be_accepted<const BPF: bool>() {
+    let mut ctx = FarmFixture::new().start::<BPF>().await;
+
+    let instruction = ctx.pay_fee(&ctx.other_creator_token_account, None, FARM_FEE);
+    let result = send(&mut ctx.banks_client, &ctx.payer, instruction, &[&ctx.creator]).await;
+    assert_eq!(farm_error(result), Some(FarmError::UnsupportedFeeMint));
+
+    // once accepted, the fee vault still has to hold the payment mint
+    let mints = vec![
+        FeeMint { mint: ctx.mint, amount: FARM_FEE },
+        FeeMint { mint: ctx.other_mint, amount: 300 },
+    ];
+    ctx.set_fee_schedule(vec![], mints).await.unwrap();
+    let instruction = ctx.pay_fee(&ctx.other_creator_token_account, None, 300);
+    let result = send(&mut ctx.banks_client, &ctx.payer, instruction, &[&ctx.creator]).await;
+    assert_eq!(farm_error(result), Some(FarmError::InvalidFeeAccount));
+}
+
+async fn set_fee_schedule_replaces_schedule<const BPF: bool>() {
+    let mut ctx = FarmFixture::new().start::<BPF>().await;
+
+    let mints = vec![FeeMint { mint: ctx.mint, amount: 42 }];
+    ctx.set_fee_schedule(tiers(), mints.clone()).await.unwrap();
+    let account = ctx
+        .banks_client
+        .get_account(find_fee_schedule_address(&ctx.program_id).0)
+        .await
+        .unwrap()
+        .unwrap();
+    let fee_schedule = FeeSchedule::deserialize(&mut &account.data[..]).unwrap();
+    assert_eq!(account.data.len(), FeeSchedule::LEN);
+    assert_eq!(fee_schedule.tiers, tiers());
+    assert_eq!(fee_schedule.mints, mints);
+}
+
+async fn set_fee_schedule_rejects_invalid_schedule<const BPF: bool>() {
+    let mut ctx = FarmFixture::new().start::<BPF>().await;
+    let mint = FeeMint { mint: ctx.mint, amount: FARM_FEE };
+
+    let unsorted = tiers().into_iter().rev().collect();
+    assert_eq!(farm_error(ctx.set_fee_schedule(unsorted, vec![mint]).await), Some(FarmError::InvalidFeeSchedule));
+    assert_eq!(farm_error(ctx.set_fee_schedule(vec![], vec![mint, mint]).await), Some(FarmError::InvalidFeeSchedule));
+    let too_many = (0..=MAX_FEE_MINTS).map(|_| FeeMint { mint: Pubkey::new_unique(), amount: 1 }).collect();
+    assert_eq!(farm_error(ctx.set_fee_schedule(vec![], too_many).await), Some(FarmError::InvalidFeeSchedule));
+
+    // only the admin sets the schedule
+    let intruder = Keypair::new();
+    let instruction = ix_set_fee_schedule(&intruder.pubkey(), &ctx.program_id, vec![], vec![mint]);
+    let result = send(&mut ctx.banks_client, &ctx.payer, instruction, &[&intruder]).await;
+    assert_eq!(farm_error(result), Some(FarmError::WrongAdmin));
+}
+
+// every scenario runs natively, and against the BPF build as well when `common::test_bpf` is on
+
+#[tokio::test]
+async fn test_fee_follows_duration_tier() {
+    fee_follows_duration_tier::<false>().await;
+    if common::test_bpf() {
+        fee_follows_duration_tier::<true>().await;
+    }
+}
+
+#[tokio::test]
+async fn test_short_farm_pays_base_fee() {
+    short_farm_pays_base_fee::<false>().await;
+    if common::test_bpf() {
+        short_farm_pays_base_fee::<true>().await;
+    }
+}
+
+#[tokio::test]
+async fn test_short_farm_pays_again_once_its_window_ends() {
+    short_farm_pays_again_once_its_window_ends::<false>().await;
+    if common::test_bpf() {
+        short_farm_pays_again_once_its_window_ends::<true>().await;
+    }
+}
+
+#[tokio::test]
+async fn test_fee_mint_must_be_accepted() {
+    fee_mint_must_be_accepted::<false>().await;
+    if common::test_bpf() {
+        fee_mint_must_be_accepted::<true>().await;
+    }
+}
+
+#[tokio::test]
+async fn test_set_fee_schedule_replaces_schedule() {
+    set_fee_schedule_replaces_schedule::<false>().await;
+    if common::test_bpf() {
+        set_fee_schedule_replaces_schedule::<true>().await;
+    }
+}
+
+#[tokio::test]
+async fn test_set_fee_schedule_rejects_invalid_schedule() {
+    set_fee_schedule_rejects_invalid_schedule::<false>().await;
+    if common::test_bpf() {
+        set_fee_schedule_rejects_invalid_schedule::<true>().await;
+    }
+}
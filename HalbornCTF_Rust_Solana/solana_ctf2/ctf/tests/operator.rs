@@ -0,0 +1,175 @@
+mod common;
+
+use {
+    common::{farm_error, FarmContext, FarmFixture},
+    ctf_solana_farm::{
+        constant::FARM_FEE,
+        error::FarmError,
+        instruction::{
+            ix_pay_create_fee, ix_propose_creator, ix_revoke_operator, ix_set_farm_metadata, ix_set_farm_paused,
+            ix_set_operator, ix_set_paused,
+        },
+    },
+    solana_program_test::*,
+    solana_sdk::{
+        pubkey::Pubkey,
+        signature::{Keypair, Signer},
+    },
+};
+
+/// starts a farm with `operator` set by the creator
+async fn start_with_operator<const BPF: bool>(operator: &Keypair) -> FarmContext {
+    let mut ctx = FarmFixture::new().start::<BPF>().await;
+    let creator = Keypair::from_bytes(&ctx.creator.to_bytes()).unwrap();
+    ctx.send(&[ix_set_operator(&ctx.farm.pubkey(), &creator.pubkey(), &operator.pubkey(), &ctx.program_id)], &[&creator])
+        .await
+        .unwrap();
+    ctx
+}
+
+async fn operator_pauses_the_farm<const BPF: bool>() {
+    let operator = Keypair::new();
+    let mut ctx = start_with_operator::<BPF>(&operator).await;
+    let farm_id = ctx.farm.pubkey();
+    assert_eq!(ctx.get_farm().await.operator, operator.pubkey());
+
+    ctx.send(&[ix_set_farm_paused(&farm_id, &operator.pubkey(), &ctx.program_id, true)], &[&operator])
+        .await
+        .unwrap();
+    assert_eq!(ctx.get_farm().await.paused, 1);
+    let result = ctx.pay(None, FARM_FEE).await;
+    assert_eq!(farm_error(result), Some(FarmError::FarmPaused));
+
+    // the creator can resume it as well
+    let creator = Keypair::from_bytes(&ctx.creator.to_bytes()).unwrap();
+    ctx.send(&[ix_set_farm_paused(&farm_id, &creator.pubkey(), &ctx.program_id, false)], &[&creator])
+        .await
+        .unwrap();
+    ctx.pay(None, FARM_FEE).await.unwrap();
+    assert_eq!(ctx.get_farm().await.enabled, 1);
+}
+
+async fn operator_permission_matrix<const BPF: bool>() {
+    let operator = Keypair::new();
+    let mut ctx = start_with_operator::<BPF>(&operator).await;
+    let farm_id = ctx.farm.pubkey();
+
+    let pay_fee = ix_pay_create_fee(
+        &farm_id,
+        &ctx.authority,
+        &operator.pubkey(),
+        &ctx.creator_token_account,
+        &ctx.fee_vault,
+        &spl_token::id(),
+        &ctx.program_id,
+        FARM_FEE,
+    );
+    let propose_creator = ix_propose_creator(&farm_id, &operator.pubkey(), &operator.pubkey(), &ctx.program_id);
+    let set_operator = ix_set_operator(&farm_id, &operator.pubkey(), &Pubkey::new_unique(), &ctx.program_id);
+    let set_metadata = ix_set_farm_metadata(
+        &farm_id,
+        &operator.pubkey(),
+        &ctx.program_id,
+        "farm".to_string(),
+        "FARM".to_string(),
+        String::new(),
+        vec![],
+    );
+    for instruction in [pay_fee, propose_creator, set_operator, set_metadata] {
+        let result = ctx.send(&[instruction], &[&operator]).await;
+        assert_eq!(farm_error(result), Some(FarmError::Unauthorized));
+    }
+    let farm = ctx.get_farm().await;
+    assert_eq!(farm.enabled, 0);
+    assert_eq!(farm.creator, ctx.creator.pubkey());
+    assert_eq!(farm.operator, operator.pubkey());
+
+    // someone without a role on the farm is rejected the same way
+    let intruder = Keypair::new();
+    let result = ctx
+        .send(&[ix_set_operator(&farm_id, &intruder.pubkey(), &intruder.pubkey(), &ctx.program_id)], &[&intruder])
+        .await;
+    assert_eq!(farm_error(result), Some(FarmError::Unauthorized));
+    let result = ctx.send(&[ix_set_farm_paused(&farm_id, &intruder.pubkey(), &ctx.program_id, true)], &[&intruder]).await;
+    assert_eq!(farm_error(result), Some(FarmError::Unauthorized));
+}
+
+async fn revoke_operator<const BPF: bool>() {
+    let operator = Keypair::new();
+    let mut ctx = start_with_operator::<BPF>(&operator).await;
+    let creator = Keypair::from_bytes(&ctx.creator.to_bytes()).unwrap();
+    let farm_id = ctx.farm.pubkey();
+
+    // the operator steps down
+    ctx.send(&[ix_revoke_operator(&farm_id, &operator.pubkey(), &ctx.program_id)], &[&operator])
+        .await
+        .unwrap();
+    assert_eq!(ctx.get_farm().await.operator, Pubkey::default());
+    let result = ctx.send(&[ix_set_farm_paused(&farm_id, &operator.pubkey(), &ctx.program_id, true)], &[&operator]).await;
+    assert_eq!(farm_error(result), Some(FarmError::Unauthorized));
+
+    // the creator removes it
+    ctx.send(&[ix_set_operator(&farm_id, &creator.pubkey(), &operator.pubkey(), &ctx.program_id)], &[&creator])
+        .await
+        .unwrap();
+    ctx.send(&[ix_revoke_operator(&farm_id, &creator.pubkey(), &ctx.program_id)], &[&creator])
+        .await
+        .unwrap();
+    let farm = ctx.get_farm().await;
+    assert_eq!(farm.operator, Pubkey::default());
+    assert_eq!(farm.paused, 0);
+}
+
+async fn program_pause_only_lets_farms_pause<const BPF: bool>() {
+    let operator = Keypair::new();
+    let mut ctx = start_with_operator::<BPF>(&operator).await;
+    let admin = Keypair::from_bytes(&ctx.admin.to_bytes()).unwrap();
+    let farm_id = ctx.farm.pubkey();
+    ctx.send(&[ix_set_paused(&admin.pubkey(), &ctx.program_id, true)], &[&admin])
+        .await
+        .unwrap();
+
+    ctx.send(&[ix_set_farm_paused(&farm_id, &operator.pubkey(), &ctx.program_id, true)], &[&operator])
+        .await
+        .unwrap();
+    assert_eq!(ctx.get_farm().await.paused, 1);
+    let result = ctx.send(&[ix_set_farm_paused(&farm_id, &operator.pubkey(), &ctx.program_id, false)], &[&operator]).await;
+    assert_eq!(farm_error(result), Some(FarmError::ProgramPaused));
+    let result = ctx.send(&[ix_revoke_operator(&farm_id, &operator.pubkey(), &ctx.program_id)], &[&operator]).await;
+    assert_eq!(farm_error(result), Some(FarmError::ProgramPaused));
+    assert_eq!(ctx.get_farm().await.operator, operator.pubkey());
+}
+
+// every scenario runs natively, and against the BPF build as well when `common::test_bpf` is on
+
+#[tokio::test]
+async fn test_operator_pauses_the_farm() {
+    operator_pauses_the_farm::<false>().await;
+    if common::test_bpf() {
+        operator_pauses_the_farm::<true>().await;
+    }
+}
+
+#[tokio::test]
+async fn test_operator_permission_matrix() {
+    operator_permission_matrix::<false>().await;
+    if common::test_bpf() {
+        operator_permission_matrix::<true>().await;
+    }
+}
+
+#[tokio::test]
+async fn test_revoke_operator() {
+    revoke_operator::<false>().await;
+    if common::test_bpf() {
+        revoke_operator::<true>().await;
+    }
+}
+
+#[tokio::test]
+async fn test_program_pause_only_lets_farms_pause() {
+    program_pause_only_lets_farms_pause::<false>().await;
+    if common::test_bpf() {
+        program_pause_only_lets_farms_pause::<true>().await;
+    }
+}
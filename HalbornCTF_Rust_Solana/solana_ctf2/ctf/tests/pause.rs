@@ -0,0 +1,175 @@
+mod common;
+
+use {
+    assert_matches::*,
+    borsh::BorshDeserialize,
+    common::{farm_error, initialize_config, send},
+    farm_core::conformance::upgrade_authority,
+    ctf_solana_farm::{
+        error::FarmError,
+        instruction::{find_config_address, ix_initialize_config, ix_pay_create_fee, ix_set_paused, ix_transfer_admin},
+        state::ProgramConfig,
+    },
+    solana_program::instruction::{Instruction, InstructionError},
+    solana_program_test::*,
+    solana_sdk::{
+        pubkey::Pubkey,
+        signature::{Keypair, Signer},
+        transaction::TransactionError,
+    },
+};
+
+fn pay_fee_instruction(program_id: &Pubkey, creator: &Keypair) -> Instruction {
+    ix_pay_create_fee(
+        &Pubkey::new_unique(),
+        &Pubkey::new_unique(),
+        &creator.pubkey(),
+        &Pubkey::new_unique(),
+        &Pubkey::new_unique(),
+        &spl_token::id(),
+        program_id,
+        5000,
+    )
+}
+
+async fn start<const BPF: bool>() -> (BanksClient, Keypair, Pubkey) {
+    let program_id = Pubkey::new_unique();
+    let (mut banks_client, payer, _) = common::program_test::<BPF>(program_id).start().await;
+    // the payer is the admin
+    initialize_config(&mut banks_client, &payer, &program_id).await;
+    (banks_client, payer, program_id)
+}
+
+async fn initializes_config<const BPF: bool>() {
+    let (mut banks_client, payer, program_id) = start::<BPF>().await;
+
+    let config_account = banks_client
+        .get_account(find_config_address(&program_id).0)
+        .await
+        .unwrap()
+        .unwrap();
+    let config = ProgramConfig::try_from_slice(&config_account.data).unwrap();
+    assert_eq!(config_account.owner, program_id);
+    assert_eq!(config.admin, payer.pubkey());
+    assert_eq!(config.paused, 0);
+
+    // the config can only be created once
+    let (other_admin, upgrade_authority) = (Keypair::new(), upgrade_authority());
+    let instruction = ix_initialize_config(&other_admin.pubkey(), &upgrade_authority.pubkey(), &program_id);
+    let result = send(&mut banks_client, &payer, instruction, &[&other_admin, &upgrade_authority]).await;
+    assert_eq!(farm_error(result), Some(FarmError::AlreadyInUse));
+}
+
+async fn initialize_config_requires_upgrade_authority<const BPF: bool>() {
+    let program_id = Pubkey::new_unique();
+    let (mut banks_client, payer, _) = common::program_test::<BPF>(program_id).start().await;
+
+    // the first caller doesn't get to be the admin
+    let squatter = Keypair::new();
+    let instruction = ix_initialize_config(&payer.pubkey(), &squatter.pubkey(), &program_id);
+    let result = send(&mut banks_client, &payer, instruction, &[&squatter]).await;
+    assert_eq!(farm_error(result), Some(FarmError::WrongAdmin));
+
+    // nor does the authority of another program
+    let mut instruction = ix_initialize_config(&payer.pubkey(), &upgrade_authority().pubkey(), &program_id);
+    instruction.accounts[3].pubkey = Pubkey::new_unique();
+    let result = send(&mut banks_client, &payer, instruction, &[&upgrade_authority()]).await;
+    assert_eq!(
+        result.unwrap_err().unwrap(),
+        TransactionError::InstructionError(0, InstructionError::InvalidAccountData)
+    );
+    assert!(banks_client.get_account(find_config_address(&program_id).0).await.unwrap().is_none());
+
+    initialize_config(&mut banks_client, &payer, &program_id).await;
+}
+
+async fn paused_program_rejects_user_instructions<const BPF: bool>() {
+    let (mut banks_client, payer, program_id) = start::<BPF>().await;
+    let creator = Keypair::new();
+
+    // unpaused, the instruction reaches the fee processor and fails on the missing farm
+    let result = send(&mut banks_client, &payer, pay_fee_instruction(&program_id, &creator), &[&creator]).await;
+    assert_ne!(farm_error(result), Some(FarmError::ProgramPaused));
+
+    send(&mut banks_client, &payer, ix_set_paused(&payer.pubkey(), &program_id, true), &[])
+        .await
+        .unwrap();
+    let result = send(&mut banks_client, &payer, pay_fee_instruction(&program_id, &creator), &[&creator]).await;
+    assert_eq!(farm_error(result), Some(FarmError::ProgramPaused));
+
+    // admin instructions stay callable while paused
+    send(&mut banks_client, &payer, ix_set_paused(&payer.pubkey(), &program_id, false), &[])
+        .await
+        .unwrap();
+    let result = send(&mut banks_client, &payer, pay_fee_instruction(&program_id, &creator), &[&creator]).await;
+    assert_ne!(farm_error(result), Some(FarmError::ProgramPaused));
+}
+
+async fn only_admin_can_pause<const BPF: bool>() {
+    let (mut banks_client, payer, program_id) = start::<BPF>().await;
+    let intruder = Keypair::new();
+
+    let result = send(&mut banks_client, &payer, ix_set_paused(&intruder.pubkey(), &program_id, true), &[&intruder]).await;
+    assert_eq!(farm_error(result), Some(FarmError::WrongAdmin));
+}
+
+async fn transfer_admin<const BPF: bool>() {
+    let (mut banks_client, payer, program_id) = start::<BPF>().await;
+    let new_admin = Keypair::new();
+
+    send(&mut banks_client, &payer, ix_set_paused(&payer.pubkey(), &program_id, true), &[])
+        .await
+        .unwrap();
+    send(&mut banks_client, &payer, ix_transfer_admin(&payer.pubkey(), &new_admin.pubkey(), &program_id), &[])
+        .await
+        .unwrap();
+
+    let result = send(&mut banks_client, &payer, ix_set_paused(&payer.pubkey(), &program_id, false), &[]).await;
+    assert_eq!(farm_error(result), Some(FarmError::WrongAdmin));
+    assert_matches!(
+        send(&mut banks_client, &payer, ix_set_paused(&new_admin.pubkey(), &program_id, false), &[&new_admin]).await,
+        Ok(())
+    );
+}
+
+// every scenario runs natively, and against the BPF build as well when `common::test_bpf` is on
+
+#[tokio::test]
+async fn test_initialize_config() {
+    initializes_config::<false>().await;
+    if common::test_bpf() {
+        initializes_config::<true>().await;
+    }
+}
+
+#[tokio::test]
+async fn test_initialize_config_requires_upgrade_authority() {
+    initialize_config_requires_upgrade_authority::<false>().await;
+    if common::test_bpf() {
+        initialize_config_requires_upgrade_authority::<true>().await;
+    }
+}
+
+#[tokio::test]
+async fn test_paused_program_rejects_user_instructions() {
+    paused_program_rejects_user_instructions::<false>().await;
+    if common::test_bpf() {
+        paused_program_rejects_user_instructions::<true>().await;
+    }
+}
+
+#[tokio::test]
+async fn test_only_admin_can_pause() {
+    only_admin_can_pause::<false>().await;
+    if common::test_bpf() {
+        only_admin_can_pause::<true>().await;
+    }
+}
+
+#[tokio::test]
+async fn test_transfer_admin() {
+    transfer_admin::<false>().await;
+    if common::test_bpf() {
+        transfer_admin::<true>().await;
+    }
+}
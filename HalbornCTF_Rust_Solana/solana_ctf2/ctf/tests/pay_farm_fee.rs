@@ -0,0 +1,59 @@
+mod common;
+
+use {
+    borsh::BorshDeserialize,
+    common::{program_test, send, token_balance, FarmFixture, CREATOR_BALANCE},
+    ctf_solana_farm::{constant::FARM_FEE, instruction::ix_pay_create_fee, state::Farm},
+    solana_program::{account_info::AccountInfo, entrypoint::ProgramResult, pubkey::Pubkey},
+    solana_program_test::*,
+    solana_sdk::{instruction::InstructionError, signature::Signer, transaction::TransactionError},
+};
+
+/// accepts every instruction, a "token program" whose transfers move nothing
+fn process_fake_token(_program_id: &Pubkey, _accounts: &[AccountInfo], _instruction_data: &[u8]) -> ProgramResult {
+    Ok(())
+}
+
+async fn pay_fee_rejects_fake_token_program<const BPF: bool>() {
+    let program_id = Pubkey::new_unique();
+    let fixture = FarmFixture::new().program_id(program_id);
+    let addresses = fixture.addresses();
+    let fake_token_program = Pubkey::new_unique();
+    let mut program_test = program_test::<BPF>(program_id);
+    program_test.add_program("fake_token", fake_token_program, processor!(process_fake_token));
+    for (address, account) in fixture.accounts(&addresses) {
+        program_test.add_account(address, account);
+    }
+    let (mut banks_client, payer, _) = program_test.start().await;
+
+    let instruction = ix_pay_create_fee(
+        &fixture.farm().pubkey(),
+        &addresses.authority,
+        &fixture.creator().pubkey(),
+        &addresses.creator_token_account,
+        &addresses.fee_vault,
+        &fake_token_program,
+        &program_id,
+        FARM_FEE,
+    );
+    let result = send(&mut banks_client, &payer, instruction, &[fixture.creator()]).await;
+    assert_eq!(
+        result.unwrap_err().unwrap(),
+        TransactionError::InstructionError(0, InstructionError::IncorrectProgramId)
+    );
+
+    // the farm stays disabled and the creator keeps the fee
+    let farm = banks_client.get_account(fixture.farm().pubkey()).await.unwrap().unwrap();
+    assert_eq!(Farm::try_from_slice(&farm.data).unwrap().enabled, 0);
+    assert_eq!(token_balance(&mut banks_client, addresses.creator_token_account).await, CREATOR_BALANCE);
+}
+
+// every scenario runs natively, and against the BPF build as well when `common::test_bpf` is on
+
+#[tokio::test]
+async fn test_pay_fee_rejects_fake_token_program() {
+    pay_fee_rejects_fake_token_program::<false>().await;
+    if common::test_bpf() {
+        pay_fee_rejects_fake_token_program::<true>().await;
+    }
+}
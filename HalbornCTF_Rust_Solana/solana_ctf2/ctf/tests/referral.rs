@@ -0,0 +1,160 @@
+mod common;
+
+use {
+    borsh::{BorshDeserialize, BorshSerialize},
+    common::{farm_error, send, FarmFixture, CREATOR_BALANCE},
+    ctf_solana_farm::{
+        constant::FARM_FEE,
+        error::FarmError,
+        instruction::{find_config_address, ix_set_paused, ix_set_referral_share},
+        state::ProgramConfig,
+    },
+    solana_program::rent::Rent,
+    solana_program_test::*,
+    solana_sdk::signature::{Keypair, Signer},
+};
+
+async fn pay_fee_without_referrer<const BPF: bool>() {
+    let mut ctx = FarmFixture::new().referral_bps(2_000).start::<BPF>().await;
+
+    ctx.pay(None, FARM_FEE).await.unwrap();
+    assert_eq!(ctx.token_balance(ctx.fee_vault).await, FARM_FEE);
+    assert_eq!(ctx.token_balance(ctx.creator_token_account).await, CREATOR_BALANCE - FARM_FEE);
+    assert_eq!(ctx.get_farm().await.enabled, 1);
+}
+
+async fn pay_fee_splits_with_referrer<const BPF: bool>() {
+    let mut ctx = FarmFixture::new().referral_bps(2_000).start::<BPF>().await;
+
+    let referrer = ctx.referrer_token_account;
+    ctx.pay(Some(&referrer), FARM_FEE).await.unwrap();
+    assert_eq!(ctx.token_balance(ctx.fee_vault).await, 4_000);
+    assert_eq!(ctx.token_balance(referrer).await, 1_000);
+}
+
+async fn zero_referral_share_skips_referrer_transfer<const BPF: bool>() {
+    let mut ctx = FarmFixture::new().start::<BPF>().await;
+
+    let referrer = ctx.referrer_token_account;
+    ctx.pay(Some(&referrer), FARM_FEE).await.unwrap();
+    assert_eq!(ctx.token_balance(ctx.fee_vault).await, FARM_FEE);
+    assert_eq!(ctx.token_balance(referrer).await, 0);
+}
+
+async fn referrer_cannot_be_fee_vault_or_creator<const BPF: bool>() {
+    let mut ctx = FarmFixture::new().referral_bps(2_000).start::<BPF>().await;
+
+    for referrer in [ctx.fee_vault, ctx.creator_token_account] {
+        assert_eq!(farm_error(ctx.pay(Some(&referrer), FARM_FEE).await), Some(FarmError::InvalidReferrer));
+    }
+}
+
+async fn set_referral_share<const BPF: bool>() {
+    let mut ctx = FarmFixture::new().start::<BPF>().await;
+
+    assert_eq!(farm_error(ctx.set_referral_share(10_001).await), Some(FarmError::InvalidReferralShare));
+    ctx.set_referral_share(10_000).await.unwrap();
+    let config_account = ctx
+        .banks_client
+        .get_account(find_config_address(&ctx.program_id).0)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(ProgramConfig::try_from_slice(&config_account.data).unwrap().referral_bps(), 10_000);
+
+    // only the admin sets the share
+    let intruder = Keypair::new();
+    let instruction = ix_set_referral_share(&intruder.pubkey(), &ctx.program_id, 0);
+    let result = send(&mut ctx.banks_client, &ctx.payer, instruction, &[&intruder]).await;
+    assert_eq!(farm_error(result), Some(FarmError::WrongAdmin));
+}
+
+async fn set_referral_share_grows_v0_config<const BPF: bool>() {
+    let mut ctx = FarmFixture::new().v0_config().start::<BPF>().await;
+    let config_address = find_config_address(&ctx.program_id).0;
+
+    // a config from before the referral share keeps working and pays no referrer
+    let referrer = ctx.referrer_token_account;
+    ctx.pay(Some(&referrer), FARM_FEE).await.unwrap();
+    assert_eq!(ctx.token_balance(ctx.fee_vault).await, FARM_FEE);
+    assert_eq!(ctx.token_balance(referrer).await, 0);
+
+    // the other admin instructions write it in place
+    let admin = ctx.admin.insecure_clone();
+    ctx.send(&[ix_set_paused(&admin.pubkey(), &ctx.program_id, true)], &[&admin]).await.unwrap();
+    let config_account = ctx.banks_client.get_account(config_address).await.unwrap().unwrap();
+    assert_eq!(config_account.data.len(), ProgramConfig::V0_LEN);
+    assert_eq!(ProgramConfig::unpack(&config_account.data).unwrap().paused, 1);
+
+    ctx.set_referral_share(2_000).await.unwrap();
+    let config_account = ctx.banks_client.get_account(config_address).await.unwrap().unwrap();
+    assert_eq!(config_account.data.len(), ProgramConfig::LEN);
+    assert!(config_account.lamports >= Rent::default().minimum_balance(ProgramConfig::LEN));
+    let config = ProgramConfig::try_from_slice(&config_account.data).unwrap();
+    assert_eq!(config.referral_bps(), 2_000);
+    assert_eq!(config.admin, ctx.admin.pubkey());
+    assert_eq!(config.paused, 1);
+}
+
+#[test]
+fn test_unpack_v0_config() {
+    let config = ProgramConfig { is_initialized: 1, bump: 254, admin: Keypair::new().pubkey(), ..ProgramConfig::default() };
+    let data = config.try_to_vec().unwrap();
+    assert_eq!(ProgramConfig::unpack(&data[..ProgramConfig::V0_LEN]).unwrap(), config);
+    assert!(ProgramConfig::unpack(&data[..ProgramConfig::V0_LEN - 1]).is_err());
+
+    // writing into a V0_LEN config leaves out the referral share
+    let mut v0 = vec![0; ProgramConfig::V0_LEN];
+    ProgramConfig { paused: 1, ..config }.pack_into(&mut v0);
+    assert_eq!(ProgramConfig::unpack(&v0).unwrap(), ProgramConfig { paused: 1, ..config });
+}
+
+// every scenario runs natively, and against the BPF build as well when `common::test_bpf` is on
+
+#[tokio::test]
+async fn test_pay_fee_without_referrer() {
+    pay_fee_without_referrer::<false>().await;
+    if common::test_bpf() {
+        pay_fee_without_referrer::<true>().await;
+    }
+}
+
+#[tokio::test]
+async fn test_pay_fee_splits_with_referrer() {
+    pay_fee_splits_with_referrer::<false>().await;
+    if common::test_bpf() {
+        pay_fee_splits_with_referrer::<true>().await;
+    }
+}
+
+#[tokio::test]
+async fn test_zero_referral_share_skips_referrer_transfer() {
+    zero_referral_share_skips_referrer_transfer::<false>().await;
+    if common::test_bpf() {
+        zero_referral_share_skips_referrer_transfer::<true>().await;
+    }
+}
+
+#[tokio::test]
+async fn test_referrer_cannot_be_fee_vault_or_creator() {
+    referrer_cannot_be_fee_vault_or_creator::<false>().await;
+    if common::test_bpf() {
+        referrer_cannot_be_fee_vault_or_creator::<true>().await;
+    }
+}
+
+#[tokio::test]
+async fn test_set_referral_share() {
+    set_referral_share::<false>().await;
+    if common::test_bpf() {
+        set_referral_share::<true>().await;
+    }
+}
+
+#[tokio::test]
+async fn test_set_referral_share_grows_v0_config() {
+    set_referral_share_grows_v0_config::<false>().await;
+    if common::test_bpf() {
+        set_referral_share_grows_v0_config::<true>().await;
+    }
+}
@@ -0,0 +1,91 @@
+use {
+    borsh::{BorshDeserialize, BorshSerialize},
+    ctf_solana_farm::{
+        constant::{FARM_DISCRIMINATOR, FARM_VERSION},
+        state::{Farm, FarmAccount, FarmMetadata},
+    },
+    solana_program::{account_info::AccountInfo, program_error::ProgramError, pubkey::Pubkey},
+};
+
+fn sample_farm() -> Farm {
+    Farm {
+        discriminator: FARM_DISCRIMINATOR,
+        version: FARM_VERSION,
+        enabled: 0,
+        nonce: 254,
+        token_program_id: spl_token::id(),
+        creator: Pubkey::new_unique(),
+        fee_vault: Pubkey::new_unique(),
+        pending_creator: Pubkey::default(),
+        duration: 86_400,
+        operator: Pubkey::new_unique(),
+        paused: 1,
+        ends_at: 1_700_086_400,
+    }
+}
+
+#[test]
+fn test_farm_account_matches_borsh_layout() {
+    let farm = sample_farm();
+    let encoded = farm.try_to_vec().unwrap();
+    assert_eq!(encoded.len(), FarmAccount::LEN);
+
+    let zero_copy: &FarmAccount = bytemuck::from_bytes(&encoded);
+    assert_eq!(Farm::from(zero_copy), farm);
+    assert_eq!(bytemuck::bytes_of(&FarmAccount::from(&farm)), &encoded[..]);
+}
+
+#[test]
+fn test_farm_account_writes_in_place() {
+    let farm = sample_farm();
+    let key = Pubkey::new_unique();
+    let owner = Pubkey::new_unique();
+    let mut lamports = 0;
+    let mut data = farm.try_to_vec().unwrap();
+    let account = AccountInfo::new(&key, false, true, &mut lamports, &mut data, &owner, false, 0);
+
+    assert_eq!(FarmAccount::load(&account).unwrap().creator, farm.creator);
+    FarmAccount::load_mut(&account).unwrap().enabled = 1;
+
+    let decoded = Farm::try_from_slice(&account.data.borrow()).unwrap();
+    assert_eq!(decoded, Farm { enabled: 1, ..farm });
+}
+
+#[test]
+fn test_farm_account_too_small() {
+    let key = Pubkey::new_unique();
+    let owner = Pubkey::new_unique();
+    let mut lamports = 0;
+    let mut data = sample_farm().try_to_vec().unwrap();
+    data.truncate(FarmAccount::LEN - 1);
+    let account = AccountInfo::new(&key, false, true, &mut lamports, &mut data, &owner, false, 0);
+
+    assert_eq!(FarmAccount::load(&account).err(), Some(ProgramError::AccountDataTooSmall));
+    assert_eq!(FarmAccount::load_mut(&account).err(), Some(ProgramError::AccountDataTooSmall));
+}
+
+#[test]
+fn test_farm_account_checks_header() {
+    let check = |data: &mut Vec<u8>| {
+        let (key, owner, mut lamports) = (Pubkey::new_unique(), Pubkey::new_unique(), 0);
+        let account = AccountInfo::new(&key, false, true, &mut lamports, data, &owner, false, 0);
+        let result = FarmAccount::load(&account).err();
+        assert_eq!(FarmAccount::load_mut(&account).err(), result);
+        result
+    };
+
+    // any other account of the farm size, e.g. farm metadata
+    let uri = "u".repeat(99);
+    let mut data = FarmMetadata::new(Pubkey::new_unique(), 255, "a".repeat(32), String::new(), uri, vec![])
+        .try_to_vec()
+        .unwrap();
+    assert_eq!(data.len(), FarmAccount::LEN);
+    assert_eq!(check(&mut data), Some(ProgramError::InvalidAccountData));
+
+    // an allocated farm Create hasn't run on
+    assert_eq!(check(&mut vec![0; FarmAccount::LEN]), Some(ProgramError::InvalidAccountData));
+
+    // a layout version this program doesn't know
+    let mut data = Farm { version: FARM_VERSION + 1, ..sample_farm() }.try_to_vec().unwrap();
+    assert_eq!(check(&mut data), Some(ProgramError::InvalidAccountData));
+}
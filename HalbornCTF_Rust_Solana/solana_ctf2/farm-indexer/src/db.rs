@@ -0,0 +1,389 @@
+use {
+    crate::decode::{instruction_name, IndexedTransaction},
+    ctf_solana_farm::{
+        constant::{FARM_DISCRIMINATOR, FARM_VERSION},
+        event::FarmEvent,
+        state::Farm,
+    },
+    rusqlite::{params, Connection, OptionalExtension, Transaction},
+    solana_sdk::{pubkey::Pubkey, signature::Signature},
+    std::{path::Path, str::FromStr},
+};
+
+/// tables of the index, created on open
+///
+/// pubkeys and signatures are stored base58 encoded, amounts in token base units
+/// `creator_stats` is derived from `farms` and `fee_payments`, it is recomputed for
+/// every creator a transaction or a farm scan touches
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS farms (
+    address TEXT PRIMARY KEY,
+    creator TEXT NOT NULL,
+    pending_creator TEXT,
+    fee_vault TEXT NOT NULL,
+    token_program_id TEXT NOT NULL,
+    nonce INTEGER NOT NULL,
+    duration INTEGER NOT NULL,
+    enabled INTEGER NOT NULL,
+    operator TEXT,
+    paused INTEGER NOT NULL,
+    ends_at INTEGER NOT NULL
+);
+CREATE INDEX IF NOT EXISTS farms_creator ON farms (creator);
+
+CREATE TABLE IF NOT EXISTS instructions (
+    signature TEXT NOT NULL,
+    position INTEGER NOT NULL,
+    instruction_index INTEGER NOT NULL,
+    is_inner INTEGER NOT NULL,
+    slot INTEGER NOT NULL,
+    block_time INTEGER,
+    success INTEGER NOT NULL,
+    name TEXT,
+    args TEXT,
+    farm TEXT,
+    PRIMARY KEY (signature, position)
+);
+CREATE INDEX IF NOT EXISTS instructions_farm ON instructions (farm);
+
+CREATE TABLE IF NOT EXISTS fee_payments (
+    signature TEXT NOT NULL,
+    event_index INTEGER NOT NULL,
+    slot INTEGER NOT NULL,
+    block_time INTEGER,
+    farm TEXT NOT NULL,
+    creator TEXT NOT NULL,
+    fee_vault TEXT NOT NULL,
+    fee_vault_amount INTEGER NOT NULL,
+    referrer TEXT,
+    referrer_amount INTEGER NOT NULL,
+    PRIMARY KEY (signature, event_index)
+);
+CREATE INDEX IF NOT EXISTS fee_payments_creator ON fee_payments (creator);
+
+CREATE TABLE IF NOT EXISTS creator_stats (
+    creator TEXT PRIMARY KEY,
+    farms INTEGER NOT NULL,
+    enabled_farms INTEGER NOT NULL,
+    fee_payments INTEGER NOT NULL,
+    fees_paid INTEGER NOT NULL,
+    referral_paid INTEGER NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS cursor (
+    program_id TEXT PRIMARY KEY,
+    signature TEXT NOT NULL,
+    slot INTEGER NOT NULL
+);
+";
+
+/// statistics of one creator, as stored in `creator_stats`
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct CreatorStats {
+    pub farms: u64,
+    pub enabled_farms: u64,
+    pub fee_payments: u64,
+    /// fee vault and referrer parts together
+    pub fees_paid: u64,
+    pub referral_paid: u64,
+}
+
+/// the local SQLite index
+pub struct Db {
+    conn: Connection,
+}
+
+impl Db {
+    pub fn open(path: &Path) -> rusqlite::Result<Self> {
+        Self::init(Connection::open(path)?)
+    }
+
+    pub fn open_in_memory() -> rusqlite::Result<Self> {
+        Self::init(Connection::open_in_memory()?)
+    }
+
+    fn init(conn: Connection) -> rusqlite::Result<Self> {
+        conn.execute_batch(SCHEMA)?;
+        Ok(Db { conn })
+    }
+
+    /// for ad hoc queries
+    pub fn connection(&self) -> &Connection {
+        &self.conn
+    }
+
+    /// last transaction of `program_id` that was indexed, tailing resumes after it
+    pub fn cursor(&self, program_id: &Pubkey) -> rusqlite::Result<Option<Signature>> {
+        let signature: Option<String> = self
+            .conn
+            .query_row("SELECT signature FROM cursor WHERE program_id = ?1", [program_id.to_string()], |row| row.get(0))
+            .optional()?;
+        // only this module writes the column
+        Ok(signature.map(|signature| Signature::from_str(&signature).expect("stored signature")))
+    }
+
+    /// stores the farms of a program account scan
+    pub fn upsert_farms(&mut self, farms: &[(Pubkey, Farm)]) -> rusqlite::Result<()> {
+        let tx = self.conn.transaction()?;
+        let mut creators = vec![];
+        for (address, farm) in farms {
+            creators.extend(upsert_farm(&tx, address, farm)?);
+        }
+        refresh_creator_stats(&tx, &creators)?;
+        tx.commit()
+    }
+
+    /// stores a transaction of `program_id` with the current state of the farms it touched,
+    /// and moves the cursor to it, indexing the same transaction twice changes nothing
+    pub fn index_transaction(
+        &mut self,
+        program_id: &Pubkey,
+        transaction: &IndexedTransaction,
+        farms: &[(Pubkey, Farm)],
+    ) -> rusqlite::Result<()> {
+        let tx = self.conn.transaction()?;
+        let signature = transaction.signature.to_string();
+        for instruction in &transaction.instructions {
+            tx.execute(
+                "INSERT OR IGNORE INTO instructions
+                 (signature, position, instruction_index, is_inner, slot, block_time, success, name, args, farm)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+                params![
+                    signature,
+                    instruction.position,
+                    instruction.instruction_index,
+                    instruction.inner,
+                    transaction.slot,
+                    transaction.block_time,
+                    transaction.success,
+                    instruction.instruction.as_ref().map(instruction_name),
+                    instruction.instruction.as_ref().map(|instruction| format!("{:?}", instruction)),
+                    instruction.farm().map(|farm| farm.to_string()),
+                ],
+            )?;
+        }
+
+        let mut creators = vec![];
+        for (event_index, event) in transaction.events.iter().enumerate() {
+            let FarmEvent::FarmFeePaid { farm, creator, fee_vault, fee_vault_amount, referrer, referrer_amount } = event;
+            tx.execute(
+                "INSERT OR IGNORE INTO fee_payments
+                 (signature, event_index, slot, block_time, farm, creator, fee_vault, fee_vault_amount, referrer, referrer_amount)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+                params![
+                    signature,
+                    event_index,
+                    transaction.slot,
+                    transaction.block_time,
+                    farm.to_string(),
+                    creator.to_string(),
+                    fee_vault.to_string(),
+                    fee_vault_amount,
+                    referrer.map(|referrer| referrer.to_string()),
+                    referrer_amount,
+                ],
+            )?;
+            creators.push(*creator);
+        }
+        for (address, farm) in farms {
+            creators.extend(upsert_farm(&tx, address, farm)?);
+        }
+        refresh_creator_stats(&tx, &creators)?;
+
+        tx.execute(
+            "INSERT OR REPLACE INTO cursor (program_id, signature, slot) VALUES (?1, ?2, ?3)",
+            params![program_id.to_string(), signature, transaction.slot],
+        )?;
+        tx.commit()
+    }
+
+    pub fn get_farm(&self, address: &Pubkey) -> rusqlite::Result<Option<Farm>> {
+        self.conn
+            .query_row(
+                "SELECT creator, pending_creator, fee_vault, token_program_id, nonce, duration, enabled, operator, paused, ends_at
+                 FROM farms WHERE address = ?1",
+                [address.to_string()],
+                |row| {
+                    let pending_creator: Option<String> = row.get(1)?;
+                    let operator: Option<String> = row.get(7)?;
+                    Ok(Farm {
+                        discriminator: FARM_DISCRIMINATOR,
+                        version: FARM_VERSION,
+                        creator: parse_pubkey(row.get(0)?),
+                        pending_creator: pending_creator.map(parse_pubkey).unwrap_or_default(),
+                        fee_vault: parse_pubkey(row.get(2)?),
+                        token_program_id: parse_pubkey(row.get(3)?),
+                        nonce: row.get(4)?,
+                        duration: row.get(5)?,
+                        enabled: row.get(6)?,
+                        operator: operator.map(parse_pubkey).unwrap_or_default(),
+                        paused: row.get(8)?,
+                        ends_at: row.get(9)?,
+                    })
+                },
+            )
+            .optional()
+    }
+
+    pub fn get_creator_stats(&self, creator: &Pubkey) -> rusqlite::Result<Option<CreatorStats>> {
+        self.conn
+            .query_row(
+                "SELECT farms, enabled_farms, fee_payments, fees_paid, referral_paid
+                 FROM creator_stats WHERE creator = ?1",
+                [creator.to_string()],
+                |row| {
+                    Ok(CreatorStats {
+                        farms: row.get(0)?,
+                        enabled_farms: row.get(1)?,
+                        fee_payments: row.get(2)?,
+                        fees_paid: row.get(3)?,
+                        referral_paid: row.get(4)?,
+                    })
+                },
+            )
+            .optional()
+    }
+}
+
+// only this module writes pubkey columns
+fn parse_pubkey(address: String) -> Pubkey {
+    Pubkey::from_str(&address).expect("stored pubkey")
+}
+
+/// stores `farm`, returns its creator and the previous one when the creator changed
+fn upsert_farm(tx: &Transaction, address: &Pubkey, farm: &Farm) -> rusqlite::Result<Vec<Pubkey>> {
+    let previous: Option<String> = tx
+        .query_row("SELECT creator FROM farms WHERE address = ?1", [address.to_string()], |row| row.get(0))
+        .optional()?;
+    tx.execute(
+        "INSERT OR REPLACE INTO farms
+         (address, creator, pending_creator, fee_vault, token_program_id, nonce, duration, enabled, operator, paused, ends_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
+        params![
+            address.to_string(),
+            farm.creator.to_string(),
+            Some(farm.pending_creator).filter(|pending| *pending != Pubkey::default()).map(|pending| pending.to_string()),
+            farm.fee_vault.to_string(),
+            farm.token_program_id.to_string(),
+            farm.nonce,
+            farm.duration,
+            farm.enabled,
+            Some(farm.operator).filter(|operator| *operator != Pubkey::default()).map(|operator| operator.to_string()),
+            farm.paused,
+            farm.ends_at,
+        ],
+    )?;
+    let mut creators = vec![farm.creator];
+    creators.extend(previous.map(parse_pubkey).filter(|previous| *previous != farm.creator));
+    Ok(creators)
+}
+
+fn refresh_creator_stats(tx: &Transaction, creators: &[Pubkey]) -> rusqlite::Result<()> {
+    let mut statement = tx.prepare_cached(
+        "INSERT OR REPLACE INTO creator_stats (creator, farms, enabled_farms, fee_payments, fees_paid, referral_paid)
+         SELECT ?1,
+             (SELECT COUNT(*) FROM farms WHERE creator = ?1),
+             (SELECT COUNT(*) FROM farms WHERE creator = ?1 AND enabled = 1),
+             COUNT(*),
+             COALESCE(SUM(fee_vault_amount + referrer_amount), 0),
+             COALESCE(SUM(referrer_amount), 0)
+         FROM fee_payments WHERE creator = ?1",
+    )?;
+    for creator in creators {
+        statement.execute([creator.to_string()])?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, crate::decode::IndexedInstruction, ctf_solana_farm::instruction::FarmInstruction};
+
+    fn farm(creator: Pubkey, enabled: u8) -> Farm {
+        Farm {
+            discriminator: FARM_DISCRIMINATOR,
+            version: FARM_VERSION,
+            enabled,
+            nonce: 254,
+            token_program_id: Pubkey::new_unique(),
+            creator,
+            fee_vault: Pubkey::new_unique(),
+            pending_creator: Pubkey::default(),
+            duration: 86_400,
+            operator: Pubkey::default(),
+            paused: 0,
+            ends_at: 0,
+        }
+    }
+
+    fn fee_paid(farm: Pubkey, creator: Pubkey, referrer: Option<Pubkey>) -> IndexedTransaction {
+        IndexedTransaction {
+            signature: Signature::new_unique(),
+            slot: 10,
+            block_time: Some(1_700_000_000),
+            success: true,
+            instructions: vec![IndexedInstruction {
+                position: 1,
+                instruction_index: 1,
+                inner: false,
+                instruction: Some(FarmInstruction::PayFarmFee(5_000)),
+                accounts: vec![farm, Pubkey::new_unique(), creator],
+            }],
+            events: vec![FarmEvent::FarmFeePaid {
+                farm,
+                creator,
+                fee_vault: Pubkey::new_unique(),
+                fee_vault_amount: if referrer.is_some() { 4_500 } else { 5_000 },
+                referrer,
+                referrer_amount: if referrer.is_some() { 500 } else { 0 },
+            }],
+        }
+    }
+
+    #[test]
+    fn test_index_fee_payments() {
+        let mut db = Db::open_in_memory().unwrap();
+        let program_id = Pubkey::new_unique();
+        let creator = Pubkey::new_unique();
+        let (first, second) = (Pubkey::new_unique(), Pubkey::new_unique());
+        db.upsert_farms(&[(first, farm(creator, 0)), (second, farm(creator, 0))]).unwrap();
+        assert_eq!(db.get_creator_stats(&creator).unwrap().unwrap().farms, 2);
+        assert_eq!(db.cursor(&program_id).unwrap(), None);
+
+        let paid = fee_paid(first, creator, None);
+        db.index_transaction(&program_id, &paid, &[(first, farm(creator, 1))]).unwrap();
+        // indexing again after a restart doesn't count the payment twice
+        db.index_transaction(&program_id, &paid, &[(first, farm(creator, 1))]).unwrap();
+        let referred = fee_paid(second, creator, Some(Pubkey::new_unique()));
+        db.index_transaction(&program_id, &referred, &[(second, farm(creator, 1))]).unwrap();
+
+        assert_eq!(
+            db.get_creator_stats(&creator).unwrap().unwrap(),
+            CreatorStats { farms: 2, enabled_farms: 2, fee_payments: 2, fees_paid: 10_000, referral_paid: 500 }
+        );
+        assert_eq!(db.cursor(&program_id).unwrap(), Some(referred.signature));
+        let name: String = db
+            .connection()
+            .query_row("SELECT name FROM instructions WHERE farm = ?1", [first.to_string()], |row| row.get(0))
+            .unwrap();
+        assert_eq!(name, "PayFarmFee");
+    }
+
+    #[test]
+    fn test_creator_transfer_moves_stats() {
+        let mut db = Db::open_in_memory().unwrap();
+        let (creator, new_creator, address) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+        let mut proposed = farm(creator, 1);
+        proposed.pending_creator = new_creator;
+        proposed.operator = Pubkey::new_unique();
+        proposed.paused = 1;
+        db.upsert_farms(&[(address, proposed.clone())]).unwrap();
+        assert_eq!(db.get_farm(&address).unwrap(), Some(proposed.clone()));
+
+        let accepted = Farm { creator: new_creator, pending_creator: Pubkey::default(), ..proposed };
+        db.upsert_farms(&[(address, accepted.clone())]).unwrap();
+        assert_eq!(db.get_farm(&address).unwrap(), Some(accepted));
+        assert_eq!(db.get_creator_stats(&creator).unwrap().unwrap().farms, 0);
+        assert_eq!(db.get_creator_stats(&new_creator).unwrap().unwrap().enabled_farms, 1);
+    }
+}
@@ -0,0 +1,255 @@
+use {
+    crate::IndexerError,
+    base64::{engine::general_purpose::STANDARD as BASE64, Engine},
+    borsh::BorshDeserialize,
+    ctf_solana_farm::{event::FarmEvent, instruction::FarmInstruction},
+    solana_sdk::{
+        clock::{Slot, UnixTimestamp},
+        pubkey::Pubkey,
+        signature::Signature,
+    },
+    solana_transaction_status::{
+        option_serializer::OptionSerializer, EncodedConfirmedTransactionWithStatusMeta, UiInstruction,
+    },
+    std::str::FromStr,
+};
+
+/// one invocation of the farm program, top level or through a cross-program invocation
+#[derive(Clone, Debug, PartialEq)]
+pub struct IndexedInstruction {
+    /// order of the invocation in the transaction, counting every instruction of any program
+    pub position: u32,
+    /// top level instruction the invocation belongs to
+    pub instruction_index: u8,
+    pub inner: bool,
+    /// None when the data isn't a `FarmInstruction`, the program rejects those
+    pub instruction: Option<FarmInstruction>,
+    pub accounts: Vec<Pubkey>,
+}
+
+impl IndexedInstruction {
+    /// farm the instruction acts on
+    pub fn farm(&self) -> Option<Pubkey> {
+        let index = self.instruction.as_ref()?.farm_account_index()?;
+        self.accounts.get(index).copied()
+    }
+}
+
+/// what the indexer keeps of a transaction that invoked the farm program
+#[derive(Clone, Debug, PartialEq)]
+pub struct IndexedTransaction {
+    pub signature: Signature,
+    pub slot: Slot,
+    pub block_time: Option<UnixTimestamp>,
+    pub success: bool,
+    pub instructions: Vec<IndexedInstruction>,
+    /// events of a failed transaction are dropped, its state changes were rolled back
+    pub events: Vec<FarmEvent>,
+}
+
+impl IndexedTransaction {
+    /// farms the transaction acted on, without duplicates
+    pub fn farms(&self) -> Vec<Pubkey> {
+        let mut farms: Vec<Pubkey> = vec![];
+        for farm in self.instructions.iter().filter_map(IndexedInstruction::farm) {
+            if !farms.contains(&farm) {
+                farms.push(farm);
+            }
+        }
+        farms
+    }
+}
+
+/// name of the instruction variant, stored next to its arguments
+pub fn instruction_name(instruction: &FarmInstruction) -> &'static str {
+    match instruction {
+        FarmInstruction::Create { .. } => "Create",
+        FarmInstruction::PayFarmFee(_) => "PayFarmFee",
+        FarmInstruction::InitializeConfig => "InitializeConfig",
+        FarmInstruction::SetPaused(_) => "SetPaused",
+        FarmInstruction::TransferAdmin(_) => "TransferAdmin",
+        FarmInstruction::ProposeCreator(_) => "ProposeCreator",
+        FarmInstruction::AcceptCreator => "AcceptCreator",
+        FarmInstruction::SetReferralShare(_) => "SetReferralShare",
+        FarmInstruction::SetFeeSchedule { .. } => "SetFeeSchedule",
+        FarmInstruction::SetFarmMetadata { .. } => "SetFarmMetadata",
+        FarmInstruction::UpdateFarmMetadata { .. } => "UpdateFarmMetadata",
+        FarmInstruction::SetOperator(_) => "SetOperator",
+        FarmInstruction::RevokeOperator => "RevokeOperator",
+        FarmInstruction::SetFarmPaused(_) => "SetFarmPaused",
+    }
+}
+
+/// events the farm program logged as `Program data:`, in order
+/// follows the invocation stack so data logged by other programs is skipped
+pub fn farm_events(program_id: &Pubkey, logs: &[String]) -> Vec<FarmEvent> {
+    let mut events = vec![];
+    let mut stack: Vec<Pubkey> = vec![];
+    for line in logs {
+        if let Some(data) = line.strip_prefix("Program data: ") {
+            if stack.last() == Some(program_id) {
+                // sol_log_data writes one base64 field per slice, the program logs a single one
+                let event = data
+                    .split(' ')
+                    .next()
+                    .and_then(|field| BASE64.decode(field).ok())
+                    .and_then(|bytes| FarmEvent::try_from_slice(&bytes).ok());
+                events.extend(event);
+            }
+            continue;
+        }
+        let mut words = match line.strip_prefix("Program ") {
+            Some(rest) => rest.splitn(2, ' '),
+            None => continue,
+        };
+        let (invoked, rest) = match (words.next().map(Pubkey::from_str), words.next()) {
+            (Some(Ok(invoked)), Some(rest)) => (invoked, rest),
+            _ => continue,
+        };
+        if rest.starts_with("invoke [") {
+            stack.push(invoked);
+        } else if rest == "success" || rest.starts_with("failed: ") {
+            stack.pop();
+        }
+    }
+    events
+}
+
+fn parse_pubkey(signature: &Signature, address: &str) -> Result<Pubkey, IndexerError> {
+    Pubkey::from_str(address).map_err(|_| IndexerError::InvalidTransaction(*signature, "invalid loaded address"))
+}
+
+/// farm program invocations and events of a transaction fetched with a binary encoding
+pub fn decode_transaction(
+    program_id: &Pubkey,
+    confirmed: &EncodedConfirmedTransactionWithStatusMeta,
+) -> Result<IndexedTransaction, IndexerError> {
+    let transaction = confirmed
+        .transaction
+        .transaction
+        .decode()
+        .ok_or(IndexerError::UndecodableTransaction(confirmed.slot))?;
+    let signature = transaction.signatures[0];
+    let meta = confirmed
+        .transaction
+        .meta
+        .as_ref()
+        .ok_or(IndexerError::InvalidTransaction(signature, "no status meta"))?;
+
+    // addresses from lookup tables follow the static keys, writable first
+    let mut account_keys = transaction.message.static_account_keys().to_vec();
+    if let OptionSerializer::Some(loaded) = &meta.loaded_addresses {
+        for address in loaded.writable.iter().chain(&loaded.readonly) {
+            account_keys.push(parse_pubkey(&signature, address)?);
+        }
+    }
+    let inner_instructions = match &meta.inner_instructions {
+        OptionSerializer::Some(inner_instructions) => inner_instructions.as_slice(),
+        _ => &[],
+    };
+
+    let mut instructions = vec![];
+    let mut position = 0;
+    let mut push = |instruction_index: u8, inner: bool, program_id_index: u8, accounts: &[u8], data: &[u8]| {
+        let key = |index: &u8| account_keys.get(*index as usize).copied();
+        if key(&program_id_index).as_ref() == Some(program_id) {
+            instructions.push(IndexedInstruction {
+                position,
+                instruction_index,
+                inner,
+                instruction: FarmInstruction::try_from_slice(data).ok(),
+                accounts: accounts.iter().filter_map(key).collect(),
+            });
+        }
+        position += 1;
+    };
+    for (index, instruction) in transaction.message.instructions().iter().enumerate() {
+        let index = index as u8;
+        push(index, false, instruction.program_id_index, &instruction.accounts, &instruction.data);
+        for inner in inner_instructions.iter().filter(|inner| inner.index == index) {
+            for instruction in &inner.instructions {
+                // binary encodings never parse inner instructions
+                if let UiInstruction::Compiled(instruction) = instruction {
+                    let data = bs58::decode(&instruction.data)
+                        .into_vec()
+                        .map_err(|_| IndexerError::InvalidTransaction(signature, "invalid inner instruction data"))?;
+                    push(index, true, instruction.program_id_index, &instruction.accounts, &data);
+                }
+            }
+        }
+    }
+
+    let success = meta.err.is_none();
+    let events = match &meta.log_messages {
+        OptionSerializer::Some(logs) if success => farm_events(program_id, logs),
+        _ => vec![],
+    };
+    Ok(IndexedTransaction {
+        signature,
+        slot: confirmed.slot,
+        block_time: confirmed.block_time,
+        success,
+        instructions,
+        events,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, borsh::BorshSerialize};
+
+    fn logs(lines: &[&str]) -> Vec<String> {
+        lines.iter().map(|line| line.to_string()).collect()
+    }
+
+    #[test]
+    fn test_farm_events_skip_other_programs() {
+        let program_id = Pubkey::new_unique();
+        let event = FarmEvent::FarmFeePaid {
+            farm: Pubkey::new_unique(),
+            creator: Pubkey::new_unique(),
+            fee_vault: Pubkey::new_unique(),
+            fee_vault_amount: 4_500,
+            referrer: Some(Pubkey::new_unique()),
+            referrer_amount: 500,
+        };
+        let data = format!("Program data: {}", BASE64.encode(event.try_to_vec().unwrap()));
+        let other = format!("Program data: {}", BASE64.encode([1, 2, 3]));
+        let invoke = format!("Program {} invoke [1]", program_id);
+        let success = format!("Program {} success", program_id);
+        // data the invoked program logs isn't a farm event
+        let token_program_id = Pubkey::new_unique();
+        let token_invoke = format!("Program {} invoke [2]", token_program_id);
+        let token_success = format!("Program {} success", token_program_id);
+
+        let logs = logs(&[
+            &invoke,
+            &token_invoke,
+            &other,
+            &token_success,
+            &data,
+            &format!("Program {} consumed 1200 of 200000 compute units", program_id),
+            &success,
+            &other,
+        ]);
+        assert_eq!(farm_events(&program_id, &logs), vec![event]);
+        assert!(farm_events(&Pubkey::new_unique(), &logs).is_empty());
+    }
+
+    #[test]
+    fn test_farm_of_instruction() {
+        let farm = Pubkey::new_unique();
+        let mut instruction = IndexedInstruction {
+            position: 0,
+            instruction_index: 0,
+            inner: false,
+            instruction: Some(FarmInstruction::AcceptCreator),
+            accounts: vec![farm, Pubkey::new_unique()],
+        };
+        assert_eq!(instruction.farm(), Some(farm));
+        instruction.instruction = Some(FarmInstruction::SetPaused(true));
+        assert_eq!(instruction.farm(), None);
+        instruction.instruction = None;
+        assert_eq!(instruction.farm(), None);
+    }
+}
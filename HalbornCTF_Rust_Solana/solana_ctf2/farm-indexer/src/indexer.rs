@@ -0,0 +1,87 @@
+use {
+    crate::{db::Db, decode::decode_transaction, IndexerError},
+    ctf_solana_farm::{
+        client::{get_signatures_since, history_commitment, FarmClient, FarmClientError},
+        state::Farm,
+    },
+    solana_client::{nonblocking::rpc_client::RpcClient, rpc_config::RpcTransactionConfig},
+    solana_sdk::{pubkey::Pubkey, signature::Signature},
+    solana_transaction_status::UiTransactionEncoding,
+    std::{str::FromStr, time::Duration},
+};
+
+/// copies the farms and the transaction history of the farm program into a `Db`
+pub struct Indexer {
+    client: FarmClient<RpcClient>,
+    db: Db,
+}
+
+impl Indexer {
+    pub fn new(client: FarmClient<RpcClient>, db: Db) -> Self {
+        Indexer { client, db }
+    }
+
+    pub fn db(&self) -> &Db {
+        &self.db
+    }
+
+    /// stores every farm of the program, returns how many there are
+    pub async fn load_farms(&mut self) -> Result<usize, IndexerError> {
+        let farms = self.client.get_farms().await?;
+        self.db.upsert_farms(&farms)?;
+        Ok(farms.len())
+    }
+
+    /// indexes the transactions of the program since the cursor, oldest first,
+    /// returns how many were indexed
+    pub async fn tail(&mut self) -> Result<usize, IndexerError> {
+        let program_id = *self.client.program_id();
+        let mut statuses =
+            get_signatures_since(self.client.connection(), &program_id, self.db.cursor(&program_id)?).await?;
+        statuses.reverse();
+        let config = RpcTransactionConfig {
+            encoding: Some(UiTransactionEncoding::Base64),
+            commitment: Some(history_commitment(self.client.connection().commitment())),
+            max_supported_transaction_version: Some(0),
+        };
+        for status in &statuses {
+            let signature = Signature::from_str(&status.signature)
+                .map_err(|_| IndexerError::InvalidSignature(status.signature.clone()))?;
+            let confirmed = self
+                .client
+                .connection()
+                .get_transaction_with_config(&signature, config)
+                .await
+                .map_err(FarmClientError::from)?;
+            let transaction = decode_transaction(&program_id, &confirmed)?;
+            let mut farms = vec![];
+            for farm_id in transaction.farms() {
+                farms.extend(self.current_farm(&farm_id).await?.map(|farm| (farm_id, farm)));
+            }
+            self.db.index_transaction(&program_id, &transaction, &farms)?;
+        }
+        Ok(statuses.len())
+    }
+
+    /// None when `farm_id` doesn't hold a farm, a failed Create leaves it that way
+    async fn current_farm(&self, farm_id: &Pubkey) -> Result<Option<Farm>, IndexerError> {
+        match self.client.get_farm(farm_id).await {
+            Ok(farm) => Ok(Some(farm)),
+            Err(FarmClientError::AccountNotFound(_) | FarmClientError::InvalidAccountData(_)) => Ok(None),
+            Err(error) => Err(error.into()),
+        }
+    }
+
+    /// loads the farms, then tails the program every `poll_interval`
+    pub async fn run(&mut self, poll_interval: Duration) -> Result<(), IndexerError> {
+        let farms = self.load_farms().await?;
+        eprintln!("Loaded {} farms", farms);
+        loop {
+            let indexed = self.tail().await?;
+            if indexed > 0 {
+                eprintln!("Indexed {} transactions", indexed);
+            }
+            tokio::time::sleep(poll_interval).await;
+        }
+    }
+}
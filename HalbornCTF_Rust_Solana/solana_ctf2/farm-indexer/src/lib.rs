@@ -0,0 +1,30 @@
+//! indexes the farms and the transaction history of the farm program into SQLite
+//!
+//! farms are loaded with getProgramAccounts, then the signatures of the program are tailed and
+//! every transaction is decoded into its farm instructions and `FarmEvent`s
+
+pub mod db;
+pub mod decode;
+pub mod indexer;
+
+use {
+    ctf_solana_farm::client::FarmClientError,
+    solana_sdk::{clock::Slot, signature::Signature},
+    thiserror::Error,
+};
+
+pub use {db::Db, indexer::Indexer};
+
+#[derive(Debug, Error)]
+pub enum IndexerError {
+    #[error(transparent)]
+    Client(#[from] FarmClientError),
+    #[error("sqlite: {0}")]
+    Db(#[from] rusqlite::Error),
+    #[error("invalid signature {0}")]
+    InvalidSignature(String),
+    #[error("can't decode the transaction in slot {0}")]
+    UndecodableTransaction(Slot),
+    #[error("transaction {0}: {1}")]
+    InvalidTransaction(Signature, &'static str),
+}
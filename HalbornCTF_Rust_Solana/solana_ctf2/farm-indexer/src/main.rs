@@ -0,0 +1,61 @@
+use {
+    clap::Parser,
+    ctf_solana_farm::client::FarmClient,
+    farm_indexer::{Db, Indexer},
+    solana_client::nonblocking::rpc_client::RpcClient,
+    solana_sdk::{commitment_config::CommitmentConfig, pubkey::Pubkey},
+    std::{error::Error, path::PathBuf, str::FromStr, time::Duration},
+};
+
+/// indexes the farms of the farm program into a SQLite database
+#[derive(Debug, Parser)]
+#[command(name = "farm-indexer", version)]
+struct Cli {
+    /// JSON RPC url of the cluster
+    #[arg(long, short = 'u', env = "FARM_RPC_URL", default_value = "http://127.0.0.1:8899")]
+    url: String,
+
+    /// farm program id
+    #[arg(long, env = "FARM_PROGRAM_ID", default_value = "x5i6Ec4fuLkEBB9cr7PRRndzLk4uPFkskDzWnPeyXHi")]
+    program_id: Pubkey,
+
+    /// confirmed or finalized, transaction history isn't served at processed
+    #[arg(long, env = "FARM_COMMITMENT", default_value = "confirmed")]
+    commitment: String,
+
+    /// SQLite database, created when missing
+    #[arg(long, env = "FARM_INDEXER_DB", default_value = "farms.sqlite")]
+    db: PathBuf,
+
+    /// seconds between two polls for new transactions
+    #[arg(long, default_value_t = 5)]
+    poll_interval: u64,
+
+    /// index what is there and exit instead of tailing
+    #[arg(long)]
+    once: bool,
+}
+
+#[tokio::main]
+async fn main() {
+    // Display, not the Debug output of returning the error, so failures read as sentences
+    if let Err(err) = run(Cli::parse()).await {
+        eprintln!("Error: {}", err);
+        std::process::exit(1);
+    }
+}
+
+async fn run(cli: Cli) -> Result<(), Box<dyn Error>> {
+    let commitment = CommitmentConfig::from_str(&cli.commitment)
+        .map_err(|err| format!("invalid commitment {}: {}", cli.commitment, err))?;
+    let client = FarmClient::new(cli.program_id, RpcClient::new_with_commitment(cli.url, commitment));
+    let mut indexer = Indexer::new(client, Db::open(&cli.db)?);
+    if cli.once {
+        let farms = indexer.load_farms().await?;
+        let indexed = indexer.tail().await?;
+        eprintln!("Loaded {} farms, indexed {} transactions into {}", farms, indexed, cli.db.display());
+        return Ok(());
+    }
+    indexer.run(Duration::from_secs(cli.poll_interval)).await?;
+    Ok(())
+}
@@ -0,0 +1,133 @@
+use {
+    ctf_solana_farm::{
+        client::{find_farm_authority, CreateFarm, FarmClient, InitializeConfig, SetFeeSchedule},
+        constant::FARM_FEE,
+        state::{FarmAccount, FeeMint},
+    },
+    farm_indexer::{db::CreatorStats, Db, Indexer},
+    solana_client::nonblocking::rpc_client::RpcClient,
+    solana_sdk::{
+        bpf_loader_upgradeable,
+        commitment_config::CommitmentConfig,
+        instruction::Instruction,
+        program_pack::Pack,
+        pubkey::Pubkey,
+        signature::{Keypair, Signer},
+        system_instruction,
+    },
+    solana_validator::test_validator::{TestValidatorGenesis, UpgradeableProgramInfo},
+    spl_token::state::{Account as TokenAccount, Mint},
+    std::{path::PathBuf, str::FromStr},
+};
+
+/// allocates an account of `space` bytes owned by `owner`
+async fn create_account(
+    client: &FarmClient<RpcClient>,
+    payer: &Keypair,
+    account: &Keypair,
+    space: usize,
+    owner: &Pubkey,
+) -> Instruction {
+    let lamports = client.connection().get_minimum_balance_for_rent_exemption(space).await.unwrap();
+    system_instruction::create_account(&payer.pubkey(), &account.pubkey(), lamports, space as u64, owner)
+}
+
+#[test]
+fn test_index_farm_history() {
+    let program_id = Pubkey::from_str("8gBxX2ZXm9E5eiyHfTrewq7JGpuhMxsedHzh5fpJMMRw").unwrap();
+    let upgrade_authority = Keypair::new();
+    let mut genesis = TestValidatorGenesis::default();
+    genesis.add_upgradeable_programs_with_path(&[UpgradeableProgramInfo {
+        program_id,
+        loader: bpf_loader_upgradeable::id(),
+        upgrade_authority: upgrade_authority.pubkey(),
+        program_path: PathBuf::from("../ctf/target/deploy/ctf_solana_farm.so"),
+    }]);
+    let (test_validator, payer) = genesis.start();
+
+    // the validator runs its own runtime, the indexer gets another one
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    runtime.block_on(async {
+        let rpc = || RpcClient::new_with_commitment(test_validator.rpc_url(), CommitmentConfig::confirmed());
+        let client = FarmClient::new(program_id, rpc());
+        let (mint, creator_token_account, farm, fee_vault) =
+            (Keypair::new(), Keypair::new(), Keypair::new(), Keypair::new());
+        let (authority, _) = find_farm_authority(&farm.pubkey(), &program_id);
+
+        let setup = [
+            create_account(&client, &payer, &mint, Mint::LEN, &spl_token::id()).await,
+            spl_token::instruction::initialize_mint2(&spl_token::id(), &mint.pubkey(), &payer.pubkey(), None, 6).unwrap(),
+            create_account(&client, &payer, &creator_token_account, TokenAccount::LEN, &spl_token::id()).await,
+            spl_token::instruction::initialize_account3(
+                &spl_token::id(),
+                &creator_token_account.pubkey(),
+                &mint.pubkey(),
+                &payer.pubkey(),
+            )
+            .unwrap(),
+            spl_token::instruction::mint_to(
+                &spl_token::id(),
+                &mint.pubkey(),
+                &creator_token_account.pubkey(),
+                &payer.pubkey(),
+                &[],
+                FARM_FEE,
+            )
+            .unwrap(),
+        ];
+        client.send(&payer, &setup, &[&mint, &creator_token_account]).await.unwrap();
+
+        let admin = payer.pubkey();
+        let fee_mints = vec![FeeMint { mint: mint.pubkey(), amount: FARM_FEE }];
+        let initialize_config = InitializeConfig { admin, upgrade_authority: upgrade_authority.pubkey() };
+        client.send(&payer, &[initialize_config.instruction(&program_id)], &[&upgrade_authority]).await.unwrap();
+        client
+            .send(&payer, &[SetFeeSchedule { admin, tiers: vec![], mints: fee_mints }.instruction(&program_id)], &[])
+            .await
+            .unwrap();
+
+        // the payer creates the farm, so it is the creator the stats are kept for
+        let create = [
+            create_account(&client, &payer, &farm, FarmAccount::LEN, &program_id).await,
+            create_account(&client, &payer, &fee_vault, TokenAccount::LEN, &spl_token::id()).await,
+            spl_token::instruction::initialize_account3(&spl_token::id(), &fee_vault.pubkey(), &mint.pubkey(), &authority)
+                .unwrap(),
+            CreateFarm::new(farm.pubkey(), payer.pubkey(), fee_vault.pubkey(), 86_400).instruction(&program_id),
+        ];
+        client.send(&payer, &create, &[&farm, &fee_vault]).await.unwrap();
+
+        let mut indexer = Indexer::new(FarmClient::new(program_id, rpc()), Db::open_in_memory().unwrap());
+        assert_eq!(indexer.load_farms().await.unwrap(), 1);
+        // InitializeConfig, SetFeeSchedule and Create, the token setup doesn't invoke the program
+        assert_eq!(indexer.tail().await.unwrap(), 3);
+        let indexed = indexer.db().get_farm(&farm.pubkey()).unwrap().unwrap();
+        assert_eq!((indexed.creator, indexed.enabled), (payer.pubkey(), 0));
+        assert_eq!(
+            indexer.db().get_creator_stats(&payer.pubkey()).unwrap().unwrap(),
+            CreatorStats { farms: 1, ..CreatorStats::default() }
+        );
+
+        client
+            .send_pay_fee(&farm.pubkey(), &payer, &payer, &creator_token_account.pubkey(), None, FARM_FEE)
+            .await
+            .unwrap();
+        assert_eq!(indexer.tail().await.unwrap(), 1);
+        assert_eq!(indexer.tail().await.unwrap(), 0);
+        assert_eq!(indexer.db().get_farm(&farm.pubkey()).unwrap().unwrap().enabled, 1);
+        assert_eq!(
+            indexer.db().get_creator_stats(&payer.pubkey()).unwrap().unwrap(),
+            CreatorStats { farms: 1, enabled_farms: 1, fee_payments: 1, fees_paid: FARM_FEE, referral_paid: 0 }
+        );
+
+        let names: Vec<String> = indexer
+            .db()
+            .connection()
+            .prepare("SELECT name FROM instructions WHERE farm = ?1 ORDER BY slot")
+            .unwrap()
+            .query_map([farm.pubkey().to_string()], |row| row.get(0))
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(names, vec!["Create", "PayFarmFee"]);
+    });
+}